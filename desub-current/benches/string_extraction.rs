@@ -0,0 +1,44 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compare pulling strings out of a large composite value by deserializing from an owned
+//! value (which copies each string) against deserializing from a borrowed one (which can
+//! hand out `&str`s pointing into the value).
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use desub_current::value::Value;
+use serde::Deserialize;
+
+fn strings(n: usize) -> Value<()> {
+	Value::unnamed_composite((0..n).map(|i| Value::str(format!("this is string number {}", i))).collect())
+}
+
+fn string_extraction(c: &mut Criterion) {
+	let value = strings(10_000);
+	let mut group = c.benchmark_group("string_extraction");
+
+	// Cloning the value isn't what we're interested in, so it's done outside of the measurement:
+	group.bench_function("owned into String", |b| {
+		b.iter_batched(|| value.clone(), |value| <Vec<String>>::deserialize(value).unwrap(), BatchSize::LargeInput)
+	});
+	group.bench_function("borrowed into String", |b| b.iter(|| <Vec<String>>::deserialize(black_box(&value)).unwrap()));
+	group.bench_function("borrowed into &str", |b| b.iter(|| <Vec<&str>>::deserialize(black_box(&value)).unwrap()));
+
+	group.finish();
+}
+
+criterion_group!(benches, string_extraction);
+criterion_main!(benches);
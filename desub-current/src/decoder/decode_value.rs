@@ -16,11 +16,12 @@
 
 use crate::value::{BitSequence, Composite, Primitive, Value, ValueDef, Variant};
 use crate::{Type, TypeId};
-use codec::{Compact, Decode};
+use codec::{Compact, CompactLen, Decode};
 use scale_info::{
 	form::PortableForm, Field, PortableRegistry, TypeDefArray, TypeDefBitSequence, TypeDefCompact, TypeDefComposite,
 	TypeDefPrimitive, TypeDefSequence, TypeDefTuple, TypeDefVariant,
 };
+use std::ops::Range;
 
 // This is used in several places below.
 type TypeDef = scale_info::TypeDef<PortableForm>;
@@ -34,75 +35,328 @@ pub enum DecodeValueError {
 	#[error("Cannot find type with ID {0}")]
 	TypeIdNotFound(u32),
 	#[error("Ran out of data during decoding")]
-	Eof,
-	#[error("Could not find variant with index {0} in {1:?}")]
-	VariantNotFound(u8, scale_info::TypeDefVariant<PortableForm>),
+	UnexpectedEof,
+	#[error("Variant index {index} is not valid; the highest variant index is {max}")]
+	InvalidVariantIndex { index: u8, max: u8 },
+	#[error("Variant index {0} is not valid; the enum has no variants")]
+	NoVariants(u8),
+	#[error("Could not decode a compact encoded value")]
+	InvalidCompact,
 	#[error("Could not decode compact encoded type into {0:?}")]
 	CannotDecodeCompactIntoType(Type),
+	#[error("Values are nested more than {0} levels deep")]
+	MaxDepthExceeded(usize),
+	#[error("Invalid hex string: {0}")]
+	InvalidHex(#[from] hex::FromHexError),
+	#[error("{0} bytes were left over after decoding")]
+	ExcessBytes(usize),
+	#[error("Failed to decode the type with ID {ty} at byte offset {offset}: {reason}")]
+	At { offset: usize, ty: u32, reason: Box<DecodeValueError> },
 }
 
+impl DecodeValueError {
+	/// The reason that decoding failed, ignoring where it failed.
+	pub fn reason(&self) -> &DecodeValueError {
+		match self {
+			DecodeValueError::At { reason, .. } => reason,
+			other => other,
+		}
+	}
+	/// The offset into the input bytes at which decoding failed, if known. This is relative
+	/// to the position of the input when decoding began.
+	pub fn offset(&self) -> Option<usize> {
+		match self {
+			DecodeValueError::At { offset, .. } => Some(*offset),
+			_ => None,
+		}
+	}
+	/// The ID of the type that was being decoded when decoding failed, if known.
+	pub fn type_id(&self) -> Option<u32> {
+		match self {
+			DecodeValueError::At { ty, .. } => Some(*ty),
+			_ => None,
+		}
+	}
+	// Record where the error happened, unless this has already been done further down.
+	fn at(self, offset: usize, ty: u32) -> DecodeValueError {
+		match self {
+			DecodeValueError::At { .. } => self,
+			reason => DecodeValueError::At { offset, ty, reason: Box::new(reason) },
+		}
+	}
+}
+
+/// The maximum depth that values will be decoded to by default. This guards against
+/// blowing the stack when decoding very deeply nested (or self referential) types.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
 /// Decode data according to the [`TypeId`] provided.
 /// The provided pointer to the data slice will be moved forwards as needed
-/// depending on what was decoded.
+/// depending on what was decoded. Values nested more than [`DEFAULT_MAX_DEPTH`]
+/// levels deep will fail to decode. If decoding fails, the error records where
+/// (see [`DecodeValueError::offset`]) and why (see [`DecodeValueError::reason`]).
 pub fn decode_value_by_id<Id: Into<TypeId>>(
 	data: &mut &[u8],
 	ty_id: Id,
 	types: &PortableRegistry,
 ) -> Result<Value<TypeId>, DecodeValueError> {
+	decode_value_with_max_depth(data, ty_id, types, DEFAULT_MAX_DEPTH)
+}
+
+/// Decode data according to the [`TypeId`] provided, in the same way as [`decode_value_by_id`], but
+/// failing with [`DecodeValueError::MaxDepthExceeded`] if values are nested more than `max_depth` levels
+/// deep (a value with nothing nested inside it has a depth of 1; see [`Value::depth`]).
+pub fn decode_value_with_max_depth<Id: Into<TypeId>>(
+	data: &mut &[u8],
+	ty_id: Id,
+	types: &PortableRegistry,
+	max_depth: usize,
+) -> Result<Value<TypeId>, DecodeValueError> {
+	let state = State::new(max_depth, data);
+	decode_value(data, ty_id, types, state)
+}
+
+/// Decode data according to the [`TypeId`] provided, giving each value the range of bytes in `data`
+/// that it was decoded from as its context. See [`Value::raw_bytes`].
+pub fn decode_value_spanned<Id: Into<TypeId>>(
+	data: &[u8],
+	ty_id: Id,
+	types: &PortableRegistry,
+) -> Result<Value<Range<usize>>, DecodeValueError> {
+	let state = State::new(DEFAULT_MAX_DEPTH, data);
+	decode_value(&mut &*data, ty_id, types, state)
+}
+
+/// Decode data according to the [`TypeId`] provided, in the same way as [`decode_value_by_id`], but giving
+/// each value a [`DecodedType`] as its context, which also records whether the value was compact encoded.
+pub fn decode_value_typed<Id: Into<TypeId>>(
+	data: &mut &[u8],
+	ty_id: Id,
+	types: &PortableRegistry,
+) -> Result<Value<DecodedType>, DecodeValueError> {
+	let state = State::new(DEFAULT_MAX_DEPTH, data);
+	decode_value(data, ty_id, types, state)
+}
+
+/// The context given to each value decoded via [`decode_value_typed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodedType {
+	/// The type that the value was decoded as.
+	pub id: TypeId,
+	/// Was the value SCALE compact encoded? This is true for the number in a `Compact<u128>`, as well as for
+	/// any single-field wrapper structs that were compact encoded (and the values nested in them).
+	pub compact: bool,
+}
+
+// Keep track of how deeply nested we are while decoding, and how long the input was,
+// so that we can work out where each value was decoded from.
+#[derive(Debug, Clone, Copy)]
+struct State {
+	depth: usize,
+	max_depth: usize,
+	input_len: usize,
+}
+
+impl State {
+	fn new(max_depth: usize, input: &[u8]) -> State {
+		State { depth: 0, max_depth, input_len: input.len() }
+	}
+	fn nested(self) -> Result<State, DecodeValueError> {
+		if self.depth >= self.max_depth {
+			return Err(DecodeValueError::MaxDepthExceeded(self.max_depth));
+		}
+		Ok(State { depth: self.depth + 1, ..self })
+	}
+	// Given the remaining input, return how far through the input we are.
+	fn offset(&self, remaining: &[u8]) -> usize {
+		self.input_len - remaining.len()
+	}
+	// Given the remaining input before and after decoding something, return the range of the input it was decoded from.
+	fn range(&self, remaining_before: &[u8], remaining_after: &[u8]) -> Range<usize> {
+		(self.input_len - remaining_before.len())..(self.input_len - remaining_after.len())
+	}
+}
+
+// The context that each decoded value is given is built from this.
+trait DecodeContext {
+	fn new(ty_id: TypeId, range: Range<usize>, compact: bool) -> Self;
+}
+
+impl DecodeContext for TypeId {
+	fn new(ty_id: TypeId, _range: Range<usize>, _compact: bool) -> Self {
+		ty_id
+	}
+}
+
+impl DecodeContext for Range<usize> {
+	fn new(_ty_id: TypeId, range: Range<usize>, _compact: bool) -> Self {
+		range
+	}
+}
+
+impl DecodeContext for DecodedType {
+	fn new(id: TypeId, _range: Range<usize>, compact: bool) -> Self {
+		DecodedType { id, compact }
+	}
+}
+
+fn decode_value<C: DecodeContext, Id: Into<TypeId>>(
+	data: &mut &[u8],
+	ty_id: Id,
+	types: &PortableRegistry,
+	state: State,
+) -> Result<Value<C>, DecodeValueError> {
+	let remaining_before = *data;
 	let ty_id = ty_id.into();
+	let (value, compact) =
+		decode_value_def(data, ty_id, types, state).map_err(|e| e.at(state.offset(data), ty_id.id()))?;
+
+	let context = C::new(ty_id, state.range(remaining_before, data), compact);
+	Ok(Value { value, context })
+}
+
+fn decode_value_def<C: DecodeContext>(
+	data: &mut &[u8],
+	ty_id: TypeId,
+	types: &PortableRegistry,
+	state: State,
+) -> Result<(ValueDef<C>, bool), DecodeValueError> {
+	let state = state.nested()?;
 	let ty = types.resolve(ty_id.id()).ok_or_else(|| DecodeValueError::TypeIdNotFound(ty_id.id()))?;
 
+	// Alongside the value, we hand back whether it was compact encoded.
 	let value = match ty.type_def() {
-		TypeDef::Composite(inner) => decode_composite_value(data, inner, types).map(ValueDef::Composite),
-		TypeDef::Sequence(inner) => decode_sequence_value(data, inner, types).map(ValueDef::Composite),
-		TypeDef::Array(inner) => decode_array_value(data, inner, types).map(ValueDef::Composite),
-		TypeDef::Tuple(inner) => decode_tuple_value(data, inner, types).map(ValueDef::Composite),
-		TypeDef::Variant(inner) => decode_variant_value(data, inner, types).map(ValueDef::Variant),
+		TypeDef::Composite(inner) => decode_composite_value(data, inner, types, state).map(ValueDef::Composite),
+		TypeDef::Sequence(inner) => decode_sequence_value(data, inner, types, state).map(ValueDef::Composite),
+		TypeDef::Array(inner) => decode_array_value(data, inner, types, state).map(ValueDef::Composite),
+		TypeDef::Tuple(inner) => decode_tuple_value(data, inner, types, state).map(ValueDef::Composite),
+		TypeDef::Variant(inner) => decode_variant_value(data, inner, types, state).map(ValueDef::Variant),
 		TypeDef::Primitive(inner) => decode_primitive_value(data, inner).map(ValueDef::Primitive),
-		TypeDef::Compact(inner) => decode_compact_value(data, inner, types),
+		TypeDef::Compact(inner) => return decode_compact_value(data, inner, types, state).map(|value| (value, true)),
 		TypeDef::BitSequence(inner) => decode_bit_sequence_value(data, inner, types).map(ValueDef::BitSequence),
-	}?;
+	};
+	value.map(|value| (value, false))
+}
+
+/// Decode a value from the start of some owned bytes according to the [`TypeId`] provided, handing
+/// back the decoded value and whatever bytes were left over.
+pub fn decode_value_owned<Id: Into<TypeId>>(
+	mut data: Vec<u8>,
+	ty_id: Id,
+	types: &PortableRegistry,
+) -> Result<(Value<TypeId>, Vec<u8>), DecodeValueError> {
+	let cursor = &mut &*data;
+	let value = decode_value_by_id(cursor, ty_id, types)?;
+	let consumed = data.len() - cursor.len();
+	data.drain(..consumed);
+	Ok((value, data))
+}
+
+/// Lazily decode the elements of a SCALE encoded sequence (ie something like a `Vec<T>`), given the
+/// [`TypeId`] of the elements. See [`SeqValues`] for more information.
+pub fn decode_seq<'a, Id: Into<TypeId>>(data: &'a [u8], elem_ty_id: Id, types: &'a PortableRegistry) -> SeqValues<'a> {
+	SeqValues { data, ty_id: elem_ty_id.into(), types, remaining: None, finished: false }
+}
 
-	Ok(Value { value, context: ty_id })
+/// An iterator which decodes one element of a SCALE encoded sequence at a time. The compact encoded
+/// length of the sequence is read when the first element is asked for. If decoding fails, the error is
+/// handed back and iteration then stops.
+#[derive(Debug, Clone)]
+pub struct SeqValues<'a> {
+	data: &'a [u8],
+	ty_id: TypeId,
+	types: &'a PortableRegistry,
+	remaining: Option<u64>,
+	finished: bool,
 }
 
-fn decode_composite_value(
+impl<'a> SeqValues<'a> {
+	/// The bytes that have not yet been decoded.
+	pub fn remaining_bytes(&self) -> &'a [u8] {
+		self.data
+	}
+}
+
+impl<'a> Iterator for SeqValues<'a> {
+	type Item = Result<Value<TypeId>, DecodeValueError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.finished {
+			return None;
+		}
+
+		// We need to know how many values to try pulling out of the data, which
+		// is given by the compact encoded length that the sequence starts with:
+		let remaining = match self.remaining {
+			Some(remaining) => remaining,
+			None => match Compact::<u64>::decode(&mut self.data) {
+				Ok(len) => len.0,
+				Err(e) => {
+					self.finished = true;
+					return Some(Err(e.into()));
+				}
+			},
+		};
+
+		if remaining == 0 {
+			self.finished = true;
+			return None;
+		}
+
+		self.remaining = Some(remaining - 1);
+		let res = decode_value_by_id(&mut self.data, self.ty_id, self.types);
+		if res.is_err() {
+			self.finished = true;
+		}
+		Some(res)
+	}
+}
+
+fn decode_composite_value<C: DecodeContext>(
 	data: &mut &[u8],
 	ty: &TypeDefComposite<PortableForm>,
 	types: &PortableRegistry,
-) -> Result<Composite<TypeId>, DecodeValueError> {
-	decode_fields(data, ty.fields(), types)
+	state: State,
+) -> Result<Composite<C>, DecodeValueError> {
+	decode_fields(data, ty.fields(), types, state)
 }
 
-fn decode_variant_value(
+fn decode_variant_value<C: DecodeContext>(
 	data: &mut &[u8],
 	ty: &TypeDefVariant<PortableForm>,
 	types: &PortableRegistry,
-) -> Result<Variant<TypeId>, DecodeValueError> {
-	let index = *data.get(0).ok_or(DecodeValueError::Eof)?;
-	*data = &data[1..];
+	state: State,
+) -> Result<Variant<C>, DecodeValueError> {
+	let index = *data.get(0).ok_or(DecodeValueError::UnexpectedEof)?;
 
-	// Does a variant exist with the index we're looking for?
-	let variant = ty
-		.variants()
-		.iter()
-		.find(|v| v.index() == index)
-		.ok_or_else(|| DecodeValueError::VariantNotFound(index, ty.clone()))?;
+	// Does a variant exist with the index we're looking for? We only consume the index
+	// if so, so that any error points at it.
+	let (position, variant) =
+		ty.variants().iter().enumerate().find(|(_, v)| v.index() == index).ok_or_else(|| {
+			match ty.variants().iter().map(|v| v.index()).max() {
+				Some(max) => DecodeValueError::InvalidVariantIndex { index, max },
+				None => DecodeValueError::NoVariants(index),
+			}
+		})?;
+	*data = &data[1..];
 
-	let fields = decode_fields(data, variant.fields(), types)?;
-	Ok(Variant { name: variant.name().clone(), values: fields })
+	// We record the variant's position in the enum rather than the index that it was
+	// encoded with, since the two differ when `#[codec(index = N)]` is used:
+	let fields = decode_fields(data, variant.fields(), types, state)?;
+	Ok(Variant { name: variant.name().clone(), values: fields, index: Some(position as u32) })
 }
 
 /// Variant and Composite types both have fields; this will decode them into values.
-fn decode_fields(
+fn decode_fields<C: DecodeContext>(
 	data: &mut &[u8],
 	fields: &[Field<PortableForm>],
 	types: &PortableRegistry,
-) -> Result<Composite<TypeId>, DecodeValueError> {
+	state: State,
+) -> Result<Composite<C>, DecodeValueError> {
 	let are_named = fields.iter().any(|f| f.name().is_some());
 	let named_field_vals = fields.iter().map(|f| {
 		let name = f.name().cloned().unwrap_or_default();
-		decode_value_by_id(data, f.ty(), types).map(|val| (name, val))
+		decode_value(data, f.ty(), types, state).map(|val| (name, val))
 	});
 
 	if are_named {
@@ -114,86 +368,128 @@ fn decode_fields(
 	}
 }
 
-fn decode_sequence_value(
+fn decode_sequence_value<C: DecodeContext>(
 	data: &mut &[u8],
 	ty: &TypeDefSequence<PortableForm>,
 	types: &PortableRegistry,
-) -> Result<Composite<TypeId>, DecodeValueError> {
+	state: State,
+) -> Result<Composite<C>, DecodeValueError> {
 	// We assume that the sequence is preceeded by a compact encoded length, so that
 	// we know how many values to try pulling out of the data.
-	let len = Compact::<u64>::decode(data)?;
+	let len = decode_compact_number::<u64>(data)?;
 	let values: Vec<_> =
-		(0..len.0).map(|_| decode_value_by_id(data, ty.type_param(), types)).collect::<Result<_, _>>()?;
+		(0..len).map(|_| decode_value(data, ty.type_param(), types, state)).collect::<Result<_, _>>()?;
 
 	Ok(Composite::Unnamed(values))
 }
 
-fn decode_array_value(
+fn decode_array_value<C: DecodeContext>(
 	data: &mut &[u8],
 	ty: &TypeDefArray<PortableForm>,
 	types: &PortableRegistry,
-) -> Result<Composite<TypeId>, DecodeValueError> {
+	state: State,
+) -> Result<Composite<C>, DecodeValueError> {
 	// The length is known based on the type we want to decode into, so we pull out the number of items according
 	// to that, and don't need a length to exist in the SCALE encoded bytes
 	let values: Vec<_> =
-		(0..ty.len()).map(|_| decode_value_by_id(data, ty.type_param(), types)).collect::<Result<_, _>>()?;
+		(0..ty.len()).map(|_| decode_value(data, ty.type_param(), types, state)).collect::<Result<_, _>>()?;
 
 	Ok(Composite::Unnamed(values))
 }
 
-fn decode_tuple_value(
+fn decode_tuple_value<C: DecodeContext>(
 	data: &mut &[u8],
 	ty: &TypeDefTuple<PortableForm>,
 	types: &PortableRegistry,
-) -> Result<Composite<TypeId>, DecodeValueError> {
-	let values: Vec<_> = ty.fields().iter().map(|f| decode_value_by_id(data, f, types)).collect::<Result<_, _>>()?;
+	state: State,
+) -> Result<Composite<C>, DecodeValueError> {
+	let values: Vec<_> = ty.fields().iter().map(|f| decode_value(data, f, types, state)).collect::<Result<_, _>>()?;
 
 	Ok(Composite::Unnamed(values))
 }
 
 fn decode_primitive_value(data: &mut &[u8], ty: &TypeDefPrimitive) -> Result<Primitive, DecodeValueError> {
 	let val = match ty {
-		TypeDefPrimitive::Bool => Primitive::Bool(bool::decode(data)?),
+		TypeDefPrimitive::Bool => Primitive::Bool(decode_fixed_width(data)?),
 		TypeDefPrimitive::Char => {
 			// [jsdw] TODO: There isn't a `char::decode`. Why? Is it wrong to use u32 or is there a more "proper" way?
-			let val = u32::decode(data)?;
+			let val = decode_fixed_width::<u32>(data)?;
 			Primitive::Char(char::from_u32(val).ok_or(DecodeValueError::InvalidChar(val))?)
 		}
-		TypeDefPrimitive::Str => Primitive::Str(String::decode(data)?),
-		TypeDefPrimitive::U8 => Primitive::U8(u8::decode(data)?),
-		TypeDefPrimitive::U16 => Primitive::U16(u16::decode(data)?),
-		TypeDefPrimitive::U32 => Primitive::U32(u32::decode(data)?),
-		TypeDefPrimitive::U64 => Primitive::U64(u64::decode(data)?),
-		TypeDefPrimitive::U128 => Primitive::U128(u128::decode(data)?),
-		TypeDefPrimitive::U256 => Primitive::U256(<[u8; 32]>::decode(data)?),
-		TypeDefPrimitive::I8 => Primitive::I8(i8::decode(data)?),
-		TypeDefPrimitive::I16 => Primitive::I16(i16::decode(data)?),
-		TypeDefPrimitive::I32 => Primitive::I32(i32::decode(data)?),
-		TypeDefPrimitive::I64 => Primitive::I64(i64::decode(data)?),
-		TypeDefPrimitive::I128 => Primitive::I128(i128::decode(data)?),
-		TypeDefPrimitive::I256 => Primitive::I256(<[u8; 32]>::decode(data)?),
+		TypeDefPrimitive::Str => {
+			// Check that the string isn't cut short before decoding it:
+			let len = decode_compact_number::<u64>(&mut &**data)?;
+			let prefix_len = Compact::<u64>::compact_len(&len);
+			if (data.len() - prefix_len) < len as usize {
+				return Err(DecodeValueError::UnexpectedEof);
+			}
+			Primitive::Str(String::decode(data)?)
+		}
+		TypeDefPrimitive::U8 => Primitive::U8(decode_fixed_width(data)?),
+		TypeDefPrimitive::U16 => Primitive::U16(decode_fixed_width(data)?),
+		TypeDefPrimitive::U32 => Primitive::U32(decode_fixed_width(data)?),
+		TypeDefPrimitive::U64 => Primitive::U64(decode_fixed_width(data)?),
+		TypeDefPrimitive::U128 => Primitive::U128(decode_fixed_width(data)?),
+		TypeDefPrimitive::U256 => Primitive::U256(decode_fixed_width(data)?),
+		TypeDefPrimitive::I8 => Primitive::I8(decode_fixed_width(data)?),
+		TypeDefPrimitive::I16 => Primitive::I16(decode_fixed_width(data)?),
+		TypeDefPrimitive::I32 => Primitive::I32(decode_fixed_width(data)?),
+		TypeDefPrimitive::I64 => Primitive::I64(decode_fixed_width(data)?),
+		TypeDefPrimitive::I128 => Primitive::I128(decode_fixed_width(data)?),
+		TypeDefPrimitive::I256 => Primitive::I256(decode_fixed_width(data)?),
 	};
 	Ok(val)
 }
 
-fn decode_compact_value(
+/// Decode a type which is always encoded to the same number of bytes as it takes up in memory (ie
+/// bools, integers and byte arrays), returning [`DecodeValueError::UnexpectedEof`] if the input is too short.
+fn decode_fixed_width<T: Decode>(data: &mut &[u8]) -> Result<T, DecodeValueError> {
+	if data.len() < std::mem::size_of::<T>() {
+		return Err(DecodeValueError::UnexpectedEof);
+	}
+	Ok(T::decode(data)?)
+}
+
+/// Decode a compact encoded number, returning [`DecodeValueError::UnexpectedEof`] if the input is too
+/// short and [`DecodeValueError::InvalidCompact`] if the bytes aren't a valid compact encoding.
+fn decode_compact_number<T>(data: &mut &[u8]) -> Result<T, DecodeValueError>
+where
+	Compact<T>: Decode,
+{
+	// The lowest two bits of the first byte tell us how many bytes the compact encoding takes up:
+	let first = *data.get(0).ok_or(DecodeValueError::UnexpectedEof)?;
+	let len = match first & 0b11 {
+		0b00 => 1,
+		0b01 => 2,
+		0b10 => 4,
+		_ => (first >> 2) as usize + 5,
+	};
+	if data.len() < len {
+		return Err(DecodeValueError::UnexpectedEof);
+	}
+	Compact::<T>::decode(data).map(|c| c.0).map_err(|_| DecodeValueError::InvalidCompact)
+}
+
+fn decode_compact_value<C: DecodeContext>(
 	data: &mut &[u8],
 	ty: &TypeDefCompact<PortableForm>,
 	types: &PortableRegistry,
-) -> Result<ValueDef<TypeId>, DecodeValueError> {
-	fn decode_compact(
+	state: State,
+) -> Result<ValueDef<C>, DecodeValueError> {
+	fn decode_compact<C: DecodeContext>(
 		data: &mut &[u8],
 		inner: &Type,
 		types: &PortableRegistry,
-	) -> Result<ValueDef<TypeId>, DecodeValueError> {
+		state: State,
+	) -> Result<ValueDef<C>, DecodeValueError> {
 		use TypeDefPrimitive::*;
 		let val = match inner.type_def() {
 			// It's obvious how to decode basic primitive unsigned types, since we have impls for them.
-			TypeDef::Primitive(U8) => ValueDef::Primitive(Primitive::U8(Compact::<u8>::decode(data)?.0)),
-			TypeDef::Primitive(U16) => ValueDef::Primitive(Primitive::U16(Compact::<u16>::decode(data)?.0)),
-			TypeDef::Primitive(U32) => ValueDef::Primitive(Primitive::U32(Compact::<u32>::decode(data)?.0)),
-			TypeDef::Primitive(U64) => ValueDef::Primitive(Primitive::U64(Compact::<u64>::decode(data)?.0)),
-			TypeDef::Primitive(U128) => ValueDef::Primitive(Primitive::U128(Compact::<u128>::decode(data)?.0)),
+			TypeDef::Primitive(U8) => ValueDef::Primitive(Primitive::U8(decode_compact_number::<u8>(data)?)),
+			TypeDef::Primitive(U16) => ValueDef::Primitive(Primitive::U16(decode_compact_number::<u16>(data)?)),
+			TypeDef::Primitive(U32) => ValueDef::Primitive(Primitive::U32(decode_compact_number::<u32>(data)?)),
+			TypeDef::Primitive(U64) => ValueDef::Primitive(Primitive::U64(decode_compact_number::<u64>(data)?)),
+			TypeDef::Primitive(U128) => ValueDef::Primitive(Primitive::U128(decode_compact_number::<u128>(data)?)),
 			// A struct with exactly 1 field containing one of the above types can be sensibly compact encoded/decoded.
 			TypeDef::Composite(composite) => {
 				if composite.fields().len() != 1 {
@@ -207,7 +503,10 @@ fn decode_compact_value(
 
 				// Decode this inner type via compact decoding. This can recurse, in case
 				// the inner type is also a 1-field composite type.
-				let inner_value = Value { value: decode_compact(data, inner_ty, types)?, context: field.ty().into() };
+				let remaining_before = *data;
+				let value = decode_compact(data, inner_ty, types, state.nested()?)?;
+				let context = C::new(field.ty().into(), state.range(remaining_before, data), true);
+				let inner_value = Value { value, context };
 
 				// Wrap the inner type in a representation of this outer composite type.
 				let composite = match field.name() {
@@ -227,7 +526,7 @@ fn decode_compact_value(
 	// Pluck the inner type out and run it through our compact decoding logic.
 	let inner =
 		types.resolve(ty.type_param().id()).ok_or_else(|| DecodeValueError::TypeIdNotFound(ty.type_param().id()))?;
-	decode_compact(data, inner, types)
+	decode_compact(data, inner, types, state)
 }
 
 fn decode_bit_sequence_value(
@@ -321,6 +620,36 @@ mod test {
 		encode_decode_check(Compact(123u128), Value::u128(123));
 	}
 
+	#[test]
+	fn decode_compact_values_are_marked_as_such() {
+		let (id, types) = make_type::<Compact<u128>>();
+		let value = decode_value_typed(&mut &*Compact(123u128).encode(), id, &types).expect("can decode");
+		assert_eq!(value.context, DecodedType { id, compact: true });
+		assert_eq!(value.without_context(), Value::u128(123));
+
+		// The same number, not compact encoded:
+		let (id, types) = make_type::<u128>();
+		let value = decode_value_typed(&mut &*123u128.encode(), id, &types).expect("can decode");
+		assert_eq!(value.context, DecodedType { id, compact: false });
+		assert_eq!(value.without_context(), Value::u128(123));
+
+		// Only the compact field of a struct is marked:
+		#[derive(Encode, scale_info::TypeInfo)]
+		struct Foo {
+			a: u32,
+			#[codec(compact)]
+			b: u32,
+		}
+		let (id, types) = make_type::<Foo>();
+		let value = decode_value_typed(&mut &*Foo { a: 1, b: 2 }.encode(), id, &types).expect("can decode");
+		assert!(!value.context.compact);
+		let compact: Vec<_> = match value.value {
+			ValueDef::Composite(composite) => composite.into_iter().map(|(_, v)| v.context.compact).collect(),
+			_ => panic!("expected a composite"),
+		};
+		assert_eq!(compact, vec![false, true]);
+	}
+
 	#[test]
 	fn decode_compact_named_wrapper_struct() {
 		// A struct that can be compact encoded:
@@ -403,18 +732,64 @@ mod test {
 			Bar { hi: String, other: u128 },
 		}
 
+		// Decoded variants carry the index that they were encoded with:
 		encode_decode_check(
 			MyEnum::Foo(true),
-			Value::variant("Foo".to_string(), Composite::Unnamed(vec![Value::bool(true)])),
+			Value::with_context(
+				ValueDef::Variant(Variant {
+					name: "Foo".to_string(),
+					values: Composite::Unnamed(vec![Value::bool(true)]),
+					index: Some(0),
+				}),
+				(),
+			),
 		);
 		encode_decode_check(
 			MyEnum::Bar { hi: "hello".to_string(), other: 123 },
-			Value::variant(
-				"Bar".to_string(),
-				Composite::Named(vec![
-					("hi".to_string(), Value::str("hello".to_string())),
-					("other".to_string(), Value::u128(123)),
-				]),
+			Value::with_context(
+				ValueDef::Variant(Variant {
+					name: "Bar".to_string(),
+					values: Composite::Named(vec![
+						("hi".to_string(), Value::str("hello".to_string())),
+						("other".to_string(), Value::u128(123)),
+					]),
+					index: Some(1),
+				}),
+				(),
+			),
+		);
+	}
+
+	#[test]
+	fn decode_variant_records_position_not_explicit_index() {
+		#[derive(Encode, scale_info::TypeInfo)]
+		enum MyEnum {
+			#[codec(index = 7)]
+			Foo,
+			Bar,
+		}
+
+		// The index recorded is the variant's position, not the index it was encoded with:
+		encode_decode_check(
+			MyEnum::Foo,
+			Value::with_context(
+				ValueDef::Variant(Variant {
+					name: "Foo".to_string(),
+					values: Composite::Unnamed(vec![]),
+					index: Some(0),
+				}),
+				(),
+			),
+		);
+		encode_decode_check(
+			MyEnum::Bar,
+			Value::with_context(
+				ValueDef::Variant(Variant {
+					name: "Bar".to_string(),
+					values: Composite::Unnamed(vec![]),
+					index: Some(1),
+				}),
+				(),
 			),
 		);
 	}
@@ -449,6 +824,198 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn decode_respects_max_depth() {
+		#[derive(Encode, scale_info::TypeInfo)]
+		enum List {
+			Nil,
+			Cons(u8, Box<List>),
+		}
+
+		let (id, types) = make_type::<List>();
+
+		// Each level of the list is a variant value containing the next, so 3 levels + Nil has a depth of 4:
+		let list = List::Cons(1, Box::new(List::Cons(2, Box::new(List::Cons(3, Box::new(List::Nil))))));
+		let bytes = list.encode();
+
+		let val = decode_value_with_max_depth(&mut &*bytes, id, &types, 4).expect("can decode");
+		assert_eq!(val.depth(), 4);
+		assert_eq!(
+			decode_value_with_max_depth(&mut &*bytes, id, &types, 3).unwrap_err().reason(),
+			&DecodeValueError::MaxDepthExceeded(3)
+		);
+	}
+
+	#[test]
+	fn decode_very_deep_value_errors_instead_of_overflowing() {
+		#[derive(scale_info::TypeInfo)]
+		#[allow(dead_code)]
+		enum List {
+			Nil,
+			Cons(u8, Box<List>),
+		}
+
+		let (id, types) = make_type::<List>();
+
+		// A million levels of Cons(0, ..) followed by a Nil:
+		let mut bytes: Vec<u8> = std::iter::repeat([1u8, 0u8]).take(1_000_000).flatten().collect();
+		bytes.push(0);
+
+		assert_eq!(
+			decode_value_by_id(&mut &*bytes, id, &types).unwrap_err().reason(),
+			&DecodeValueError::MaxDepthExceeded(DEFAULT_MAX_DEPTH)
+		);
+	}
+
+	#[test]
+	fn decode_truncated_input_reports_offset() {
+		#[derive(Encode, scale_info::TypeInfo)]
+		struct Foo {
+			a: u8,
+			b: Vec<u16>,
+			c: u32,
+		}
+
+		let (id, types) = make_type::<Foo>();
+		let bytes = Foo { a: 1, b: vec![2, 3], c: 4 }.encode();
+
+		// Cut off part way through `c`, which begins after 1 byte for `a` and 5 for `b`:
+		let err = decode_value_by_id(&mut &bytes[..8], id, &types).unwrap_err();
+		assert_eq!(err.offset(), Some(6));
+		let c_ty = match types.resolve(id.id()).expect("type exists").type_def() {
+			TypeDef::Composite(composite) => composite.fields()[2].ty().id(),
+			_ => panic!("expected a composite type"),
+		};
+		assert_eq!(err.type_id(), Some(c_ty));
+		assert_eq!(err.reason(), &DecodeValueError::UnexpectedEof);
+
+		// Cut off part way through the second item in `b`:
+		let err = decode_value_by_id(&mut &bytes[..4], id, &types).unwrap_err();
+		assert_eq!(err.offset(), Some(4));
+		assert_eq!(err.reason(), &DecodeValueError::UnexpectedEof);
+
+		// A truncated compact length is also reported as such:
+		let err = decode_value_by_id(&mut &[1u8, 0b01][..], id, &types).unwrap_err();
+		assert_eq!(err.offset(), Some(1));
+		assert_eq!(err.reason(), &DecodeValueError::UnexpectedEof);
+	}
+
+	#[test]
+	fn decode_bad_variant_index_reports_offset() {
+		#[allow(dead_code)]
+		#[derive(scale_info::TypeInfo)]
+		enum Foo {
+			A(u8),
+			B(bool),
+		}
+
+		let (id, types) = make_type::<(u8, Foo)>();
+		let err = decode_value_by_id(&mut &[1u8, 2, 0][..], id, &types).unwrap_err();
+		assert_eq!(err.offset(), Some(1));
+		assert_eq!(err.reason(), &DecodeValueError::InvalidVariantIndex { index: 2, max: 1 });
+		assert_eq!(
+			err.to_string(),
+			format!(
+				"Failed to decode the type with ID {} at byte offset 1: Variant index 2 is not valid; the highest variant index is 1",
+				err.type_id().unwrap()
+			)
+		);
+	}
+
+	#[test]
+	fn decode_variant_index_of_empty_enum() {
+		#[allow(dead_code)]
+		#[derive(scale_info::TypeInfo)]
+		enum Never {}
+
+		let (id, types) = make_type::<Never>();
+		let err = decode_value_by_id(&mut &[0u8][..], id, &types).unwrap_err();
+		assert_eq!(err.reason(), &DecodeValueError::NoVariants(0));
+		assert!(err.to_string().ends_with("Variant index 0 is not valid; the enum has no variants"));
+	}
+
+	#[test]
+	fn decode_spanned_and_extract_raw_bytes() {
+		#[derive(Encode, scale_info::TypeInfo)]
+		struct Foo {
+			a: u8,
+			b: Vec<u16>,
+			#[codec(compact)]
+			c: u32,
+		}
+
+		let (id, types) = make_type::<Foo>();
+		let bytes = Foo { a: 1, b: vec![2, 3], c: 1000 }.encode();
+
+		let value = decode_value_spanned(&bytes, id, &types).expect("can decode");
+		assert_eq!(value.context, 0..bytes.len());
+		assert_eq!(value.raw_bytes(&bytes), Some(&*bytes));
+
+		let fields = match value.value {
+			ValueDef::Composite(Composite::Named(fields)) => fields,
+			_ => panic!("expected named composite"),
+		};
+
+		// 1 byte for the u8, 1 + 4 bytes for the Vec<u16>, and then 2 bytes for the compact u32:
+		let spans: Vec<_> = fields.iter().map(|(_, v)| v.context.clone()).collect();
+		assert_eq!(spans, vec![0..1, 1..6, 6..8]);
+
+		// The raw bytes of each field can be decoded independently:
+		let b_bytes = fields[1].1.raw_bytes(&bytes).expect("b is in range");
+		assert_eq!(<Vec<u16>>::decode(&mut &*b_bytes), Ok(vec![2, 3]));
+		let c_bytes = fields[2].1.raw_bytes(&bytes).expect("c is in range");
+		assert_eq!(Compact::<u32>::decode(&mut &*c_bytes), Ok(Compact(1000)));
+	}
+
+	#[test]
+	fn decode_owned_bytes_with_leftovers() {
+		let (id, types) = make_type::<(u8, bool)>();
+
+		let mut bytes = (1u8, true).encode();
+		bytes.extend(b"extra");
+
+		let (value, leftover) = decode_value_owned(bytes, id, &types).expect("can decode");
+		assert_eq!(value.without_context(), Value::unnamed_composite(vec![Value::u8(1), Value::bool(true)]));
+		assert_eq!(leftover, b"extra".to_vec());
+
+		// No leftover bytes is fine too:
+		let bytes = (1u8, true).encode();
+		let (_, leftover) = decode_value_owned(bytes, id, &types).expect("can decode");
+		assert!(leftover.is_empty());
+	}
+
+	#[test]
+	fn decode_seq_lazily() {
+		let (id, types) = make_type::<u16>();
+
+		// An empty sequence yields nothing:
+		let encoded = Vec::<u16>::new().encode();
+		assert_eq!(decode_seq(&encoded, id, &types).count(), 0);
+
+		// A well formed sequence yields each value in turn, leaving any other bytes alone:
+		let mut encoded = vec![1u16, 2, 3].encode();
+		encoded.extend(b"extra");
+		let mut values = decode_seq(&encoded, id, &types);
+		assert_eq!(values.next().map(|v| v.map(|v| v.without_context())), Some(Ok(Value::u16(1))));
+		assert_eq!(values.next().map(|v| v.map(|v| v.without_context())), Some(Ok(Value::u16(2))));
+		assert_eq!(values.next().map(|v| v.map(|v| v.without_context())), Some(Ok(Value::u16(3))));
+		assert_eq!(values.next(), None);
+		assert_eq!(values.remaining_bytes(), b"extra");
+
+		// A truncated sequence yields an error and then stops:
+		let encoded = vec![1u16, 2, 3].encode();
+		let mut values = decode_seq(&encoded[..encoded.len() - 1], id, &types);
+		assert!(matches!(values.next(), Some(Ok(_))));
+		assert!(matches!(values.next(), Some(Ok(_))));
+		assert!(matches!(values.next(), Some(Err(_))));
+		assert_eq!(values.next(), None);
+
+		// As does a sequence with no length prefix:
+		let mut values = decode_seq(&[], id, &types);
+		assert!(matches!(values.next(), Some(Err(_))));
+		assert_eq!(values.next(), None);
+	}
+
 	#[test]
 	fn decode_bit_sequence() {
 		use bitvec::{bitvec, order::Lsb0};
@@ -23,7 +23,7 @@
 //! See [`decode_storage()`] and then the documentation on [`StorageDecoder`] to decode storage lookups.
 
 mod decode_storage;
-mod decode_value;
+pub(crate) mod decode_value;
 mod extrinsic_bytes;
 
 use crate::metadata::Metadata;
@@ -34,9 +34,16 @@ use extrinsic_bytes::{AllExtrinsicBytes, ExtrinsicBytesError};
 use serde::Serialize;
 use sp_runtime::{AccountId32, MultiAddress, MultiSignature};
 use std::borrow::Cow;
+use std::ops::Range;
 
 // Re-export the DecodeValueError here, which we expose in our global `DecodeError` enum.
-pub use decode_value::DecodeValueError;
+pub use decode_value::{DecodeValueError, DEFAULT_MAX_DEPTH};
+
+// Re-export the iterator returned from `decode_seq`.
+pub use decode_value::SeqValues;
+
+// Re-export the context given to values by `decode_value_typed`.
+pub use decode_value::DecodedType;
 
 // Re-export storage related types that are part of our public interface.
 pub use decode_storage::{
@@ -75,6 +82,58 @@ pub fn decode_value_by_id<'a, Id: Into<TypeId>>(
 	decode_value::decode_value_by_id(data, ty, metadata.types())
 }
 
+/// Decode a single [`Value`] in the same way as [`decode_value_by_id`], but fail with
+/// [`DecodeValueError::MaxDepthExceeded`] if values are nested more than `max_depth` levels deep, rather than
+/// the default of [`DEFAULT_MAX_DEPTH`]. This guards against deeply nested or self referential types exhausting the stack.
+pub fn decode_value_with_max_depth<Id: Into<TypeId>>(
+	metadata: &Metadata,
+	ty: Id,
+	data: &mut &[u8],
+	max_depth: usize,
+) -> Result<Value<TypeId>, DecodeValueError> {
+	decode_value::decode_value_with_max_depth(data, ty, metadata.types(), max_depth)
+}
+
+/// Decode a single [`Value`] from some SCALE encoded bytes, in the same way as [`decode_value_by_id`], but giving
+/// each value the range of `data` that it was decoded from as its context. Use [`Value::raw_bytes`] to get hold of the
+/// exact bytes that any value (including nested values) was decoded from.
+pub fn decode_value_spanned<Id: Into<TypeId>>(
+	metadata: &Metadata,
+	ty: Id,
+	data: &[u8],
+) -> Result<Value<Range<usize>>, DecodeValueError> {
+	decode_value::decode_value_spanned(data, ty, metadata.types())
+}
+
+/// Decode a single [`Value`] from some SCALE encoded bytes, in the same way as [`decode_value_by_id`], but giving
+/// each value a [`DecodedType`] as its context. This records whether a value was compact encoded, which is otherwise
+/// lost: a `Compact<u128>` and a plain `u128` both decode into a [`crate::value::Primitive::U128`].
+pub fn decode_value_typed<Id: Into<TypeId>>(
+	metadata: &Metadata,
+	ty: Id,
+	data: &mut &[u8],
+) -> Result<Value<DecodedType>, DecodeValueError> {
+	decode_value::decode_value_typed(data, ty, metadata.types())
+}
+
+/// Decode a single [`Value`] from the start of some owned SCALE encoded bytes, given some metadata and the ID of
+/// the type that we are expecting it to decode into. The decoded value is handed back alongside any bytes that were
+/// not consumed in decoding it, which saves managing a cursor when decoding a value from the front of some larger blob.
+pub fn decode_value_owned<Id: Into<TypeId>>(
+	metadata: &Metadata,
+	ty: Id,
+	data: Vec<u8>,
+) -> Result<(Value<TypeId>, Vec<u8>), DecodeValueError> {
+	decode_value::decode_value_owned(data, ty, metadata.types())
+}
+
+/// Lazily decode a SCALE encoded sequence (for instance a `Vec<T>`), given some metadata and the ID of the
+/// type of the elements in the sequence. This hands back an iterator which decodes one element at a time,
+/// which avoids decoding everything up front when the sequence is large.
+pub fn decode_seq<'a, Id: Into<TypeId>>(metadata: &'a Metadata, elem_ty: Id, data: &'a [u8]) -> SeqValues<'a> {
+	decode_value::decode_seq(data, elem_ty, metadata.types())
+}
+
 /// Generate a [`StorageDecoder`] struct which is capable of decoding SCALE encoded storage keys. It's advisable
 /// to cache this struct if you are decoding lots of storage entries, since it is non-trivial to create.
 ///
@@ -0,0 +1,110 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::encode_value::{encode_value_by_id, scale_size_hint};
+use super::validate::{validate_value, ValidationError};
+use crate::value::Value;
+use crate::TypeId;
+use codec::{Encode, Output};
+use scale_info::PortableRegistry;
+
+/// A [`Value`] paired with the type that it should be encoded as, which implements [`Encode`]. This
+/// allows values to be handed to anything expecting `impl Encode`.
+///
+/// [`Encode`] has no way to report errors, and so the value is checked against the type using
+/// [`validate_value`] when this is created. Validation runs the same encoding logic (throwing the
+/// bytes away), and so encoding the value afterwards is guaranteed to succeed.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodableValue<'a, T> {
+	value: &'a Value<T>,
+	ty_id: TypeId,
+	types: &'a PortableRegistry,
+}
+
+impl<'a, T> EncodableValue<'a, T> {
+	/// Pair a value with the type it should be encoded as, returning an error if it cannot be encoded into that type.
+	pub fn new<Id: Into<TypeId>>(
+		value: &'a Value<T>,
+		ty_id: Id,
+		types: &'a PortableRegistry,
+	) -> Result<Self, ValidationError> {
+		let ty_id = ty_id.into();
+		validate_value(value, ty_id, types)?;
+		Ok(EncodableValue { value, ty_id, types })
+	}
+}
+
+impl<'a, T> Encode for EncodableValue<'a, T> {
+	fn size_hint(&self) -> usize {
+		scale_size_hint(self.value, self.ty_id, self.types).unwrap_or(0)
+	}
+
+	fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+		encode_value_by_id(self.value, self.ty_id, self.types, dest)
+			.expect("values are encoded once to validate them on creation, so encoding them again cannot fail")
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::encoder::make_type;
+	use crate::value::Composite;
+
+	#[allow(dead_code)]
+	#[derive(Encode, scale_info::TypeInfo)]
+	enum Call {
+		Transfer {
+			dest: [u8; 2],
+			#[codec(compact)]
+			amount: u128,
+		},
+		Remark(Vec<u8>),
+	}
+
+	#[test]
+	fn encodes_like_the_encoder() {
+		let (id, types) = make_type::<Call>();
+		let value = Value::variant(
+			"Transfer".into(),
+			Composite::Named(vec![
+				("dest".into(), Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)])),
+				("amount".into(), Value::u128(1_000_000)),
+			]),
+		);
+
+		let mut expected = Vec::new();
+		encode_value_by_id(&value, id, &types, &mut expected).expect("can encode");
+		assert_eq!(expected, Call::Transfer { dest: [1, 2], amount: 1_000_000 }.encode());
+
+		let encodable = EncodableValue::new(&value, id, &types).expect("value is valid");
+		assert_eq!(encodable.encode(), expected);
+		assert_eq!(encodable.size_hint(), expected.len());
+
+		// It can be nested in anything else which is Encode:
+		let call = || Call::Transfer { dest: [1, 2], amount: 1_000_000 };
+		assert_eq!(vec![encodable, encodable].encode(), vec![call(), call()].encode());
+	}
+
+	#[test]
+	fn invalid_values_are_rejected_up_front() {
+		let (id, types) = make_type::<Call>();
+		let value = Value::variant("Remark".into(), Composite::Unnamed(vec![Value::bool(true)]));
+		let err = EncodableValue::new(&value, id, &types).unwrap_err();
+		assert_eq!(err.path, vec![0.into()]);
+	}
+}
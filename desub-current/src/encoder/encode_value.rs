@@ -0,0 +1,743 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::value::{i256_to_i128, u256_to_u128, Composite, PathSegment, Primitive, Value, ValueDef};
+use crate::{Type, TypeId};
+use codec::{Compact, Encode, Output};
+use scale_info::{
+	form::PortableForm, Field, PortableRegistry, TypeDefArray, TypeDefCompact, TypeDefComposite, TypeDefPrimitive,
+	TypeDefSequence, TypeDefTuple, TypeDefVariant,
+};
+
+// This is used in several places below.
+type TypeDef = scale_info::TypeDef<PortableForm>;
+
+#[derive(Debug, Clone, thiserror::Error, PartialEq)]
+pub enum EncodeError {
+	#[error("Cannot find type with ID {0}")]
+	TypeIdNotFound(u32),
+	#[error("Expected {expected} value to encode into the type with ID {ty}")]
+	WrongShape { expected: &'static str, ty: u32 },
+	#[error("Expected {expected} values to encode into the type with ID {ty}, but got {actual}")]
+	WrongLength { expected: usize, actual: usize, ty: u32 },
+	#[error("Cannot encode {0:?} into the primitive type {1:?}")]
+	WrongPrimitive(Primitive, TypeDefPrimitive),
+	#[error("Could not find variant with name '{0}' in {1:?}")]
+	VariantNotFound(String, scale_info::TypeDefVariant<PortableForm>),
+	#[error("Could not find a value for the field '{0}'")]
+	MissingField(String),
+	#[error("Could not compact encode a value into {0:?}")]
+	CannotEncodeCompactIntoType(Type),
+}
+
+/// Encode a [`Value`] into SCALE bytes according to the [`TypeId`] provided, appending
+/// them to the output provided.
+pub fn encode_value_by_id<T, Id: Into<TypeId>, O: Output + ?Sized>(
+	value: &Value<T>,
+	ty_id: Id,
+	types: &PortableRegistry,
+	out: &mut O,
+) -> Result<(), EncodeError> {
+	encode_value_with_path(value, ty_id, types, out, &mut ())
+}
+
+/// This is told about each field or item that the encoder steps into and back out of, so that it can
+/// keep track of where in a value any error occurred. On error, nothing is stepped back out of.
+pub(super) trait TrackPath {
+	fn enter(&mut self, segment: impl FnOnce() -> PathSegment);
+	fn exit(&mut self);
+}
+
+// Encoding normally doesn't care where it is.
+impl TrackPath for () {
+	fn enter(&mut self, _segment: impl FnOnce() -> PathSegment) {}
+	fn exit(&mut self) {}
+}
+
+impl TrackPath for Vec<PathSegment> {
+	fn enter(&mut self, segment: impl FnOnce() -> PathSegment) {
+		self.push(segment());
+	}
+	fn exit(&mut self) {
+		self.pop();
+	}
+}
+
+/// Encode a [`Value`] in the same way as [`encode_value_by_id`], telling `path` about each nested value
+/// that's encoded along the way.
+pub(super) fn encode_value_with_path<T, Id: Into<TypeId>, O: Output + ?Sized, P: TrackPath>(
+	value: &Value<T>,
+	ty_id: Id,
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+) -> Result<(), EncodeError> {
+	let ty_id = ty_id.into().id();
+	let ty = types.resolve(ty_id).ok_or(EncodeError::TypeIdNotFound(ty_id))?;
+
+	match ty.type_def() {
+		TypeDef::Composite(inner) => encode_composite_value(&value.value, ty_id, inner, types, out, path),
+		TypeDef::Sequence(inner) => encode_sequence_value(&value.value, ty_id, inner, types, out, path),
+		TypeDef::Array(inner) => encode_array_value(&value.value, ty_id, inner, types, out, path),
+		TypeDef::Tuple(inner) => encode_tuple_value(&value.value, ty_id, inner, types, out, path),
+		TypeDef::Variant(inner) => encode_variant_value(&value.value, ty_id, inner, types, out, path),
+		TypeDef::Primitive(inner) => encode_primitive_value(&value.value, ty_id, inner, out),
+		TypeDef::Compact(inner) => encode_compact_value(&value.value, inner, types, out),
+		TypeDef::BitSequence(_) => match &value.value {
+			ValueDef::BitSequence(bits) => {
+				// [jsdw] TODO: As with decoding, we assume that the bit sequence uses Lsb0 and u8.
+				bits.encode_to(out);
+				Ok(())
+			}
+			_ => Err(EncodeError::WrongShape { expected: "a bit sequence", ty: ty_id }),
+		},
+	}
+}
+
+/// Return the number of bytes that [`encode_value_by_id`] would produce given the same arguments,
+/// without allocating anything to hold them. This is useful for pre-sizing an output buffer. The
+/// same [`EncodeError`]s are returned as would be when encoding.
+pub fn scale_size_hint<T, Id: Into<TypeId>>(
+	value: &Value<T>,
+	ty_id: Id,
+	types: &PortableRegistry,
+) -> Result<usize, EncodeError> {
+	let mut counter = ByteCounter(0);
+	encode_value_by_id(value, ty_id, types, &mut counter)?;
+	Ok(counter.0)
+}
+
+/// An [`Output`] which just counts the bytes written to it.
+struct ByteCounter(usize);
+
+impl Output for ByteCounter {
+	fn write(&mut self, bytes: &[u8]) {
+		self.0 += bytes.len();
+	}
+	fn push_byte(&mut self, _byte: u8) {
+		self.0 += 1;
+	}
+}
+
+// Encode a value nested inside of the one currently being encoded, which is found at the path segment given.
+fn encode_nested<T, Id: Into<TypeId>, O: Output + ?Sized, P: TrackPath>(
+	value: &Value<T>,
+	ty_id: Id,
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+	segment: impl FnOnce() -> PathSegment,
+) -> Result<(), EncodeError> {
+	path.enter(segment);
+	encode_value_with_path(value, ty_id, types, out, path)?;
+	path.exit();
+	Ok(())
+}
+
+fn encode_composite_value<T, O: Output + ?Sized, P: TrackPath>(
+	value: &ValueDef<T>,
+	ty_id: u32,
+	ty: &TypeDefComposite<PortableForm>,
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+) -> Result<(), EncodeError> {
+	match value {
+		ValueDef::Composite(composite) => encode_fields(composite, ty_id, ty.fields(), types, out, path),
+		_ => Err(EncodeError::WrongShape { expected: "a composite", ty: ty_id }),
+	}
+}
+
+fn encode_variant_value<T, O: Output + ?Sized, P: TrackPath>(
+	value: &ValueDef<T>,
+	ty_id: u32,
+	ty: &TypeDefVariant<PortableForm>,
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+) -> Result<(), EncodeError> {
+	let value = match value {
+		ValueDef::Variant(variant) => variant,
+		_ => return Err(EncodeError::WrongShape { expected: "a variant", ty: ty_id }),
+	};
+
+	// Does a variant exist with the name we're looking for?
+	let variant = ty
+		.variants()
+		.iter()
+		.find(|v| v.name() == &value.name)
+		.ok_or_else(|| EncodeError::VariantNotFound(value.name.clone(), ty.clone()))?;
+
+	variant.index().encode_to(out);
+	encode_fields(&value.values, ty_id, variant.fields(), types, out, path)
+}
+
+/// Variant and Composite types both have fields; this will encode values into them.
+fn encode_fields<T, O: Output + ?Sized, P: TrackPath>(
+	composite: &Composite<T>,
+	ty_id: u32,
+	fields: &[Field<PortableForm>],
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+) -> Result<(), EncodeError> {
+	if composite.len() != fields.len() {
+		return Err(EncodeError::WrongLength { expected: fields.len(), actual: composite.len(), ty: ty_id });
+	}
+
+	let are_named = fields.iter().any(|f| f.name().is_some());
+	match composite {
+		// If we have names on both sides, we line the values up by name so that order doesn't matter:
+		Composite::Named(values) if are_named => {
+			for field in fields {
+				let name = field.name().cloned().unwrap_or_default();
+				let (_, value) =
+					values.iter().find(|(n, _)| n == &name).ok_or_else(|| EncodeError::MissingField(name.clone()))?;
+				encode_nested(value, field.ty(), types, out, path, || PathSegment::Field(name))?;
+			}
+		}
+		// Otherwise, the values are expected to be in the right order:
+		Composite::Named(values) => {
+			for ((name, value), field) in values.iter().zip(fields) {
+				encode_nested(value, field.ty(), types, out, path, || PathSegment::Field(name.clone()))?;
+			}
+		}
+		Composite::Unnamed(values) => {
+			for (idx, (value, field)) in values.iter().zip(fields).enumerate() {
+				encode_nested(value, field.ty(), types, out, path, || PathSegment::Index(idx))?;
+			}
+		}
+	}
+	Ok(())
+}
+
+fn encode_sequence_value<T, O: Output + ?Sized, P: TrackPath>(
+	value: &ValueDef<T>,
+	ty_id: u32,
+	ty: &TypeDefSequence<PortableForm>,
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+) -> Result<(), EncodeError> {
+	let values = match value {
+		ValueDef::Composite(composite) => composite_values(composite),
+		_ => return Err(EncodeError::WrongShape { expected: "a composite", ty: ty_id }),
+	};
+
+	// Sequences are preceeded by a compact encoded length:
+	Compact(values.len() as u64).encode_to(out);
+	for (idx, value) in values.into_iter().enumerate() {
+		encode_nested(value, ty.type_param(), types, out, path, || PathSegment::Index(idx))?;
+	}
+	Ok(())
+}
+
+fn encode_array_value<T, O: Output + ?Sized, P: TrackPath>(
+	value: &ValueDef<T>,
+	ty_id: u32,
+	ty: &TypeDefArray<PortableForm>,
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+) -> Result<(), EncodeError> {
+	let values = match value {
+		ValueDef::Composite(composite) => composite_values(composite),
+		_ => return Err(EncodeError::WrongShape { expected: "a composite", ty: ty_id }),
+	};
+
+	// The length is known based on the type, so no length is encoded, but we need to make sure it lines up:
+	if values.len() != ty.len() as usize {
+		return Err(EncodeError::WrongLength { expected: ty.len() as usize, actual: values.len(), ty: ty_id });
+	}
+	for (idx, value) in values.into_iter().enumerate() {
+		encode_nested(value, ty.type_param(), types, out, path, || PathSegment::Index(idx))?;
+	}
+	Ok(())
+}
+
+fn encode_tuple_value<T, O: Output + ?Sized, P: TrackPath>(
+	value: &ValueDef<T>,
+	ty_id: u32,
+	ty: &TypeDefTuple<PortableForm>,
+	types: &PortableRegistry,
+	out: &mut O,
+	path: &mut P,
+) -> Result<(), EncodeError> {
+	let values = match value {
+		ValueDef::Composite(composite) => composite_values(composite),
+		_ => return Err(EncodeError::WrongShape { expected: "a composite", ty: ty_id }),
+	};
+
+	if values.len() != ty.fields().len() {
+		return Err(EncodeError::WrongLength { expected: ty.fields().len(), actual: values.len(), ty: ty_id });
+	}
+	for (idx, (value, field_ty)) in values.into_iter().zip(ty.fields()).enumerate() {
+		encode_nested(value, field_ty, types, out, path, || PathSegment::Index(idx))?;
+	}
+	Ok(())
+}
+
+fn encode_primitive_value<T, O: Output + ?Sized>(
+	value: &ValueDef<T>,
+	ty_id: u32,
+	ty: &TypeDefPrimitive,
+	out: &mut O,
+) -> Result<(), EncodeError> {
+	let prim = match value {
+		ValueDef::Primitive(prim) => prim,
+		_ => return Err(EncodeError::WrongShape { expected: "a primitive", ty: ty_id }),
+	};
+
+	match (ty, prim) {
+		(TypeDefPrimitive::Bool, Primitive::Bool(v)) => v.encode_to(out),
+		(TypeDefPrimitive::Char, Primitive::Char(v)) => (*v as u32).encode_to(out),
+		(TypeDefPrimitive::Str, Primitive::Str(v)) => v.encode_to(out),
+		// Integers are range checked against the type being encoded into, so their widths needn't line up:
+		(ty, prim) => {
+			encode_integer(prim, ty, out).ok_or_else(|| EncodeError::WrongPrimitive(prim.clone(), ty.clone()))?
+		}
+	}
+	Ok(())
+}
+
+/// Encode an integer primitive of any width or signedness into the integer type given, so long as its value
+/// fits. Nothing is written (and `None` is handed back) if the primitive isn't an integer which fits.
+fn encode_integer<O: Output + ?Sized>(prim: &Primitive, ty: &TypeDefPrimitive, out: &mut O) -> Option<()> {
+	match ty {
+		TypeDefPrimitive::U8 => u8::try_from(as_u128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::U16 => u16::try_from(as_u128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::U32 => u32::try_from(as_u128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::U64 => u64::try_from(as_u128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::U128 => as_u128(prim)?.encode_to(out),
+		TypeDefPrimitive::U256 => as_u256_bytes(prim)?.encode_to(out),
+		TypeDefPrimitive::I8 => i8::try_from(as_i128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::I16 => i16::try_from(as_i128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::I32 => i32::try_from(as_i128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::I64 => i64::try_from(as_i128(prim)?).ok()?.encode_to(out),
+		TypeDefPrimitive::I128 => as_i128(prim)?.encode_to(out),
+		TypeDefPrimitive::I256 => as_i256_bytes(prim)?.encode_to(out),
+		_ => return None,
+	}
+	Some(())
+}
+
+/// The value of an unsigned integer primitive, if it fits into a `u128`.
+fn unsigned(prim: &Primitive) -> Option<u128> {
+	match *prim {
+		Primitive::U8(v) => Some(v.into()),
+		Primitive::U16(v) => Some(v.into()),
+		Primitive::U32(v) => Some(v.into()),
+		Primitive::U64(v) => Some(v.into()),
+		Primitive::U128(v) => Some(v),
+		Primitive::U256(v) => u256_to_u128(&v),
+		_ => None,
+	}
+}
+
+/// The value of a signed integer primitive, if it fits into an `i128`.
+fn signed(prim: &Primitive) -> Option<i128> {
+	match *prim {
+		Primitive::I8(v) => Some(v.into()),
+		Primitive::I16(v) => Some(v.into()),
+		Primitive::I32(v) => Some(v.into()),
+		Primitive::I64(v) => Some(v.into()),
+		Primitive::I128(v) => Some(v),
+		Primitive::I256(v) => i256_to_i128(&v),
+		_ => None,
+	}
+}
+
+/// The value of any integer primitive, if it fits into a `u128`.
+fn as_u128(prim: &Primitive) -> Option<u128> {
+	unsigned(prim).or_else(|| u128::try_from(signed(prim)?).ok())
+}
+
+/// The value of any integer primitive, if it fits into an `i128`.
+fn as_i128(prim: &Primitive) -> Option<i128> {
+	signed(prim).or_else(|| i128::try_from(unsigned(prim)?).ok())
+}
+
+/// The value of any integer primitive as 32 little endian bytes, if it's not negative.
+fn as_u256_bytes(prim: &Primitive) -> Option<[u8; 32]> {
+	match *prim {
+		Primitive::U256(v) => Some(v),
+		// The top bit is the sign bit, so if it's unset then the bytes are the same either way:
+		Primitive::I256(v) if v[31] & 0x80 == 0 => Some(v),
+		_ => as_u128(prim).map(|v| extend_to_256(v.to_le_bytes(), 0x00)),
+	}
+}
+
+/// The value of any integer primitive as 32 little endian two's complement bytes, if it fits.
+fn as_i256_bytes(prim: &Primitive) -> Option<[u8; 32]> {
+	match *prim {
+		Primitive::I256(v) => Some(v),
+		Primitive::U256(v) if v[31] & 0x80 == 0 => Some(v),
+		_ => match unsigned(prim) {
+			Some(v) => Some(extend_to_256(v.to_le_bytes(), 0x00)),
+			None => signed(prim).map(|v| extend_to_256(v.to_le_bytes(), if v < 0 { 0xFF } else { 0x00 })),
+		},
+	}
+}
+
+/// Fill out the high bytes of a 128 bit little endian integer to make it 256 bits wide.
+fn extend_to_256(low: [u8; 16], fill: u8) -> [u8; 32] {
+	let mut bytes = [fill; 32];
+	bytes[..16].copy_from_slice(&low);
+	bytes
+}
+
+fn encode_compact_value<T, O: Output + ?Sized>(
+	value: &ValueDef<T>,
+	ty: &TypeDefCompact<PortableForm>,
+	types: &PortableRegistry,
+	out: &mut O,
+) -> Result<(), EncodeError> {
+	fn encode_compact<T, O: Output + ?Sized>(
+		value: &ValueDef<T>,
+		inner: &Type,
+		types: &PortableRegistry,
+		out: &mut O,
+	) -> Result<(), EncodeError> {
+		use TypeDefPrimitive::*;
+		match (inner.type_def(), value) {
+			// It's obvious how to encode basic primitive unsigned types, since we have impls for them. As when
+			// encoding them normally, any integer whose value fits into the type can be encoded into it.
+			(TypeDef::Primitive(ty @ (U8 | U16 | U32 | U64 | U128)), ValueDef::Primitive(prim)) => {
+				let wrong_primitive = || EncodeError::WrongPrimitive(prim.clone(), ty.clone());
+				let n = as_u128(prim).ok_or_else(wrong_primitive)?;
+				match ty {
+					U8 => Compact(u8::try_from(n).map_err(|_| wrong_primitive())?).encode_to(out),
+					U16 => Compact(u16::try_from(n).map_err(|_| wrong_primitive())?).encode_to(out),
+					U32 => Compact(u32::try_from(n).map_err(|_| wrong_primitive())?).encode_to(out),
+					U64 => Compact(u64::try_from(n).map_err(|_| wrong_primitive())?).encode_to(out),
+					_ => Compact(n).encode_to(out),
+				}
+			}
+			// A struct with exactly 1 field containing one of the above types can be sensibly compact encoded/decoded.
+			(TypeDef::Composite(composite), ValueDef::Composite(values))
+				if composite.fields().len() == 1 && values.len() == 1 =>
+			{
+				let field_type_id = composite.fields()[0].ty().id();
+				let inner_ty = types.resolve(field_type_id).ok_or(EncodeError::TypeIdNotFound(field_type_id))?;
+				let inner_value = composite_values(values)[0];
+
+				// Encode the inner value via compact encoding. This can recurse, in case
+				// the inner type is also a 1-field composite type.
+				encode_compact(&inner_value.value, inner_ty, types, out)?;
+			}
+			// For now, we give up if we have been asked for any other type:
+			_cannot_encode_into => return Err(EncodeError::CannotEncodeCompactIntoType(inner.clone())),
+		}
+		Ok(())
+	}
+
+	// Pluck the inner type out and run it through our compact encoding logic.
+	let inner = types.resolve(ty.type_param().id()).ok_or_else(|| EncodeError::TypeIdNotFound(ty.type_param().id()))?;
+	encode_compact(value, inner, types, out)
+}
+
+/// Return references to the values in a composite, ignoring any names.
+fn composite_values<T>(composite: &Composite<T>) -> Vec<&Value<T>> {
+	match composite {
+		Composite::Named(values) => values.iter().map(|(_, v)| v).collect(),
+		Composite::Unnamed(values) => values.iter().collect(),
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::decoder::decode_value::decode_value_by_id;
+	use crate::encoder::make_type;
+
+	/// Encode a value, decode it into a [`Value`], and then check that encoding
+	/// that [`Value`] hands back exactly the same bytes as we started with.
+	fn round_trip_check<T: Encode + scale_info::TypeInfo + 'static>(val: T) {
+		round_trip_check_explicit_info::<T, _>(val)
+	}
+
+	/// As above, but the type info to use is provided separately from the value to encode.
+	fn round_trip_check_explicit_info<Ty: scale_info::TypeInfo + 'static, T: Encode>(val: T) {
+		let encoded = val.encode();
+		let (id, portable_registry) = make_type::<Ty>();
+
+		let value = decode_value_by_id(&mut &*encoded, id, &portable_registry).expect("decoding failed");
+
+		let mut re_encoded = Vec::new();
+		encode_value_by_id(&value, id, &portable_registry, &mut re_encoded).expect("encoding failed");
+		assert_eq!(encoded, re_encoded, "re-encoded bytes do not match the original bytes");
+
+		// It shouldn't matter what context the value has:
+		let mut re_encoded = Vec::new();
+		value.without_context().scale_encode(id, &portable_registry, &mut re_encoded).expect("encoding failed");
+		assert_eq!(encoded, re_encoded, "re-encoded bytes do not match the original bytes");
+
+		// The size hint should line up exactly with the encoded length:
+		let size_hint = scale_size_hint(&value, id, &portable_registry).expect("size hint failed");
+		assert_eq!(size_hint, encoded.len(), "size hint does not match the encoded length");
+	}
+
+	#[test]
+	fn round_trip_primitives() {
+		round_trip_check(true);
+		round_trip_check(false);
+		round_trip_check_explicit_info::<char, _>('a' as u32);
+		round_trip_check("hello".to_string());
+		round_trip_check(123u8);
+		round_trip_check(123u16);
+		round_trip_check(123u32);
+		round_trip_check(123u64);
+		round_trip_check(123u128);
+		round_trip_check(-123i8);
+		round_trip_check(-123i16);
+		round_trip_check(-123i32);
+		round_trip_check(-123i64);
+		round_trip_check(-123i128);
+	}
+
+	#[test]
+	fn round_trip_compact_primitives() {
+		round_trip_check(Compact(123u8));
+		round_trip_check(Compact(123u16));
+		round_trip_check(Compact(123u32));
+		round_trip_check(Compact(123u64));
+		round_trip_check(Compact(123u128));
+	}
+
+	#[test]
+	fn round_trip_compact_fields() {
+		// Whether a field is compact encoded is part of its type, so a decoded value that is
+		// encoded using the same type will be compact encoded again rather than fixed width:
+		#[derive(Encode, scale_info::TypeInfo)]
+		struct Balance {
+			#[codec(compact)]
+			amount: u128,
+			nonce: u32,
+		}
+
+		let (id, types) = make_type::<Balance>();
+		let encoded = Balance { amount: 1, nonce: 2 }.encode();
+		assert_eq!(encoded.len(), 1 + 4);
+
+		let value = decode_value_by_id(&mut &*encoded, id, &types).expect("decoding failed");
+		let mut re_encoded = Vec::new();
+		encode_value_by_id(&value, id, &types, &mut re_encoded).expect("encoding failed");
+		assert_eq!(re_encoded, encoded);
+
+		round_trip_check(Balance { amount: u128::MAX, nonce: 0 });
+	}
+
+	#[test]
+	fn round_trip_sequence_array_tuple_types() {
+		round_trip_check(vec![1i32, 2, 3]);
+		round_trip_check(Vec::<u8>::new());
+		round_trip_check([1i32, 2, 3]);
+		round_trip_check((1i32, true, 123456u128));
+	}
+
+	#[test]
+	fn round_trip_variant_types() {
+		#[derive(Encode, scale_info::TypeInfo)]
+		enum MyEnum {
+			Foo(bool),
+			#[codec(index = 10)]
+			Bar {
+				hi: String,
+				other: u128,
+			},
+		}
+
+		round_trip_check(MyEnum::Foo(true));
+		round_trip_check(MyEnum::Bar { hi: "hello".to_string(), other: 123 });
+		round_trip_check(Some(vec![1u8, 2, 3]));
+		round_trip_check(None::<u8>);
+	}
+
+	#[test]
+	fn round_trip_composite_types() {
+		#[derive(Encode, scale_info::TypeInfo)]
+		struct Unnamed(bool, String, Vec<u8>);
+
+		#[derive(Encode, scale_info::TypeInfo)]
+		struct Named {
+			is_valid: bool,
+			name: String,
+			bytes: Vec<u8>,
+		}
+
+		round_trip_check(Unnamed(true, "James".into(), vec![1, 2, 3]));
+		round_trip_check(Named { is_valid: true, name: "James".into(), bytes: vec![1, 2, 3] });
+	}
+
+	#[test]
+	fn round_trip_bit_sequence() {
+		use bitvec::{bitvec, order::Lsb0};
+		round_trip_check(bitvec![Lsb0, u8; 0, 1, 1, 0, 1, 0]);
+	}
+
+	#[cfg(feature = "hashing")]
+	#[test]
+	fn encode_and_hash_extrinsic() {
+		// A SCALE encoded balances transfer extrinsic. Extrinsics are encoded like a `Vec<u8>`; the
+		// first two bytes here are the compact encoded length, and the rest are the extrinsic bytes:
+		let ext_bytes = hex::decode("31028400d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d016ada9b477ef454972200e098f1186d4a2aeee776f1f6a68609797f5ba052906ad2427bdca865442158d118e2dfc82226077e4dfdff975d005685bab66eefa38a150200000500001cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07ce5c0").unwrap();
+
+		let (id, types) = make_type::<Vec<u8>>();
+		let value = Value::unnamed_composite(ext_bytes[2..].iter().map(|&b| Value::u8(b)).collect());
+
+		let hash = value.encode_and_hash(id, &types).expect("can encode and hash");
+		assert_eq!(hex::encode(hash), "9873ca783200039c52ca41657ba57438f9da6935bec5bc1d662a4090cb0fee0a");
+	}
+
+	#[test]
+	fn named_fields_can_be_given_in_any_order() {
+		#[derive(Encode, scale_info::TypeInfo)]
+		struct Named {
+			a: bool,
+			b: u8,
+		}
+
+		let (id, types) = make_type::<Named>();
+		let value = Value::named_composite(vec![("b".into(), Value::u8(123)), ("a".into(), Value::bool(true))]);
+
+		let mut encoded = Vec::new();
+		encode_value_by_id(&value, id, &types, &mut encoded).expect("encoding failed");
+		assert_eq!(encoded, Named { a: true, b: 123 }.encode());
+	}
+
+	#[test]
+	fn size_hint_accounts_for_length_prefixes() {
+		// Compact length prefixes are 1, 2 or 4 bytes long depending on the length:
+		for len in [0, 63, 64, 16383, 16384] {
+			let (id, types) = make_type::<Vec<u8>>();
+			let value = Value::unnamed_composite(vec![Value::u8(1); len]);
+			assert_eq!(scale_size_hint(&value, id, &types), Ok(vec![1u8; len].encode().len()));
+
+			let (id, types) = make_type::<String>();
+			let value = Value::str("a".repeat(len));
+			assert_eq!(scale_size_hint(&value, id, &types), Ok("a".repeat(len).encode().len()));
+		}
+
+		#[allow(dead_code)]
+		#[derive(Encode, scale_info::TypeInfo)]
+		enum Foo {
+			Named {
+				names: Vec<String>,
+				#[codec(compact)]
+				amount: u128,
+			},
+			Empty,
+		}
+		let (id, types) = make_type::<Foo>();
+		let value = Value::variant(
+			"Named".into(),
+			Composite::Named(vec![
+				("names".into(), Value::unnamed_composite(vec![Value::str("hello".into()); 100])),
+				("amount".into(), Value::u128(u128::MAX)),
+			]),
+		);
+		let expected = Foo::Named { names: vec!["hello".into(); 100], amount: u128::MAX }.encode().len();
+		assert_eq!(scale_size_hint(&value, id, &types), Ok(expected));
+		let value = Value::variant("Empty".into(), Composite::Unnamed(vec![]));
+		assert_eq!(scale_size_hint(&value, id, &types), Ok(1));
+	}
+
+	#[test]
+	fn size_hint_fails_like_encoding() {
+		let (id, types) = make_type::<(u8, bool)>();
+		let value = Value::unnamed_composite(vec![Value::u8(1)]);
+		assert!(matches!(
+			scale_size_hint(&value, id, &types),
+			Err(EncodeError::WrongLength { expected: 2, actual: 1, .. })
+		));
+	}
+
+	#[test]
+	fn integers_encode_into_any_type_they_fit() {
+		fn encode<Ty: scale_info::TypeInfo + 'static>(value: Value<()>) -> Result<Vec<u8>, EncodeError> {
+			let (id, types) = make_type::<Ty>();
+			let mut out = Vec::new();
+			encode_value_by_id(&value, id, &types, &mut out).map(|_| out)
+		}
+
+		assert_eq!(encode::<u8>(Value::u128(1)), Ok(1u8.encode()));
+		assert_eq!(
+			encode::<u8>(Value::u128(256)),
+			Err(EncodeError::WrongPrimitive(Primitive::U128(256), TypeDefPrimitive::U8))
+		);
+		assert_eq!(encode::<u64>(Value::u8(7)), Ok(7u64.encode()));
+		assert_eq!(encode::<i16>(Value::i8(-1)), Ok((-1i16).encode()));
+		assert_eq!(encode::<i8>(Value::u32(127)), Ok(127i8.encode()));
+		assert!(encode::<i8>(Value::u32(128)).is_err());
+		assert!(encode::<u32>(Value::i32(-1)).is_err());
+
+		// 256 bit integers are little endian, and signed ones are sign extended:
+		assert!(encode::<u8>(Value::primitive(Primitive::U256([1; 32]))).is_err());
+		let mut one = [0; 32];
+		one[0] = 1;
+		assert_eq!(encode::<u8>(Value::primitive(Primitive::U256(one))), Ok(1u8.encode()));
+		assert_eq!(encode::<i32>(Value::primitive(Primitive::I256([0xFF; 32]))), Ok((-1i32).encode()));
+
+		// Compact encoded integers are range checked in the same way:
+		assert_eq!(encode::<Compact<u8>>(Value::u128(200)), Ok(Compact(200u8).encode()));
+		assert!(encode::<Compact<u8>>(Value::u128(256)).is_err());
+		assert!(encode::<Compact<u128>>(Value::i8(-1)).is_err());
+	}
+
+	#[test]
+	fn mismatched_values_fail_to_encode() {
+		#[allow(dead_code)]
+		#[derive(scale_info::TypeInfo)]
+		enum MyEnum {
+			Foo(bool),
+		}
+
+		#[allow(dead_code)]
+		#[derive(scale_info::TypeInfo)]
+		struct Named {
+			a: bool,
+			b: u8,
+		}
+
+		let (id, types) = make_type::<u8>();
+		assert_eq!(
+			encode_value_by_id(&Value::u16(256), id, &types, &mut Vec::new()),
+			Err(EncodeError::WrongPrimitive(Primitive::U16(256), TypeDefPrimitive::U8))
+		);
+		assert!(matches!(
+			encode_value_by_id(&Value::unnamed_composite(vec![]), id, &types, &mut Vec::new()),
+			Err(EncodeError::WrongShape { .. })
+		));
+
+		let (id, types) = make_type::<[u8; 2]>();
+		assert!(matches!(
+			encode_value_by_id(&Value::unnamed_composite(vec![Value::u8(1)]), id, &types, &mut Vec::new()),
+			Err(EncodeError::WrongLength { expected: 2, actual: 1, .. })
+		));
+
+		let (id, types) = make_type::<MyEnum>();
+		let value = Value::variant("Bar".into(), Composite::Unnamed(vec![Value::bool(true)]));
+		assert!(matches!(
+			encode_value_by_id(&value, id, &types, &mut Vec::new()),
+			Err(EncodeError::VariantNotFound(name, _)) if name == "Bar"
+		));
+
+		let (id, types) = make_type::<Named>();
+		let value = Value::named_composite(vec![("a".into(), Value::bool(true)), ("c".into(), Value::u8(1))]);
+		assert_eq!(encode_value_by_id(&value, id, &types, &mut Vec::new()), Err(EncodeError::MissingField("b".into())));
+	}
+}
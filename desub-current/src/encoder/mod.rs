@@ -0,0 +1,67 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Given some [`Metadata`] obtained from a substrate node, this module exposes the functionality to
+//! SCALE encode [`Value`]s back into bytes that are compatible with that metadata. This is the
+//! counterpart to the [`crate::decoder`] module.
+//!
+//! See [`encode_value_by_id`], or [`Value::scale_encode`] if you only have a type registry to hand.
+//! [`validate_value`] checks that a value can be encoded into some type without encoding it, and
+//! [`EncodableValue`] pairs a value with a type so that it can be used wherever `impl Encode` is expected.
+
+mod encodable;
+pub(crate) mod encode_value;
+mod validate;
+
+use crate::metadata::Metadata;
+use crate::value::Value;
+use crate::TypeId;
+
+pub use encodable::EncodableValue;
+pub use encode_value::EncodeError;
+pub use validate::{validate_value, ValidationError};
+
+/// SCALE encode a single [`Value`], given some metadata and the ID of the type that we want it to be
+/// encoded as, appending the resulting bytes to `out`. An [`EncodeError`] is returned if the shape of
+/// the value does not line up with the type.
+pub fn encode_value_by_id<T, Id: Into<TypeId>>(
+	metadata: &Metadata,
+	ty: Id,
+	value: &Value<T>,
+	out: &mut Vec<u8>,
+) -> Result<(), EncodeError> {
+	encode_value::encode_value_by_id(value, ty, metadata.types(), out)
+}
+
+/// Return the number of bytes that [`encode_value_by_id`] would append to its output given the same
+/// arguments, without allocating the output. This can be used to pre-size the output buffer.
+pub fn scale_size_hint<T, Id: Into<TypeId>>(
+	metadata: &Metadata,
+	ty: Id,
+	value: &Value<T>,
+) -> Result<usize, EncodeError> {
+	encode_value::scale_size_hint(value, ty, metadata.types())
+}
+
+/// Given a type definition, return the [`TypeId`] and [`scale_info::PortableRegistry`] that our encode
+/// functions expect. Shared by the tests of each of our submodules.
+#[cfg(test)]
+pub(crate) fn make_type<T: scale_info::TypeInfo + 'static>() -> (TypeId, scale_info::PortableRegistry) {
+	let m = scale_info::MetaType::new::<T>();
+	let mut types = scale_info::Registry::new();
+	let id = types.register_type(&m);
+	(id.into(), types.into())
+}
@@ -0,0 +1,199 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::encode_value::{encode_value_with_path, EncodeError};
+use crate::value::{path_to_pointer, PathSegment, Value};
+use crate::TypeId;
+use codec::Output;
+use scale_info::PortableRegistry;
+
+/// An error returned from [`validate_value`], pointing to the first part of a value
+/// which cannot be encoded into the corresponding type.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("Cannot encode the value at {}: {reason}", describe_path(.path))]
+pub struct ValidationError {
+	/// The path to the offending value, which can be handed to [`Value::at`].
+	pub path: Vec<PathSegment>,
+	/// Why the value at this path cannot be encoded.
+	pub reason: EncodeError,
+}
+
+/// Check that a [`Value`] can be SCALE encoded into the type with the [`TypeId`] given, without keeping
+/// any of the encoded bytes. The value is encoded exactly as it would be by [`super::encode_value_by_id`],
+/// and the first part of it which doesn't line up with its type is reported. A value which passes
+/// validation will encode successfully.
+pub fn validate_value<T, Id: Into<TypeId>>(
+	value: &Value<T>,
+	ty_id: Id,
+	types: &PortableRegistry,
+) -> Result<(), ValidationError> {
+	// On error, the path is left pointing at the offending value.
+	let mut path = Vec::new();
+	encode_value_with_path(value, ty_id, types, &mut NoOutput, &mut path)
+		.map_err(|reason| ValidationError { path, reason })
+}
+
+/// An [`Output`] which throws away anything written to it.
+struct NoOutput;
+
+impl Output for NoOutput {
+	fn write(&mut self, _bytes: &[u8]) {}
+}
+
+fn describe_path(path: &[PathSegment]) -> String {
+	if path.is_empty() {
+		return "the top level".to_string();
+	}
+	path_to_pointer(path)
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use crate::encoder::encode_value::encode_value_by_id;
+	use crate::encoder::make_type;
+	use crate::value::{Composite, Primitive};
+	use codec::Encode;
+	use scale_info::TypeDefPrimitive;
+
+	#[allow(dead_code)]
+	#[derive(Encode, scale_info::TypeInfo)]
+	enum Call {
+		Transfer { dest: [u8; 2], details: Details },
+		Remark(Vec<u8>),
+	}
+
+	#[derive(Encode, scale_info::TypeInfo)]
+	struct Details {
+		#[codec(compact)]
+		amount: u128,
+		memo: (bool, String),
+	}
+
+	fn transfer(details: Value<()>) -> Value<()> {
+		Value::variant(
+			"Transfer".into(),
+			Composite::Named(vec![
+				("dest".into(), Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)])),
+				("details".into(), details),
+			]),
+		)
+	}
+
+	fn details() -> Value<()> {
+		Value::named_composite(vec![
+			("memo".into(), Value::unnamed_composite(vec![Value::bool(true), Value::str("hi".into())])),
+			("amount".into(), Value::u128(100)),
+		])
+	}
+
+	#[test]
+	fn compatible_values_validate() {
+		let (id, types) = make_type::<Call>();
+		assert_eq!(validate_value(&transfer(details()), id, &types), Ok(()));
+
+		let remark = Value::variant("Remark".into(), Composite::Unnamed(vec![Value::unnamed_composite(vec![])]));
+		assert_eq!(validate_value(&remark, id, &types), Ok(()));
+
+		// Anything that validates can be encoded:
+		let mut out = Vec::new();
+		encode_value_by_id(&transfer(details()), id, &types, &mut out).expect("can encode");
+	}
+
+	#[test]
+	fn wrong_arity_composite_is_reported_with_path() {
+		let (id, types) = make_type::<Call>();
+		let mut value = transfer(details());
+		value
+			.replace_at(&["details".into(), "memo".into()], Value::unnamed_composite(vec![Value::bool(true)]))
+			.unwrap();
+
+		let err = validate_value(&value, id, &types).unwrap_err();
+		assert_eq!(err.path, vec!["details".into(), "memo".into()]);
+		assert!(matches!(err.reason, EncodeError::WrongLength { expected: 2, actual: 1, .. }));
+
+		// Values nested in sequences are pointed to by index:
+		let remark = Value::variant(
+			"Remark".into(),
+			Composite::Unnamed(vec![Value::unnamed_composite(vec![Value::u8(1), Value::u16(256)])]),
+		);
+		let err = validate_value(&remark, id, &types).unwrap_err();
+		assert_eq!(err.path, vec![0.into(), 1.into()]);
+		assert_eq!(err.reason, EncodeError::WrongPrimitive(Primitive::U16(256), TypeDefPrimitive::U8));
+		assert_eq!(err.to_string(), format!("Cannot encode the value at /0/1: {}", err.reason));
+	}
+
+	#[test]
+	fn integers_are_range_checked() {
+		let (id, types) = make_type::<u8>();
+		assert_eq!(validate_value(&Value::u128(1), id, &types), Ok(()));
+		assert_eq!(validate_value(&Value::i64(255), id, &types), Ok(()));
+		assert_eq!(
+			validate_value(&Value::u128(256), id, &types).unwrap_err().reason,
+			EncodeError::WrongPrimitive(Primitive::U128(256), TypeDefPrimitive::U8)
+		);
+		assert!(validate_value(&Value::i8(-1), id, &types).is_err());
+
+		// Compact encoded fields are range checked in the same way:
+		let (id, types) = make_type::<Call>();
+		let amount = |amount| {
+			transfer(Value::named_composite(vec![
+				("amount".into(), amount),
+				("memo".into(), Value::unnamed_composite(vec![Value::bool(true), Value::str("hi".into())])),
+			]))
+		};
+		assert_eq!(validate_value(&amount(Value::u8(100)), id, &types), Ok(()));
+		assert!(validate_value(&amount(Value::i32(-100)), id, &types).is_err());
+	}
+
+	#[test]
+	fn error_paths_are_escaped() {
+		let err =
+			ValidationError { path: vec!["a/b".into(), 0.into(), "~c".into()], reason: EncodeError::TypeIdNotFound(1) };
+		assert_eq!(err.to_string(), format!("Cannot encode the value at /a~1b/0/~0c: {}", err.reason));
+	}
+
+	#[test]
+	fn unknown_variant_is_reported() {
+		let (id, types) = make_type::<Call>();
+		let value = Value::variant("Nope".into(), Composite::Unnamed(vec![]));
+		let err = validate_value(&value, id, &types).unwrap_err();
+		assert_eq!(err.path, vec![]);
+		assert!(matches!(err.reason, EncodeError::VariantNotFound(ref name, _) if name == "Nope"));
+	}
+
+	#[test]
+	fn validation_fails_like_encoding() {
+		let (id, types) = make_type::<Call>();
+		let bad_values = vec![
+			transfer(Value::named_composite(vec![])),
+			transfer(Value::named_composite(vec![
+				("amount".into(), Value::i64(-100)),
+				("memo".into(), Value::unnamed_composite(vec![Value::bool(true), Value::str("hi".into())])),
+			])),
+			transfer(Value::named_composite(vec![
+				("amount".into(), Value::u128(100)),
+				("other".into(), Value::unnamed_composite(vec![Value::bool(true), Value::str("hi".into())])),
+			])),
+			Value::u8(1),
+		];
+		for value in bad_values {
+			let err = validate_value(&value, id, &types).unwrap_err();
+			assert_eq!(Err(err.reason), encode_value_by_id(&value, id, &types, &mut Vec::new()));
+		}
+	}
+}
@@ -20,6 +20,7 @@
 mod type_id;
 
 pub mod decoder;
+pub mod encoder;
 pub mod metadata;
 pub mod value;
 
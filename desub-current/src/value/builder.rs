@@ -0,0 +1,161 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{Composite, Value, ValueDef};
+
+/// A fluent interface for constructing [`Value`]s, which is handy for building up nested values in
+/// tests and fixtures. Fields and items can be given anything that converts into a [`Value`], which
+/// includes the native types that correspond to a [`super::Primitive`].
+///
+/// ```rust
+/// use desub_current::value::{Value, ValueBuilder};
+///
+/// let value = ValueBuilder::named()
+///     .field("a", 1u8)
+///     .field("b", ValueBuilder::unnamed().item(true).item("hello").build())
+///     .build();
+///
+/// assert_eq!(value, Value::named_composite(vec![
+///     ("a".into(), Value::u8(1)),
+///     ("b".into(), Value::unnamed_composite(vec![Value::bool(true), Value::str("hello".into())])),
+/// ]));
+/// ```
+pub struct ValueBuilder;
+
+impl ValueBuilder {
+	/// Build a named composite value.
+	pub fn named() -> NamedBuilder {
+		NamedBuilder { variant: None, values: Vec::new() }
+	}
+	/// Build an unnamed composite value.
+	pub fn unnamed() -> UnnamedBuilder {
+		UnnamedBuilder { variant: None, values: Vec::new() }
+	}
+	/// Build a variant value with the given name.
+	pub fn variant(name: impl Into<String>) -> VariantBuilder {
+		VariantBuilder { name: name.into() }
+	}
+	/// Build a primitive value from some native type, for instance a `u64` or `&str`.
+	pub fn primitive(val: impl Into<super::Primitive>) -> Value<()> {
+		Value::primitive(val.into())
+	}
+}
+
+/// Build a variant value; see [`ValueBuilder::variant()`].
+pub struct VariantBuilder {
+	name: String,
+}
+
+impl VariantBuilder {
+	/// Give the variant named fields.
+	pub fn named(self) -> NamedBuilder {
+		NamedBuilder { variant: Some(self.name), values: Vec::new() }
+	}
+	/// Give the variant unnamed fields.
+	pub fn unnamed(self) -> UnnamedBuilder {
+		UnnamedBuilder { variant: Some(self.name), values: Vec::new() }
+	}
+	/// Build a variant with no fields.
+	pub fn build(self) -> Value<()> {
+		Value::variant(self.name, Composite::Unnamed(Vec::new()))
+	}
+}
+
+/// Build a named composite value, or a variant with named fields; see [`ValueBuilder::named()`].
+pub struct NamedBuilder {
+	variant: Option<String>,
+	values: Vec<(String, Value<()>)>,
+}
+
+impl NamedBuilder {
+	/// Add a named field.
+	pub fn field(mut self, name: impl Into<String>, val: impl Into<Value<()>>) -> Self {
+		self.values.push((name.into(), val.into()));
+		self
+	}
+	/// Build the value.
+	pub fn build(self) -> Value<()> {
+		build(self.variant, Composite::Named(self.values))
+	}
+}
+
+/// Build an unnamed composite value, or a variant with unnamed fields; see [`ValueBuilder::unnamed()`].
+pub struct UnnamedBuilder {
+	variant: Option<String>,
+	values: Vec<Value<()>>,
+}
+
+impl UnnamedBuilder {
+	/// Add an unnamed field.
+	pub fn item(mut self, val: impl Into<Value<()>>) -> Self {
+		self.values.push(val.into());
+		self
+	}
+	/// Build the value.
+	pub fn build(self) -> Value<()> {
+		build(self.variant, Composite::Unnamed(self.values))
+	}
+}
+
+fn build(variant: Option<String>, values: Composite<()>) -> Value<()> {
+	match variant {
+		Some(name) => Value::variant(name, values),
+		None => Value { value: ValueDef::Composite(values), context: () },
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	#[test]
+	fn build_composites() {
+		let built = ValueBuilder::named()
+			.field("a", 1u8)
+			.field("b", true)
+			.field("c", ValueBuilder::unnamed().item('c').item("hello").item(Value::u128(2)).build())
+			.build();
+
+		let expected = Value::named_composite(vec![
+			("a".into(), Value::u8(1)),
+			("b".into(), Value::bool(true)),
+			("c".into(), Value::unnamed_composite(vec![Value::char('c'), Value::str("hello".into()), Value::u128(2)])),
+		]);
+
+		assert_eq!(built, expected);
+	}
+
+	#[test]
+	fn build_variants() {
+		assert_eq!(
+			ValueBuilder::variant("Foo").named().field("a", -1i32).build(),
+			Value::variant("Foo".into(), Composite::Named(vec![("a".into(), Value::i32(-1))]))
+		);
+		assert_eq!(
+			ValueBuilder::variant("Bar").unnamed().item(1u64).build(),
+			Value::variant("Bar".into(), Composite::Unnamed(vec![Value::u64(1)]))
+		);
+		assert_eq!(ValueBuilder::variant("Baz").build(), Value::variant("Baz".into(), Composite::Unnamed(vec![])));
+	}
+
+	#[test]
+	fn build_primitives() {
+		assert_eq!(ValueBuilder::primitive(1u16), Value::u16(1));
+		assert_eq!(ValueBuilder::primitive("hi"), Value::str("hi".into()));
+		assert_eq!(ValueBuilder::primitive(true), Value::bool(true));
+	}
+}
@@ -0,0 +1,393 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{Composite, DeserializeError, Primitive, Value, ValueDef};
+use std::convert::TryFrom;
+use std::iter::FromIterator;
+
+// Native types can be converted into the corresponding primitives, and from there into values.
+macro_rules! impl_from_native {
+	($($ty:ty => $variant:ident),*) => {$(
+		impl From<$ty> for Primitive {
+			fn from(val: $ty) -> Self {
+				Primitive::$variant(val)
+			}
+		}
+
+		impl From<$ty> for Value<()> {
+			fn from(val: $ty) -> Self {
+				Value::primitive(Primitive::$variant(val))
+			}
+		}
+	)*}
+}
+
+impl_from_native!(
+	bool => Bool, char => Char, String => Str,
+	u8 => U8, u16 => U16, u32 => U32, u64 => U64, u128 => U128,
+	i8 => I8, i16 => I16, i32 => I32, i64 => I64, i128 => I128,
+	f32 => F32, f64 => F64
+);
+
+impl From<&str> for Primitive {
+	fn from(val: &str) -> Self {
+		Primitive::Str(val.to_string())
+	}
+}
+
+impl From<&str> for Value<()> {
+	fn from(val: &str) -> Self {
+		Value::str(val.to_string())
+	}
+}
+
+impl From<Primitive> for Value<()> {
+	fn from(val: Primitive) -> Self {
+		Value::primitive(val)
+	}
+}
+
+impl<T> FromIterator<(String, Value<T>)> for Composite<T> {
+	fn from_iter<I: IntoIterator<Item = (String, Value<T>)>>(iter: I) -> Self {
+		Composite::Named(iter.into_iter().collect())
+	}
+}
+
+impl<T> FromIterator<Value<T>> for Composite<T> {
+	fn from_iter<I: IntoIterator<Item = Value<T>>>(iter: I) -> Self {
+		Composite::Unnamed(iter.into_iter().collect())
+	}
+}
+
+/// Consume a composite, handing back each value alongside its field name, which is `None` for
+/// the values of unnamed composites. This allows named and unnamed composites to be handled alike.
+impl<T> IntoIterator for Composite<T> {
+	type Item = (Option<String>, Value<T>);
+	type IntoIter = CompositeIntoIter<T>;
+	fn into_iter(self) -> Self::IntoIter {
+		match self {
+			Composite::Named(values) => CompositeIntoIter(CompositeIntoIterInner::Named(values.into_iter())),
+			Composite::Unnamed(values) => CompositeIntoIter(CompositeIntoIterInner::Unnamed(values.into_iter())),
+		}
+	}
+}
+
+/// An iterator over the names and values of a [`Composite`]. See its [`IntoIterator`] impl.
+#[derive(Debug)]
+pub struct CompositeIntoIter<T>(CompositeIntoIterInner<T>);
+
+#[derive(Debug)]
+enum CompositeIntoIterInner<T> {
+	Named(std::vec::IntoIter<(String, Value<T>)>),
+	Unnamed(std::vec::IntoIter<Value<T>>),
+}
+
+impl<T> Iterator for CompositeIntoIter<T> {
+	type Item = (Option<String>, Value<T>);
+	fn next(&mut self) -> Option<Self::Item> {
+		match &mut self.0 {
+			CompositeIntoIterInner::Named(values) => values.next().map(|(name, value)| (Some(name), value)),
+			CompositeIntoIterInner::Unnamed(values) => values.next().map(|value| (None, value)),
+		}
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		match &self.0 {
+			CompositeIntoIterInner::Named(values) => values.size_hint(),
+			CompositeIntoIterInner::Unnamed(values) => values.size_hint(),
+		}
+	}
+}
+
+impl<T> DoubleEndedIterator for CompositeIntoIter<T> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		match &mut self.0 {
+			CompositeIntoIterInner::Named(values) => values.next_back().map(|(name, value)| (Some(name), value)),
+			CompositeIntoIterInner::Unnamed(values) => values.next_back().map(|value| (None, value)),
+		}
+	}
+}
+
+impl<T> ExactSizeIterator for CompositeIntoIter<T> {}
+
+// Each integer type can be converted from any integer primitive whose value fits into it.
+macro_rules! impl_try_from_primitive_for_int {
+	($($ty:ty),*) => {$(
+		impl TryFrom<Primitive> for $ty {
+			type Error = DeserializeError;
+			fn try_from(prim: Primitive) -> Result<Self, Self::Error> {
+				let n = match &prim {
+					Primitive::U8(v) => <$ty>::try_from(*v).ok(),
+					Primitive::U16(v) => <$ty>::try_from(*v).ok(),
+					Primitive::U32(v) => <$ty>::try_from(*v).ok(),
+					Primitive::U64(v) => <$ty>::try_from(*v).ok(),
+					Primitive::U128(v) => <$ty>::try_from(*v).ok(),
+					Primitive::U256(v) => u256_to_u128(v).and_then(|v| <$ty>::try_from(v).ok()),
+					Primitive::I8(v) => <$ty>::try_from(*v).ok(),
+					Primitive::I16(v) => <$ty>::try_from(*v).ok(),
+					Primitive::I32(v) => <$ty>::try_from(*v).ok(),
+					Primitive::I64(v) => <$ty>::try_from(*v).ok(),
+					Primitive::I128(v) => <$ty>::try_from(*v).ok(),
+					Primitive::I256(v) => i256_to_i128(v).and_then(|v| <$ty>::try_from(v).ok()),
+					Primitive::Bool(_)
+					| Primitive::Char(_)
+					| Primitive::Str(_)
+					| Primitive::F32(_)
+					| Primitive::F64(_) => {
+						return Err(wrong_primitive(&prim, stringify!($ty)))
+					}
+				};
+				n.ok_or_else(|| {
+					DeserializeError::from_string(format!("{:?} does not fit into a {}", prim, stringify!($ty)))
+				})
+			}
+		}
+	)*}
+}
+
+impl_try_from_primitive_for_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl TryFrom<Primitive> for bool {
+	type Error = DeserializeError;
+	fn try_from(prim: Primitive) -> Result<Self, Self::Error> {
+		match prim {
+			Primitive::Bool(b) => Ok(b),
+			other => Err(wrong_primitive(&other, "bool")),
+		}
+	}
+}
+
+impl TryFrom<Primitive> for char {
+	type Error = DeserializeError;
+	fn try_from(prim: Primitive) -> Result<Self, Self::Error> {
+		match prim {
+			Primitive::Char(c) => Ok(c),
+			other => Err(wrong_primitive(&other, "char")),
+		}
+	}
+}
+
+impl TryFrom<Primitive> for String {
+	type Error = DeserializeError;
+	fn try_from(prim: Primitive) -> Result<Self, Self::Error> {
+		match prim {
+			Primitive::Str(s) => Ok(s),
+			other => Err(wrong_primitive(&other, "String")),
+		}
+	}
+}
+
+impl<T> TryFrom<&Value<T>> for Vec<u8> {
+	type Error = DeserializeError;
+	/// Unnamed composites containing only `U8` values (which is how a `Vec<u8>` or `[u8; N]` is decoded) are
+	/// converted into those bytes, and 256 bit integers into their 32 little endian bytes.
+	fn try_from(value: &Value<T>) -> Result<Self, Self::Error> {
+		match &value.value {
+			ValueDef::Composite(Composite::Unnamed(vals)) => vals
+				.iter()
+				.enumerate()
+				.map(|(idx, val)| match val.value {
+					ValueDef::Primitive(Primitive::U8(b)) => Ok(b),
+					_ => Err(DeserializeError::from_string(format!(
+						"Cannot convert the composite into bytes: the value at index {} is a {}, not a u8",
+						idx,
+						val.type_name()
+					))),
+				})
+				.collect(),
+			ValueDef::Primitive(Primitive::U256(bytes) | Primitive::I256(bytes)) => Ok(bytes.to_vec()),
+			_ => Err(wrong_value(value, "Vec<u8>")),
+		}
+	}
+}
+
+impl<T> TryFrom<&Value<T>> for String {
+	type Error = DeserializeError;
+	fn try_from(value: &Value<T>) -> Result<Self, Self::Error> {
+		match &value.value {
+			ValueDef::Primitive(Primitive::Str(s)) => Ok(s.clone()),
+			_ => Err(wrong_value(value, "String")),
+		}
+	}
+}
+
+impl<T> TryFrom<&Value<T>> for bool {
+	type Error = DeserializeError;
+	fn try_from(value: &Value<T>) -> Result<Self, Self::Error> {
+		match value.value {
+			ValueDef::Primitive(Primitive::Bool(b)) => Ok(b),
+			_ => Err(wrong_value(value, "bool")),
+		}
+	}
+}
+
+fn wrong_value<T>(value: &Value<T>, target: &str) -> DeserializeError {
+	DeserializeError::from_string(format!("Cannot convert a {} value into a {}", value.type_name(), target))
+}
+
+fn wrong_primitive(prim: &Primitive, target: &str) -> DeserializeError {
+	DeserializeError::from_string(format!("Cannot convert {:?} into a {}", prim, target))
+}
+
+// Interpret 32 little endian bytes as an unsigned integer, handing it back
+// if the high bytes are all zero and so it fits into a u128.
+pub(crate) fn u256_to_u128(bytes: &[u8; 32]) -> Option<u128> {
+	let (low, high) = bytes.split_at(16);
+	if high.iter().any(|b| *b != 0) {
+		return None;
+	}
+	Some(u128::from_le_bytes(low.try_into().expect("16 bytes")))
+}
+
+// Interpret 32 little endian bytes as a two's complement signed integer, handing it
+// back if the high bytes are just a sign extension of the low ones and so it fits into an i128.
+pub(crate) fn i256_to_i128(bytes: &[u8; 32]) -> Option<i128> {
+	let (low, high) = bytes.split_at(16);
+	let n = i128::from_le_bytes(low.try_into().expect("16 bytes"));
+	let extension = if n < 0 { 0xFF } else { 0x00 };
+	if high.iter().any(|b| *b != extension) {
+		return None;
+	}
+	Some(n)
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	#[test]
+	fn native_into_value() {
+		assert_eq!(Value::from(1u8), Value::u8(1));
+		assert_eq!(Value::from(-1i64), Value::i64(-1));
+		assert_eq!(Value::from("hi"), Value::str("hi".into()));
+		assert_eq!(Primitive::from(true), Primitive::Bool(true));
+		assert_eq!(Primitive::from('a'), Primitive::Char('a'));
+	}
+
+	#[test]
+	fn collect_into_composites() {
+		let named: Composite<()> =
+			vec![("a", 1u8), ("b", 2u8)].into_iter().map(|(k, v)| (k.to_string(), v.into())).collect();
+		assert_eq!(named, Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::u8(2))]));
+
+		let unnamed: Composite<()> = (1..=3u8).map(Value::u8).collect();
+		assert_eq!(unnamed, Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]));
+	}
+
+	#[test]
+	fn iterate_over_composites() {
+		let named = Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]);
+		let unnamed = Composite::Unnamed(vec![Value::u8(1), Value::bool(true)]);
+
+		let mut names = Vec::new();
+		let mut values = Vec::new();
+		for composite in [named, unnamed] {
+			for (name, value) in composite {
+				names.push(name);
+				values.push(value);
+			}
+		}
+		assert_eq!(names, vec![Some("a".to_string()), Some("b".to_string()), None, None]);
+		assert_eq!(values, vec![Value::u8(1), Value::bool(true), Value::u8(1), Value::bool(true)]);
+
+		let iter = Composite::Unnamed(vec![Value::u8(1), Value::u8(2)]).into_iter();
+		assert_eq!(iter.len(), 2);
+		assert_eq!(iter.rev().next(), Some((None, Value::u8(2))));
+	}
+
+	#[test]
+	fn primitive_into_narrower_int() {
+		assert_eq!(u8::try_from(Primitive::U64(255)), Ok(255));
+		assert_eq!(i8::try_from(Primitive::U128(127)), Ok(127));
+		assert_eq!(u64::try_from(Primitive::I32(100)), Ok(100));
+		assert_eq!(i16::try_from(Primitive::I64(-300)), Ok(-300));
+	}
+
+	#[test]
+	fn primitive_into_int_overflows() {
+		u8::try_from(Primitive::U16(256)).expect_err("too large");
+		i8::try_from(Primitive::I16(-129)).expect_err("too small");
+		u128::try_from(Primitive::Bool(true)).expect_err("not a number");
+	}
+
+	#[test]
+	fn primitive_into_int_sign_mismatch() {
+		u8::try_from(Primitive::I8(-1)).expect_err("negative into unsigned");
+		i64::try_from(Primitive::U64(u64::MAX)).expect_err("too large for signed");
+		assert_eq!(i64::try_from(Primitive::U64(i64::MAX as u64)), Ok(i64::MAX));
+	}
+
+	#[test]
+	fn wide_primitives_into_int() {
+		let mut u256 = [0u8; 32];
+		u256[..16].copy_from_slice(&u128::MAX.to_le_bytes());
+		assert_eq!(u128::try_from(Primitive::U256(u256)), Ok(u128::MAX));
+		u64::try_from(Primitive::U256(u256)).expect_err("too large");
+		u256[16] = 1;
+		u128::try_from(Primitive::U256(u256)).expect_err("high bytes set");
+
+		let minus_one = [0xFF; 32];
+		assert_eq!(i128::try_from(Primitive::I256(minus_one)), Ok(-1));
+		assert_eq!(i8::try_from(Primitive::I256(minus_one)), Ok(-1));
+		u128::try_from(Primitive::I256(minus_one)).expect_err("negative into unsigned");
+
+		let mut i256_min = [0u8; 32];
+		i256_min[31] = 0x80;
+		i128::try_from(Primitive::I256(i256_min)).expect_err("too small");
+	}
+
+	#[test]
+	fn primitive_into_non_ints() {
+		assert_eq!(bool::try_from(Primitive::Bool(true)), Ok(true));
+		assert_eq!(char::try_from(Primitive::Char('a')), Ok('a'));
+		assert_eq!(String::try_from(Primitive::Str("hi".into())), Ok("hi".to_string()));
+		bool::try_from(Primitive::U8(1)).expect_err("not a bool");
+		String::try_from(Primitive::Char('a')).expect_err("not a string");
+	}
+
+	#[test]
+	fn value_ref_into_bytes() {
+		let bytes = Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)]);
+		assert_eq!(Vec::<u8>::try_from(&bytes), Ok(vec![1, 2]));
+		assert_eq!(Vec::<u8>::try_from(&Value::unnamed_composite(vec![])), Ok(vec![]));
+
+		let mut u256 = [0u8; 32];
+		u256[0] = 1;
+		assert_eq!(Vec::<u8>::try_from(&Value::primitive(Primitive::U256(u256))), Ok(u256.to_vec()));
+
+		let not_bytes = Value::unnamed_composite(vec![Value::u8(1), Value::u16(2)]);
+		assert_eq!(
+			Vec::<u8>::try_from(&not_bytes).unwrap_err().to_string(),
+			"Cannot convert the composite into bytes: the value at index 1 is a u16, not a u8"
+		);
+		assert_eq!(
+			Vec::<u8>::try_from(&Value::named_composite(vec![])).unwrap_err().to_string(),
+			"Cannot convert a named composite value into a Vec<u8>"
+		);
+	}
+
+	#[test]
+	fn value_ref_into_string_and_bool() {
+		assert_eq!(String::try_from(&Value::str("hi".into())), Ok("hi".to_string()));
+		assert_eq!(bool::try_from(&Value::bool(true)), Ok(true));
+
+		assert_eq!(
+			String::try_from(&Value::char('a')).unwrap_err().to_string(),
+			"Cannot convert a char value into a String"
+		);
+		assert_eq!(bool::try_from(&Value::u8(1)).unwrap_err().to_string(), "Cannot convert a u8 value into a bool");
+	}
+}
@@ -115,6 +115,8 @@ impl<'de> Visitor<'de> for PrimitiveVisitor {
 	visit_prim!(visit_u32 u32 U32);
 	visit_prim!(visit_u64 u64 U64);
 	visit_prim!(visit_u128 u128 U128);
+	visit_prim!(visit_f32 f32 F32);
+	visit_prim!(visit_f64 f64 F64);
 	visit_prim!(visit_char char Char);
 
 	fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -244,11 +246,12 @@ impl<'de> Visitor<'de> for VariantVisitor {
 		data.variant().and_then(|(name, variant_access)| {
 			use serde::de::VariantAccess;
 			// We have to ask for a particular enum type, but we don't know what type
-			// of enum to expect (we support anything!). So, we just call the visitor method
-			// that doesn't require any extra fields, and we know that this will just give back
-			// whatever it can based on our impl (who knows about other impls though).
-			let values = variant_access.newtype_variant()?;
-			Ok(Variant { name, values })
+			// of enum to expect (we support anything!). So, we ask for a struct variant
+			// and hand our composite visitor over, and we know that this will just give back
+			// whatever it can based on our impl (who knows about other impls though). We
+			// avoid asking for a newtype variant, since that would unwrap single values.
+			let values = variant_access.struct_variant(&[], CompositeVisitor)?;
+			Ok(Variant { name, values, index: None })
 		})
 	}
 
@@ -276,7 +279,7 @@ impl<'de> Visitor<'de> for VariantVisitor {
 		}
 
 		if let (Some(name), Some(values)) = (name, values) {
-			Ok(Variant { name, values })
+			Ok(Variant { name, values, index: None })
 		} else {
 			Err(A::Error::custom("map must contain 'name' and 'values' to deserialize to a Variant"))
 		}
@@ -321,6 +324,8 @@ impl<'de> Visitor<'de> for ValueDefVisitor {
 		visit_u32(u32)
 		visit_u64(u64)
 		visit_u128(u128)
+		visit_f32(f32)
+		visit_f64(f64)
 		visit_char(char)
 		visit_str(&str)
 		visit_string(String)
@@ -407,6 +412,8 @@ mod test {
 		assert_value_isomorphic(Value::bool(true));
 		assert_value_isomorphic(Value::char('a'));
 		assert_value_isomorphic(Value::str("Hello!".into()));
+		assert_value_isomorphic(Value::f32(1.5));
+		assert_value_isomorphic(Value::f64(-2.25));
 
 		// Alas, I256 and U256 are both a sequence of bytes, which could equally be represented
 		// by a composite sequence (as other sequences-of-things are). We could have a special case where
@@ -495,11 +502,17 @@ mod test {
 		assert_value_isomorphic(ValueDef::Variant(Variant {
 			name: "Foo".into(),
 			values: Composite::Unnamed(vec![Value::u64(123), Value::bool(true)]),
+			index: None,
+		}));
+		assert_value_isomorphic(ValueDef::Variant(Variant {
+			name: "Foo".into(),
+			values: Composite::Unnamed(vec![]),
+			index: None,
 		}));
-		assert_value_isomorphic(ValueDef::Variant(Variant { name: "Foo".into(), values: Composite::Unnamed(vec![]) }));
 		assert_value_isomorphic(ValueDef::Variant(Variant {
 			name: "Foo".into(),
 			values: Composite::Named(vec![("a".into(), Value::u64(123)), ("b".into(), Value::bool(true))]),
+			index: None,
 		}));
 
 		// unwrapped work as well:
@@ -507,11 +520,18 @@ mod test {
 		assert_value_isomorphic(Variant {
 			name: "Foo".into(),
 			values: Composite::Unnamed(vec![Value::u64(123), Value::bool(true)]),
+			index: None,
+		});
+		assert_value_isomorphic(Variant { name: "Foo".into(), values: Composite::Unnamed(vec![]), index: None });
+		assert_value_isomorphic(Variant {
+			name: "Foo".into(),
+			values: Composite::Unnamed(vec![Value::u64(123)]),
+			index: None,
 		});
-		assert_value_isomorphic(Variant { name: "Foo".into(), values: Composite::Unnamed(vec![]) });
 		assert_value_isomorphic(Variant {
 			name: "Foo".into(),
 			values: Composite::Named(vec![("a".into(), Value::u64(123)), ("b".into(), Value::bool(true))]),
+			index: None,
 		});
 	}
 
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::convert::{i256_to_i128, u256_to_u128};
 use super::{BitSequence, Composite, Primitive, Value, ValueDef, Variant};
 use serde::{
 	de::{self, EnumAccess, IntoDeserializer, SeqAccess, VariantAccess},
@@ -21,6 +22,7 @@ use serde::{
 };
 use std::borrow::Cow;
 use std::fmt::Display;
+use std::ops::Deref;
 
 /*
 This module implements the Deserializer trait on our Value enum
@@ -71,10 +73,10 @@ various "special cases" though (like newtype wrapper structs) where we try to be
 pub struct Error(Cow<'static, str>);
 
 impl Error {
-	fn from_string<S: Into<String>>(s: S) -> Error {
+	pub(crate) fn from_string<S: Into<String>>(s: S) -> Error {
 		Error(Cow::Owned(s.into()))
 	}
-	fn from_str(s: &'static str) -> Error {
+	pub(crate) fn from_str(s: &'static str) -> Error {
 		Error(Cow::Borrowed(s))
 	}
 }
@@ -90,152 +92,574 @@ impl ser::Error for Error {
 	}
 }
 
-/// Spit out the simple deserialize methods to avoid loads of repetition.
-macro_rules! deserialize_x {
-	($fn_name:ident) => {
-		fn $fn_name<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+/// Values handed to `deserialize_ignored_any` are being skipped over, so there's no need to look inside
+/// them (or build deserializers for their contents); we just tell the visitor that there's nothing there.
+macro_rules! deserialize_ignored_any_as_unit {
+	() => {
+		fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 		where
 			V: de::Visitor<'de>,
 		{
-			self.value.$fn_name(visitor)
+			visitor.visit_unit()
 		}
 	};
 }
 
-// Our Value type has some context, which we ignore, and some definition, whose deserializer
-// impl we forward to.
-impl<'de, T> Deserializer<'de> for Value<T> {
-	type Error = Error;
+/// Options which tweak how a [`Value`] is deserialized; see [`DeserializeWith`]. Options apply to the value
+/// being deserialized and to every value nested inside it. The default options deserialize values in the same
+/// way as the [`Deserializer`] impl on [`Value`] does.
+///
+/// Wrappers like [`ByIndex`] and [`ParseFloats`] deserialize a value with just one of these options set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeserializeOptions {
+	/// Deserialize a composite containing exactly one value into a scalar type (like a `u32`, `bool` or
+	/// `String`) by handing back that value. This allows one-field wrapper types (for instance, a decoded
+	/// `struct Wrapper(u32)`) to be deserialized straight into the type that they wrap, and wrappers around
+	/// wrappers are unwrapped all the way down. Other targets (like tuples, newtype structs and sequences)
+	/// are unaffected. Disabled by default, so that composites only deserialize into scalars if asked to.
+	pub unwrap_single_values: bool,
+	/// When deserializing a composite into a tuple struct whose length doesn't line up, but the composite
+	/// contains a single value which is itself a composite of the right length, deserialize from that inner
+	/// composite instead. Only one layer of wrapping is removed in this way; a composite wrapped in two or
+	/// more single-value composites will still fail to deserialize. Disabled by default.
+	pub unwrap_tuple_structs: bool,
+	/// Resolve enum variants by their [`Variant::index`] rather than by their name. This is useful when the
+	/// names of the variants in the target enum don't line up with the names of the decoded variants (for
+	/// instance, because a variant has been renamed in the runtime), but the order of them does. Variants
+	/// must have their index set, which is the case for values that have been decoded from SCALE bytes. This
+	/// is the position of the variant in its enum, which is what serde expects, rather than any
+	/// `#[codec(index = N)]` that it's encoded with. Disabled by default.
+	pub by_index: bool,
+	/// How the names of decoded variants are compared against the names of the variants in the target enum.
+	/// Defaults to [`VariantMatch::Exact`].
+	pub variant_match: VariantMatch,
+	/// If set, a variant that isn't one of the target enum's variants is deserialized into the variant with
+	/// this name instead. This must be a unit variant, since the values of the unknown variant are discarded.
+	/// When resolving variants by index, variants whose index is out of range are handled in the same way.
+	/// This helps with forward compatibility, for instance when new variants are added to an enum in the
+	/// runtime. Not set by default.
+	pub fallback_variant: Option<&'static str>,
+	/// Parse a [`Primitive::Str`] into a float when an `f32` or `f64` is asked for. This accommodates decimal
+	/// numbers that ended up as strings (for instance, having come from some JSON-like source). Composites
+	/// containing exactly one value are looked inside for such strings, as they would be with
+	/// [`DeserializeOptions::unwrap_single_values`] set. Disabled by default, so that strings are not coerced
+	/// into floats.
+	pub parse_floats: bool,
+	/// Match the names of fields loosely against the fields of the target struct. A field whose name doesn't
+	/// exactly match one of the struct's fields is matched ignoring case and any underscores or hyphens, so
+	/// that (for instance) a decoded `blockNumber` field will be deserialized into a struct field called
+	/// `block_number`. If more than one struct field matches in this way, then none of them are picked. When
+	/// the fields aren't known (for instance when deserializing into a map, or into a struct containing a
+	/// `#[serde(flatten)]` field), field names are converted to snake case instead. Disabled by default.
+	pub loose_field_names: bool,
+	/// Hand [`Composite::Unnamed`] values made up entirely of [`Primitive::U8`]s to `deserialize_any` calls as
+	/// bytes (via `visit_byte_buf`) rather than as a sequence of values. This is much more efficient for generic
+	/// value types which call `deserialize_any` and know how to handle bytes. Empty composites are not treated
+	/// as bytes, and other deserialize calls are unaffected. Disabled by default.
+	pub any_bytes: bool,
+	/// If set, variants are presented as maps (rather than as enums) whose entries are the fields of the
+	/// variant plus an entry whose key is this tag, and whose value is the name of the variant. This allows a
+	/// variant to be deserialized into an internally tagged enum (`#[serde(tag = "...")]`), and makes it
+	/// possible to `#[serde(flatten)]` such an enum into some larger struct (for instance, to model calls as
+	/// flat rows). Unnamed fields are given the keys `"0"`, `"1"` and so on. Variants are still deserialized
+	/// as enums when an enum is asked for. Not set by default.
+	pub variant_tag: Option<&'static str>,
+}
+
+impl Default for DeserializeOptions {
+	fn default() -> Self {
+		DeserializeOptions {
+			unwrap_single_values: false,
+			unwrap_tuple_structs: false,
+			by_index: false,
+			variant_match: VariantMatch::Exact,
+			fallback_variant: None,
+			parse_floats: false,
+			loose_field_names: false,
+			any_bytes: false,
+			variant_tag: None,
+		}
+	}
+}
 
-	deserialize_x!(deserialize_any);
-	deserialize_x!(deserialize_bool);
-	deserialize_x!(deserialize_i8);
-	deserialize_x!(deserialize_i16);
-	deserialize_x!(deserialize_i32);
-	deserialize_x!(deserialize_i64);
-	deserialize_x!(deserialize_i128);
-	deserialize_x!(deserialize_u8);
-	deserialize_x!(deserialize_u16);
-	deserialize_x!(deserialize_u32);
-	deserialize_x!(deserialize_u64);
-	deserialize_x!(deserialize_u128);
-	deserialize_x!(deserialize_f32);
-	deserialize_x!(deserialize_f64);
-	deserialize_x!(deserialize_char);
-	deserialize_x!(deserialize_str);
-	deserialize_x!(deserialize_string);
-	deserialize_x!(deserialize_bytes);
-	deserialize_x!(deserialize_byte_buf);
-	deserialize_x!(deserialize_option);
-	deserialize_x!(deserialize_unit);
-	deserialize_x!(deserialize_seq);
-	deserialize_x!(deserialize_map);
-	deserialize_x!(deserialize_identifier);
-	deserialize_x!(deserialize_ignored_any);
+/// Wrap a [`Value`] in this before deserializing it to have it deserialized according to the
+/// [`DeserializeOptions`] given.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeserializeWith<T>(pub Value<T>, pub DeserializeOptions);
+
+/// Wrap a [`Value`] in this before deserializing it to have composites containing exactly one value
+/// deserialized into scalar types by handing back that value; see [`DeserializeOptions::unwrap_single_values`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnwrapSingleValues<T>(pub Value<T>);
+
+/// Wrap a [`Value`] in this before deserializing it to have a single layer of wrapping removed from
+/// composites that don't otherwise line up with the tuple struct that they're being deserialized into;
+/// see [`DeserializeOptions::unwrap_tuple_structs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnwrapTupleStructs<T>(pub Value<T>);
+
+/// Wrap a [`Value`] in this before deserializing it to have enum variants resolved by their index
+/// rather than by their name; see [`DeserializeOptions::by_index`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ByIndex<T>(pub Value<T>);
+
+/// How the name of a decoded variant is compared against the names of the variants in some target enum.
+/// See [`DeserializeOptions::variant_match`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantMatch {
+	/// Variant names must match exactly. This is what happens when deserializing a [`Value`] without
+	/// wrapping it in [`WithVariantMatch`].
+	Exact,
+	/// If no target variant name matches exactly, then names are compared ignoring case and any
+	/// underscores or hyphens, so that (for instance) a decoded `AllGood` variant will match a target
+	/// variant called `all_good`. If more than one target variant matches in this way, then none of
+	/// them are picked.
+	Loose,
+}
 
-	fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.value.deserialize_unit_struct(name, visitor)
+impl VariantMatch {
+	// Given the decoded name, hand back the target variant that it matched if serde should be given that
+	// name rather than the decoded one.
+	fn resolve(self, name: &str, variants: &'static [&'static str]) -> Option<&'static str> {
+		match self {
+			VariantMatch::Exact => None,
+			VariantMatch::Loose => loose_match(name, variants),
+		}
 	}
+}
 
-	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.value.deserialize_newtype_struct(name, visitor)
+// Hand back the one candidate that the given name matches loosely (ignoring case and any underscores or
+// hyphens). If the name matches a candidate exactly, or no single candidate matches, nothing is handed back.
+fn loose_match(name: &str, candidates: &'static [&'static str]) -> Option<&'static str> {
+	if candidates.iter().any(|c| *c == name) {
+		return None;
 	}
 
-	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.value.deserialize_tuple(len, visitor)
+	let normalized_name = loose_name(name);
+	let mut matches = candidates.iter().filter(|c| loose_name(c) == normalized_name);
+
+	match (matches.next(), matches.next()) {
+		(Some(candidate), None) => Some(*candidate),
+		_ => None,
 	}
+}
 
-	fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.value.deserialize_tuple_struct(name, len, visitor)
+// Normalize a name so that names which differ only in case, underscores and hyphens are equal.
+fn loose_name(s: &str) -> String {
+	s.chars().filter(|c| *c != '_' && *c != '-').flat_map(|c| c.to_lowercase()).collect()
+}
+
+// Convert a name to snake case, so that (for instance) `blockNumber` and `Block-Number` both become `block_number`.
+fn snake_case(s: &str) -> String {
+	let mut out = String::with_capacity(s.len());
+	let mut after_lowercase = false;
+	for c in s.chars() {
+		if c == '_' || c == '-' {
+			out.push('_');
+			after_lowercase = false;
+			continue;
+		}
+		if c.is_uppercase() && after_lowercase {
+			out.push('_');
+		}
+		after_lowercase = c.is_lowercase() || c.is_ascii_digit();
+		out.extend(c.to_lowercase());
 	}
+	out
+}
 
-	fn deserialize_struct<V>(
-		self,
-		name: &'static str,
-		fields: &'static [&'static str],
-		visitor: V,
-	) -> Result<V::Value, Self::Error>
+/// Wrap a [`Value`] in this before deserializing it to control how the names of enum variants are
+/// matched up; see [`DeserializeOptions::variant_match`]. This is useful when variant names in the runtime
+/// are `PascalCase`, but the target enum uses some other casing (for instance via
+/// `#[serde(rename_all = "snake_case")]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithVariantMatch<T>(pub Value<T>, pub VariantMatch);
+
+/// Wrap a [`Value`] in this before deserializing it to have any variant (wherever it is nested) whose name
+/// isn't one of the target enum's variants deserialized into the fallback unit variant given instead; see
+/// [`DeserializeOptions::fallback_variant`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WithFallbackVariant<T>(pub Value<T>, pub &'static str);
+
+/// Wrap a [`Value`] in this before deserializing it to have strings parsed into floats when an `f32` or
+/// `f64` is asked for; see [`DeserializeOptions::parse_floats`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseFloats<T>(pub Value<T>);
+
+/// Wrap a [`Value`] in this before deserializing it to have the names of fields matched loosely against
+/// the field names of the target struct; see [`DeserializeOptions::loose_field_names`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LooseFieldNames<T>(pub Value<T>);
+
+/// Wrap a [`Value`] in this before deserializing it to have composites made up entirely of bytes handed
+/// to `deserialize_any` calls as bytes; see [`DeserializeOptions::any_bytes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnyBytes<T>(pub Value<T>);
+
+/// Wrap a [`Value`] in this before deserializing it to have variants presented as maps containing the
+/// tag given; see [`DeserializeOptions::variant_tag`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaggedVariant<T>(pub Value<T>, pub &'static str);
+
+/*
+Owned values are taken apart, references are pointed into
+=========================================================
+
+Whether we're given a value or a reference to one, deserializing is handed off to `ValueDe`, which holds
+some part of the value. What it holds (and so how it hands things out) is decided by a `Hold` impl:
+
+- For owned values, `ValueDe` owns that part of the value, and takes it apart as it goes, so that strings
+  and the like are moved out rather than copied.
+- For references to values, `ValueDe` holds a reference into the value, and hands things out by reference
+  so that targets like `&str` can borrow from them (see `from_value_ref`).
+
+Either way, the rest of the code only needs to be written once, and can look at whatever it holds via `Deref`.
+*/
+
+// How a `ValueDe` holds the parts of the value that it's deserializing from, and hands them over to serde.
+trait Hold<'de, T>: Sized {
+	type Composite: Deref<Target = Composite<T>>;
+	type Variant: Deref<Target = Variant<T>>;
+	type BitSequence: Deref<Target = BitSequence>;
+	type Primitive: Deref<Target = Primitive>;
+	type PrimitiveDe: Deserializer<'de, Error = Error>;
+	type Name: Deserializer<'de, Error = Error>;
+
+	// Hand a primitive over to serde.
+	fn primitive(prim: Self::Primitive) -> Self::PrimitiveDe;
+	// Split a variant into its name (ready to hand over to serde) and its values.
+	fn variant(variant: Self::Variant) -> (Self::Name, Self::Composite);
+	// Hand back the value at the given index in some composite. Each value is asked for at most once.
+	fn take(composite: &mut Self::Composite, idx: usize) -> Node<'de, T, Self>;
+}
+
+// Own the value being deserialized from, so that the value needn't outlive the deserialized output.
+struct Owned;
+
+// Some owned part of a value, which derefs to the part itself to line up with the references held by `Borrowed`.
+struct Held<P>(P);
+
+impl<P> Deref for Held<P> {
+	type Target = P;
+	fn deref(&self) -> &P {
+		&self.0
+	}
+}
+
+impl<'de, T> Hold<'de, T> for Owned {
+	type Composite = Held<Composite<T>>;
+	type Variant = Held<Variant<T>>;
+	type BitSequence = Held<BitSequence>;
+	type Primitive = Held<Primitive>;
+	type PrimitiveDe = Primitive;
+	type Name = de::value::StringDeserializer<Error>;
+
+	fn primitive(prim: Self::Primitive) -> Self::PrimitiveDe {
+		prim.0
+	}
+	fn variant(variant: Self::Variant) -> (Self::Name, Self::Composite) {
+		let Variant { name, values, .. } = variant.0;
+		(name.into_deserializer(), Held(values))
+	}
+	fn take(composite: &mut Self::Composite, idx: usize) -> Node<'de, T, Self> {
+		let value = match &mut composite.0 {
+			Composite::Named(values) => &mut values[idx].1.value,
+			Composite::Unnamed(values) => &mut values[idx].value,
+		};
+		// Swapping in an empty composite doesn't allocate, and leaves the names of any fields in place:
+		Node::from(std::mem::replace(value, ValueDef::Composite(Composite::Unnamed(Vec::new()))))
+	}
+}
+
+// Point into the value being deserialized from, so that the deserialized output can borrow from it.
+struct Borrowed;
+
+impl<'de, T: 'de> Hold<'de, T> for Borrowed {
+	type Composite = &'de Composite<T>;
+	type Variant = &'de Variant<T>;
+	type BitSequence = &'de BitSequence;
+	type Primitive = &'de Primitive;
+	type PrimitiveDe = &'de Primitive;
+	type Name = de::value::BorrowedStrDeserializer<'de, Error>;
+
+	fn primitive(prim: Self::Primitive) -> Self::PrimitiveDe {
+		prim
+	}
+	fn variant(variant: Self::Variant) -> (Self::Name, Self::Composite) {
+		(de::value::BorrowedStrDeserializer::new(&variant.name), &variant.values)
+	}
+	fn take(composite: &mut Self::Composite, idx: usize) -> Node<'de, T, Self> {
+		let composite: &'de Composite<T> = composite;
+		Node::from(&composite[idx])
+	}
+}
+
+// The part of a value that a `ValueDe` holds. The context of a value is ignored.
+enum Node<'de, T, H: Hold<'de, T>> {
+	Composite(H::Composite),
+	Variant(H::Variant),
+	BitSequence(H::BitSequence),
+	Primitive(H::Primitive),
+}
+
+impl<'de, T> From<ValueDef<T>> for Node<'de, T, Owned> {
+	fn from(value: ValueDef<T>) -> Self {
+		match value {
+			ValueDef::Composite(composite) => Node::Composite(Held(composite)),
+			ValueDef::Variant(variant) => Node::Variant(Held(variant)),
+			ValueDef::BitSequence(seq) => Node::BitSequence(Held(seq)),
+			ValueDef::Primitive(prim) => Node::Primitive(Held(prim)),
+		}
+	}
+}
+
+impl<'de, T> From<Value<T>> for Node<'de, T, Owned> {
+	fn from(value: Value<T>) -> Self {
+		Node::from(value.value)
+	}
+}
+
+impl<'de, T> From<Composite<T>> for Node<'de, T, Owned> {
+	fn from(composite: Composite<T>) -> Self {
+		Node::Composite(Held(composite))
+	}
+}
+
+impl<'de, T> From<Variant<T>> for Node<'de, T, Owned> {
+	fn from(variant: Variant<T>) -> Self {
+		Node::Variant(Held(variant))
+	}
+}
+
+impl<'de, T> From<&'de ValueDef<T>> for Node<'de, T, Borrowed> {
+	fn from(value: &'de ValueDef<T>) -> Self {
+		match value {
+			ValueDef::Composite(composite) => Node::Composite(composite),
+			ValueDef::Variant(variant) => Node::Variant(variant),
+			ValueDef::BitSequence(seq) => Node::BitSequence(seq),
+			ValueDef::Primitive(prim) => Node::Primitive(prim),
+		}
+	}
+}
+
+impl<'de, T> From<&'de Value<T>> for Node<'de, T, Borrowed> {
+	fn from(value: &'de Value<T>) -> Self {
+		Node::from(&value.value)
+	}
+}
+
+impl<'de, T> From<&'de Composite<T>> for Node<'de, T, Borrowed> {
+	fn from(composite: &'de Composite<T>) -> Self {
+		Node::Composite(composite)
+	}
+}
+
+impl<'de, T> From<&'de Variant<T>> for Node<'de, T, Borrowed> {
+	fn from(variant: &'de Variant<T>) -> Self {
+		Node::Variant(variant)
+	}
+}
+
+// This is the deserializer that all of our value types hand off to. `H` decides how the value is held, and the
+// options given are applied to everything nested inside the value that we hold.
+struct ValueDe<'de, T, H: Hold<'de, T>> {
+	node: Node<'de, T, H>,
+	options: DeserializeOptions,
+}
+
+impl<'de, T, H: Hold<'de, T>> ValueDe<'de, T, H> {
+	fn new(node: impl Into<Node<'de, T, H>>) -> Self {
+		Self::with_options(node, DeserializeOptions::default())
+	}
+
+	fn with_options(node: impl Into<Node<'de, T, H>>, options: DeserializeOptions) -> Self {
+		ValueDe { node: node.into(), options }
+	}
+
+	// Hold the values of a variant, discarding its name.
+	fn variant_values(variant: H::Variant, options: DeserializeOptions) -> Self {
+		let (_, values) = H::variant(variant);
+		Self::with_options(Node::Composite(values), options)
+	}
+
+	// Hand out each of the values in a composite in turn, ignoring any names.
+	fn seq(
+		mut composite: H::Composite,
+		options: DeserializeOptions,
+	) -> de::value::SeqDeserializer<impl Iterator<Item = ValueDe<'de, T, H>>, Error> {
+		let len = composite.len();
+		de::value::SeqDeserializer::new(
+			(0..len).map(move |idx| Self::with_options(H::take(&mut composite, idx), options)),
+		)
+	}
+
+	// Hand the values that we hold to serde as a map of the fields given, if we can. Field names are
+	// matched loosely against the fields given if asked for, and variants are handed out as a map if tagged.
+	fn deserialize_fields<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		self.value.deserialize_struct(name, fields, visitor)
+		let options = self.options;
+		let field_names = if options.loose_field_names { FieldNames::LooseMatch(fields) } else { FieldNames::Exact };
+		match (self.node, options.variant_tag) {
+			(Node::Composite(composite), _) if matches!(*composite, Composite::Named(_)) => {
+				visitor.visit_map(MapValues::<T, H>::new(composite, options, field_names))
+			}
+			(Node::Variant(variant), Some(tag)) => {
+				visitor.visit_map(MapValues::<T, H>::tagged(variant, tag, options, field_names))
+			}
+			(node, _) => Self::with_options(node, options).deserialize_any(visitor),
+		}
 	}
+}
 
-	fn deserialize_enum<V>(
+impl DeserializeOptions {
+	// Work out which of the target enum's variants the variant given should be deserialized into. The name
+	// of the variant is left for the caller to fill in, since it may need to be moved out of the variant.
+	fn variant_key<T>(
 		self,
-		name: &'static str,
+		variant: &Variant<T>,
+		enum_name: &str,
 		variants: &'static [&'static str],
-		visitor: V,
-	) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.value.deserialize_enum(name, variants, visitor)
-	}
-}
-
-// Our ValueDef deserializer needs to handle BitSeq itself, but otherwise delegates to
-// the inner implementations of things to handle. This macro makes that less repetitive
-// to write by only requiring a bitseq impl.
-macro_rules! delegate_except_bitseq {
-    (
-        $name:ident ( $self:ident, $($arg:ident),* ),
-            $seq:pat => $expr:expr
-    ) => {
-        match $self {
-            ValueDef::BitSequence($seq) => {
-                $expr
-            },
-            ValueDef::Composite(composite) => {
-                composite.$name( $($arg),* )
-            },
-            ValueDef::Variant(variant) => {
-                variant.$name( $($arg),* )
-            },
-            ValueDef::Primitive(prim) => {
-                prim.$name( $($arg),* )
-            },
-        }
-    }
-}
-
-// The goal here is simply to forward deserialization methods of interest to
-// the relevant subtype. The exception is our BitSequence type, which doesn't
-// have a sub type to forward to and so is handled here.
-impl<'de, T> Deserializer<'de> for ValueDef<T> {
+	) -> Result<VariantKey<()>, Error> {
+		let key = if self.by_index {
+			match variant.index {
+				Some(index) => VariantKey::Index(index),
+				None => {
+					return Err(Error::from_string(format!(
+						"Cannot deserialize variant {} into the enum {} by index; it has no index",
+						variant.name, enum_name
+					)))
+				}
+			}
+		} else {
+			match self.variant_match.resolve(&variant.name, variants) {
+				Some(matched) => VariantKey::Matched(matched),
+				None => VariantKey::Name(()),
+			}
+		};
+
+		let known = match key {
+			VariantKey::Name(()) => variants.iter().any(|v| *v == variant.name),
+			VariantKey::Index(index) => (index as usize) < variants.len(),
+			VariantKey::Matched(_) | VariantKey::Fallback(_) => true,
+		};
+		match self.fallback_variant {
+			Some(fallback) if !known => Ok(VariantKey::Fallback(fallback)),
+			_ => Ok(key),
+		}
+	}
+}
+
+// Most deserialize calls can't be satisfied by a bit sequence, and hand back an error like this.
+fn bitseq_error(target: &str) -> Error {
+	Error::from_string(format!("Cannot deserialize BitSequence into {}", target))
+}
+
+// Hand back the bytes in a composite, if it's made up entirely of U8 values.
+fn composite_bytes<T>(composite: &Composite<T>) -> Result<Vec<u8>, Error> {
+	(0..composite.len())
+		.map(|idx| match composite[idx].value {
+			ValueDef::Primitive(Primitive::U8(byte)) => Ok(byte),
+			_ => Err(Error::from_str("Cannot deserialize composite that is not entirely U8's into bytes")),
+		})
+		.collect()
+}
+
+// If asked to, a composite containing exactly one value can be deserialized into a scalar type by handing
+// back that value. Otherwise, the visitor is given the whole composite (or whatever else we hold).
+macro_rules! deserialize_scalar {
+	($($fn_name:ident),+) => {
+		$(
+			fn $fn_name<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+			where
+				V: de::Visitor<'de>,
+			{
+				let options = self.options;
+				match self.node {
+					Node::Composite(mut composite) if options.unwrap_single_values && composite.len() == 1 => {
+						Self::with_options(H::take(&mut composite, 0), options).$fn_name(visitor)
+					}
+					Node::Primitive(prim) => H::primitive(prim).$fn_name(visitor),
+					_ => self.deserialize_any(visitor),
+				}
+			}
+		)+
+	};
+}
+
+// Floats are deserialized like other scalars, except that strings (including those inside single value
+// composites) are parsed into them if the `parse_floats` option is set, and bit sequences are rejected outright.
+macro_rules! deserialize_float {
+	($($fn_name:ident $visit_fn:ident $ty:ty = $target:literal),+) => {
+		$(
+			fn $fn_name<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+			where
+				V: de::Visitor<'de>,
+			{
+				let options = self.options;
+				match self.node {
+					Node::Primitive(prim) => match &*prim {
+						Primitive::Str(s) if options.parse_floats => {
+							let n = s.trim().parse::<$ty>().map_err(|_| {
+								Error::from_string(format!("Cannot parse the string {:?} into {}", s, $target))
+							})?;
+							visitor.$visit_fn(n)
+						}
+						_ => H::primitive(prim).$fn_name(visitor),
+					},
+					Node::Composite(mut composite)
+						if (options.unwrap_single_values || options.parse_floats) && composite.len() == 1 =>
+					{
+						Self::with_options(H::take(&mut composite, 0), options).$fn_name(visitor)
+					}
+					Node::BitSequence(_) => Err(bitseq_error($target)),
+					_ => self.deserialize_any(visitor),
+				}
+			}
+		)+
+	};
+}
+
+impl<'de, T, H: Hold<'de, T>> Deserializer<'de> for ValueDe<'de, T, H> {
 	type Error = Error;
 
 	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
-		V: serde::de::Visitor<'de>,
+		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_any(self, visitor),
-			seq => {
-				BitVecPieces::new(seq)?.deserialize_any(visitor)
+		let options = self.options;
+		match self.node {
+			Node::Composite(composite) if matches!(*composite, Composite::Named(_)) => {
+				visitor.visit_map(MapValues::<T, H>::new(composite, options, FieldNames::Exact))
+			}
+			Node::Composite(composite)
+				if options.any_bytes
+					&& matches!(&*composite, Composite::Unnamed(values) if !values.is_empty()
+						&& values.iter().all(|v| matches!(v.value, ValueDef::Primitive(Primitive::U8(_))))) =>
+			{
+				visitor.visit_byte_buf(composite_bytes(&composite)?)
 			}
+			Node::Composite(composite) => visitor.visit_seq(Self::seq(composite, options)),
+			Node::Variant(variant) => match options.variant_tag {
+				Some(tag) => visitor.visit_map(MapValues::<T, H>::tagged(variant, tag, options, FieldNames::Exact)),
+				None => visitor.visit_enum(VariantDe::<T, H>::new(variant, options)),
+			},
+			Node::BitSequence(seq) => BitVecPieces::new(&seq)?.deserialize_any(visitor),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_any(visitor),
 		}
 	}
 
-	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_newtype_struct(self, name, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into a newtype struct"))
-			}
+		match self.node {
+			Node::BitSequence(_) => Err(bitseq_error("a newtype struct")),
+			// A composite is handed to the inner type whole, rather than its single value (if it has one).
+			// Scalar targets unwrap single value composites themselves if asked to (see `deserialize_scalar!`), and
+			// this way targets like `Foo(Vec<u8>)` still see a sequence when given exactly one value.
+			_ => visitor.visit_seq(de::value::SeqDeserializer::new(std::iter::once(self))),
 		}
 	}
 
@@ -243,10 +667,18 @@ impl<'de, T> Deserializer<'de> for ValueDef<T> {
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_tuple(self, len, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into a tuple"))
+		let options = self.options;
+		match self.node {
+			Node::Composite(composite) => {
+				if let Some(e) = tuple_len_error(&composite, len) {
+					return Err(e);
+				}
+				// A sequence of unnamed values is ideal, but if the values are named then we just ignore the names:
+				visitor.visit_seq(Self::seq(composite, options))
 			}
+			Node::Variant(variant) => Self::variant_values(variant, options).deserialize_tuple(len, visitor),
+			Node::BitSequence(_) => Err(bitseq_error("a tuple")),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_tuple(len, visitor),
 		}
 	}
 
@@ -254,10 +686,30 @@ impl<'de, T> Deserializer<'de> for ValueDef<T> {
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_tuple_struct(self, name, len, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into a tuple struct"))
+		let options = self.options;
+		match self.node {
+			// If asked to, and the lengths don't line up, but we have a single value that is itself a composite
+			// of the right length, then we unwrap it and deserialize from that instead. Only one layer of
+			// wrapping is removed in this way.
+			Node::Composite(mut composite)
+				if options.unwrap_tuple_structs && composite.len() != len && composite.len() == 1 =>
+			{
+				match H::take(&mut composite, 0) {
+					Node::Composite(inner) if inner.len() == len => {
+						Self::with_options(Node::Composite(inner), options).deserialize_tuple(len, visitor)
+					}
+					_ => Err(Error::from_string(format!(
+						"Cannot deserialize composite of length 1 into tuple struct of length {}",
+						len
+					))),
+				}
+			}
+			Node::Composite(_) => self.deserialize_tuple(len, visitor),
+			Node::Variant(variant) => {
+				Self::variant_values(variant, options).deserialize_tuple_struct(name, len, visitor)
 			}
+			Node::BitSequence(_) => Err(bitseq_error("a tuple struct")),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_tuple_struct(name, len, visitor),
 		}
 	}
 
@@ -265,10 +717,14 @@ impl<'de, T> Deserializer<'de> for ValueDef<T> {
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_unit(self, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into a ()"))
-			}
+		let options = self.options;
+		match self.node {
+			// 0 length composite types can be treated as the unit type:
+			Node::Composite(composite) if composite.is_empty() => visitor.visit_unit(),
+			Node::Composite(_) => Err(Error::from_str("Cannot deserialize non-empty Composite into a unit value")),
+			Node::Variant(variant) => Self::variant_values(variant, options).deserialize_unit(visitor),
+			Node::BitSequence(_) => Err(bitseq_error("a ()")),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_unit(visitor),
 		}
 	}
 
@@ -276,10 +732,10 @@ impl<'de, T> Deserializer<'de> for ValueDef<T> {
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_unit_struct(self, name, visitor),
-			_ => {
-				Err(Error::from_string(format!("Cannot deserialize BitSequence into the unit struct {}", name)))
-			}
+		match self.node {
+			Node::Composite(_) | Node::Variant(_) => self.deserialize_unit(visitor),
+			Node::BitSequence(_) => Err(bitseq_error(&format!("the unit struct {}", name))),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_unit_struct(name, visitor),
 		}
 	}
 
@@ -292,32 +748,47 @@ impl<'de, T> Deserializer<'de> for ValueDef<T> {
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_enum(self, name, variants, visitor),
-			_ => {
-				Err(Error::from_string(format!("Cannot deserialize BitSequence into the enum {}", name)))
+		let options = self.options;
+		match self.node {
+			Node::Variant(variant) => {
+				let key = options.variant_key(&variant, name, variants)?;
+				let (variant_name, values) = H::variant(variant);
+				let key = match key {
+					VariantKey::Name(()) => VariantKey::Name(variant_name),
+					VariantKey::Matched(matched) => VariantKey::Matched(matched),
+					VariantKey::Index(index) => VariantKey::Index(index),
+					// The values of an unknown variant are discarded, leaving the fallback unit variant:
+					VariantKey::Fallback(fallback) => return visitor.visit_enum(fallback.into_deserializer()),
+				};
+				visitor.visit_enum(VariantDe { key, values: Self::with_options(Node::Composite(values), options) })
 			}
+			Node::Composite(_) => self.deserialize_any(visitor),
+			Node::BitSequence(_) => Err(bitseq_error(&format!("the enum {}", name))),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_enum(name, variants, visitor),
 		}
 	}
 
-	fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_bytes(self, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into raw bytes"))
-			}
+		match self.node {
+			Node::Composite(composite) => visitor.visit_byte_buf(composite_bytes(&composite)?),
+			Node::Variant(_) => self.deserialize_any(visitor),
+			Node::BitSequence(_) => Err(bitseq_error("raw bytes")),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_byte_buf(visitor),
 		}
 	}
 
-	fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_byte_buf(self, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into raw bytes"))
-			}
+		match self.node {
+			Node::Composite(_) => self.deserialize_byte_buf(visitor),
+			Node::Variant(_) => self.deserialize_any(visitor),
+			Node::BitSequence(_) => Err(bitseq_error("raw bytes")),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_bytes(visitor),
 		}
 	}
 
@@ -325,10 +796,14 @@ impl<'de, T> Deserializer<'de> for ValueDef<T> {
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_seq(self, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into a sequence"))
-			}
+		let options = self.options;
+		match self.node {
+			// Deserializing the sequence in this way will complain if not all of the values are consumed,
+			// which is important for targets that expect a fixed number of items (like `GenericArray`s).
+			Node::Composite(composite) => Self::seq(composite, options).deserialize_any(visitor),
+			Node::Variant(variant) => Self::variant_values(variant, options).deserialize_seq(visitor),
+			Node::BitSequence(_) => Err(bitseq_error("a sequence")),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_seq(visitor),
 		}
 	}
 
@@ -336,297 +811,509 @@ impl<'de, T> Deserializer<'de> for ValueDef<T> {
 	where
 		V: de::Visitor<'de>,
 	{
-		delegate_except_bitseq! { deserialize_map(self, visitor),
-			_ => {
-				Err(Error::from_str("Cannot deserialize BitSequence into a map"))
+		let options = self.options;
+		// We don't know which field names to expect here, so loose field names are converted to snake case:
+		let field_names = if options.loose_field_names { FieldNames::SnakeCase } else { FieldNames::Exact };
+		match self.node {
+			// Empty composites are unit-like, and so can be treated as empty maps:
+			Node::Composite(composite) if matches!(*composite, Composite::Named(_)) || composite.is_empty() => {
+				visitor.visit_map(MapValues::<T, H>::new(composite, options, field_names))
+			}
+			Node::Composite(_) => Err(Error::from_str("Cannot deserialize an unnamed composite into a map")),
+			Node::Variant(variant) => match options.variant_tag {
+				Some(tag) => visitor.visit_map(MapValues::<T, H>::tagged(variant, tag, options, field_names)),
+				None => Self::variant_values(variant, options).deserialize_map(visitor),
+			},
+			Node::BitSequence(_) => Err(bitseq_error("a map")),
+			Node::Primitive(prim) => H::primitive(prim).deserialize_map(visitor),
+		}
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		let options = self.options;
+		// SCALE encoded `Option`s are decoded into `None` and `Some` variants; line these up with
+		// the serde notion of an option. Anything else is treated as being present.
+		match self.node {
+			Node::Variant(variant) if variant.name == "None" && variant.values.is_empty() => visitor.visit_none(),
+			Node::Variant(variant) if variant.name == "Some" && variant.values.len() == 1 => {
+				let (_, mut values) = H::variant(variant);
+				visitor.visit_some(Self::with_options(H::take(&mut values, 0), options))
 			}
+			_ => visitor.visit_some(self),
 		}
 	}
 
-	// None of the sub types particularly care about these, so we just allow them to forward to
+	deserialize_scalar! {
+		deserialize_bool, deserialize_i8, deserialize_i16, deserialize_i32, deserialize_i64, deserialize_i128,
+		deserialize_u8, deserialize_u16, deserialize_u32, deserialize_u64, deserialize_u128,
+		deserialize_char, deserialize_str, deserialize_string
+	}
+
+	deserialize_float! {
+		deserialize_f32 visit_f32 f32 = "an f32", deserialize_f64 visit_f64 f64 = "an f64"
+	}
+
+	fn deserialize_struct<V>(
+		self,
+		_name: &'static str,
+		fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_fields(fields, visitor)
+	}
+
+	// Nothing that we hold particularly cares about this, so we just allow it to forward to
 	// deserialize_any and go from there.
 	forward_to_deserialize_any! {
-		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-		option struct identifier ignored_any
+		identifier
 	}
+
+	deserialize_ignored_any_as_unit!();
 }
 
-impl<'de, T> IntoDeserializer<'de, Error> for Value<T> {
-	type Deserializer = Value<T>;
+impl<'de, T, H: Hold<'de, T>> IntoDeserializer<'de, Error> for ValueDe<'de, T, H> {
+	type Deserializer = Self;
 	fn into_deserializer(self) -> Self::Deserializer {
 		self
 	}
 }
 
-impl<'de, T> Deserializer<'de> for Composite<T> {
+// Because composite types are used to represent variant fields, we allow variant accesses
+// on a `ValueDe` holding them, which just delegate to the deserialize methods above.
+impl<'de, T, H: Hold<'de, T>> VariantAccess<'de> for ValueDe<'de, T, H> {
 	type Error = Error;
 
-	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: serde::de::Visitor<'de>,
-	{
-		match self {
-			Composite::Named(values) => visitor.visit_map(de::value::MapDeserializer::new(values.into_iter())),
-			Composite::Unnamed(values) => visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter())),
-		}
+	fn unit_variant(self) -> Result<(), Self::Error> {
+		Deserialize::deserialize(self)
 	}
 
-	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	fn newtype_variant_seed<S>(self, seed: S) -> Result<S::Value, Self::Error>
 	where
-		V: de::Visitor<'de>,
+		S: de::DeserializeSeed<'de>,
 	{
-		match self {
-			Composite::Named(values) => {
-				visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter().map(|(_, v)| v)))
+		let options = self.options;
+		match self.node {
+			// A decoded newtype variant like `Foo(Bar)` will contain exactly one unnamed value,
+			// and it's that value (not the whole composite) which we want to deserialize:
+			Node::Composite(mut composite) if matches!(&*composite, Composite::Unnamed(values) if values.len() == 1) => {
+				seed.deserialize(Self::with_options(H::take(&mut composite, 0), options))
 			}
-			Composite::Unnamed(values) => visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter())),
+			// Otherwise, treat the whole set of values as the inner type (eg named values can
+			// be deserialized into a struct wrapped by the variant):
+			_ => seed.deserialize(self),
 		}
 	}
 
-	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self {
-			// A sequence of named values? just ignores the names:
-			Composite::Named(values) => {
-				if values.len() != len {
-					return Err(Error::from_string(format!(
-						"Cannot deserialize composite of length {} into tuple of length {}",
-						values.len(),
-						len
-					)));
-				}
-				visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter().map(|(_, v)| v)))
-			}
-			// A sequence of unnamed values is ideal:
-			Composite::Unnamed(values) => {
-				if values.len() != len {
-					return Err(Error::from_string(format!(
-						"Cannot deserialize composite of length {} into tuple of length {}",
-						values.len(),
-						len
-					)));
-				}
-				visitor.visit_seq(de::value::SeqDeserializer::new(values.into_iter()))
-			}
-		}
+		self.deserialize_tuple(len, visitor)
 	}
 
-	fn deserialize_tuple_struct<V>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		self.deserialize_tuple(len, visitor)
+		self.deserialize_fields(fields, visitor)
 	}
+}
 
-	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		// 0 length composite types can be treated as the unit type:
-		if self.is_empty() {
-			visitor.visit_unit()
-		} else {
-			Err(Error::from_str("Cannot deserialize non-empty Composite into a unit value"))
-		}
+// What we hand to serde to pick out the target variant of some enum.
+enum VariantKey<N> {
+	// The name of the variant.
+	Name(N),
+	// The name of the target variant that the name of the variant matched loosely.
+	Matched(&'static str),
+	// The position of the variant in its enum.
+	Index(u32),
+	// The fallback variant, which is used in place of variants that the target enum doesn't know about.
+	Fallback(&'static str),
+}
+
+// Variant types can be treated as serde enums. Here we just hand back the name (or index) of the
+// variant and a `ValueDe` holding its values, which allows deserializing of those values.
+struct VariantDe<'de, T, H: Hold<'de, T>> {
+	key: VariantKey<H::Name>,
+	values: ValueDe<'de, T, H>,
+}
+
+impl<'de, T, H: Hold<'de, T>> VariantDe<'de, T, H> {
+	fn new(variant: H::Variant, options: DeserializeOptions) -> Self {
+		let (name, values) = H::variant(variant);
+		VariantDe { key: VariantKey::Name(name), values: ValueDe::with_options(Node::Composite(values), options) }
 	}
+}
+
+impl<'de, T, H: Hold<'de, T>> EnumAccess<'de> for VariantDe<'de, T, H> {
+	type Error = Error;
 
-	fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	type Variant = ValueDe<'de, T, H>;
+
+	fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
 	where
-		V: de::Visitor<'de>,
+		V: de::DeserializeSeed<'de>,
 	{
-		self.deserialize_unit(visitor)
+		let values = self.values;
+		let key = match self.key {
+			VariantKey::Name(name) => seed.deserialize(name),
+			VariantKey::Matched(name) | VariantKey::Fallback(name) => seed.deserialize(name.into_deserializer()),
+			VariantKey::Index(index) => seed.deserialize(index.into_deserializer()),
+		};
+		key.map(|key| (key, values))
 	}
+}
 
-	fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		visitor.visit_seq(de::value::SeqDeserializer::new(Some(self).into_iter()))
+// Composite values (and the values of tagged variants) are handed to serde as a map via this. If a key can't
+// be deserialized (for instance, it's an unknown field), we list the names that were actually present in the error.
+struct MapValues<'de, T, H: Hold<'de, T>> {
+	options: DeserializeOptions,
+	// The tag and name of the variant whose values we're handing out, which is handed out first.
+	tag: Option<(&'static str, H::Name)>,
+	values: H::Composite,
+	field_names: FieldNames,
+	idx: usize,
+}
+
+impl<'de, T, H: Hold<'de, T>> MapValues<'de, T, H> {
+	fn new(values: H::Composite, options: DeserializeOptions, field_names: FieldNames) -> Self {
+		MapValues { options, tag: None, values, field_names, idx: 0 }
 	}
 
-	fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	// Unnamed values are given the keys "0", "1" and so on, so that they can sit alongside the tag.
+	fn tagged(variant: H::Variant, tag: &'static str, options: DeserializeOptions, field_names: FieldNames) -> Self {
+		let (name, values) = H::variant(variant);
+		MapValues { tag: Some((tag, name)), ..MapValues::new(values, options, field_names) }
+	}
+}
+
+impl<'de, T, H: Hold<'de, T>> de::MapAccess<'de> for MapValues<'de, T, H> {
+	type Error = Error;
+
+	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
 	where
-		V: de::Visitor<'de>,
+		K: de::DeserializeSeed<'de>,
 	{
-		match self {
-			Composite::Named(values) => {
-				let bytes = values
-					.into_iter()
-					.map(|(_n, v)| {
-						if let ValueDef::Primitive(Primitive::U8(byte)) = v.value {
-							Ok(byte)
-						} else {
-							Err(Error::from_str("Cannot deserialize composite that is not entirely U8's into bytes"))
-						}
-					})
-					.collect::<Result<_, Error>>()?;
-				visitor.visit_byte_buf(bytes)
-			}
-			Composite::Unnamed(values) => {
-				let bytes = values
-					.into_iter()
-					.map(|v| {
-						if let ValueDef::Primitive(Primitive::U8(byte)) = v.value {
-							Ok(byte)
-						} else {
-							Err(Error::from_str("Cannot deserialize composite that is not entirely U8's into bytes"))
-						}
-					})
-					.collect::<Result<_, Error>>()?;
-				visitor.visit_byte_buf(bytes)
-			}
+		if let Some((tag, _)) = &self.tag {
+			return seed.deserialize(MapKey(tag)).map(Some);
 		}
+
+		let name = match &*self.values {
+			Composite::Named(values) => match values.get(self.idx) {
+				Some((name, _)) => self.field_names.apply(name),
+				None => return Ok(None),
+			},
+			Composite::Unnamed(values) if self.idx < values.len() => Cow::Owned(self.idx.to_string()),
+			Composite::Unnamed(_) => return Ok(None),
+		};
+		// Only the values of a composite are taken as we go, so the names are all still here:
+		seed.deserialize(MapKey(&name)).map(Some).map_err(|e| match &*self.values {
+			Composite::Named(values) => with_fields_present(e, values.iter().map(|(n, _)| n)),
+			Composite::Unnamed(_) => e,
+		})
 	}
 
-	fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
 	where
-		V: de::Visitor<'de>,
+		V: de::DeserializeSeed<'de>,
 	{
-		self.deserialize_byte_buf(visitor)
+		if let Some((_, name)) = self.tag.take() {
+			return seed.deserialize(name);
+		}
+
+		if self.idx >= self.values.len() {
+			return Err(Error::from_str("No value left to deserialize"));
+		}
+		let value = H::take(&mut self.values, self.idx);
+		self.idx += 1;
+		seed.deserialize(ValueDe::with_options(value, self.options))
 	}
 
-	forward_to_deserialize_any! {
-		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-		option struct map
-		enum identifier ignored_any
+	fn size_hint(&self) -> Option<usize> {
+		Some(self.values.len() - self.idx + usize::from(self.tag.is_some()))
 	}
 }
 
-impl<'de, T> IntoDeserializer<'de, Error> for Composite<T> {
-	type Deserializer = Composite<T>;
-	fn into_deserializer(self) -> Self::Deserializer {
-		self
+// How the names of named values are handed to serde as map keys.
+#[derive(Clone, Copy)]
+enum FieldNames {
+	// As they are.
+	Exact,
+	// Matched loosely against the names of the fields that are expected (see `loose_match`).
+	LooseMatch(&'static [&'static str]),
+	// Converted to snake case, for when we don't know which field names to expect.
+	SnakeCase,
+}
+
+impl FieldNames {
+	fn apply(self, name: &str) -> Cow<'_, str> {
+		match self {
+			FieldNames::Exact => Cow::Borrowed(name),
+			FieldNames::LooseMatch(fields) => Cow::Borrowed(loose_match(name, fields).unwrap_or(name)),
+			FieldNames::SnakeCase => Cow::Owned(snake_case(name)),
+		}
 	}
 }
 
-// Because composite types are used to represent variant fields, we allow
-// variant accesses to be called on it, which just delegate to methods defined above.
-impl<'de, T> VariantAccess<'de> for Composite<T> {
-	type Error = Error;
+// Add the names of the fields that are present to some error from deserializing a field name.
+fn with_fields_present<'a>(e: Error, names: impl Iterator<Item = &'a String>) -> Error {
+	let names: Vec<_> = names.map(|n| format!("`{}`", n)).collect();
+	Error::from_string(format!("{} (the fields present are {})", e, names.join(", ")))
+}
 
-	fn unit_variant(self) -> Result<(), Self::Error> {
-		Deserialize::deserialize(self)
-	}
+// Deserialize calls on our value types (and references to them) are all handed to the `ValueDe`
+// given here, which can refer to the value (or reference) being deserialized from as `$this`.
+macro_rules! deserialize_via {
+	(|$this:ident| $de:expr) => {
+		deserialize_via! { @visit |$this| $de;
+			deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+			deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+			deserialize_f32 deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+			deserialize_byte_buf deserialize_option deserialize_unit deserialize_seq deserialize_map
+			deserialize_identifier deserialize_ignored_any
+		}
 
-	fn newtype_variant_seed<S>(self, seed: S) -> Result<S::Value, Self::Error>
-	where
-		S: de::DeserializeSeed<'de>,
-	{
-		seed.deserialize(self)
-	}
+		fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			let $this = self;
+			$de.deserialize_unit_struct(name, visitor)
+		}
 
-	fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.deserialize_tuple(len, visitor)
-	}
+		fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			let $this = self;
+			$de.deserialize_newtype_struct(name, visitor)
+		}
 
-	fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.deserialize_any(visitor)
-	}
+		fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			let $this = self;
+			$de.deserialize_tuple(len, visitor)
+		}
+
+		fn deserialize_tuple_struct<V>(
+			self,
+			name: &'static str,
+			len: usize,
+			visitor: V,
+		) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			let $this = self;
+			$de.deserialize_tuple_struct(name, len, visitor)
+		}
+
+		fn deserialize_struct<V>(
+			self,
+			name: &'static str,
+			fields: &'static [&'static str],
+			visitor: V,
+		) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			let $this = self;
+			$de.deserialize_struct(name, fields, visitor)
+		}
+
+		fn deserialize_enum<V>(
+			self,
+			name: &'static str,
+			variants: &'static [&'static str],
+			visitor: V,
+		) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			let $this = self;
+			$de.deserialize_enum(name, variants, visitor)
+		}
+	};
+	(@visit |$this:ident| $de:expr; $($fn_name:ident)+) => {
+		$(
+			fn $fn_name<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+			where
+				V: de::Visitor<'de>,
+			{
+				let $this = self;
+				$de.$fn_name(visitor)
+			}
+		)+
+	};
+}
+
+// Our Value type has some context, which we ignore, and some definition, which we point at.
+impl<'de, T> Deserializer<'de> for Value<T> {
+	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Owned>::new(this));
+}
+
+impl<'de, T> Deserializer<'de> for ValueDef<T> {
+	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Owned>::new(this));
+}
+
+impl<'de, T> Deserializer<'de> for Composite<T> {
+	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Owned>::new(this));
 }
 
 impl<'de, T> Deserializer<'de> for Variant<T> {
 	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Owned>::new(this));
+}
 
-	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: serde::de::Visitor<'de>,
-	{
-		visitor.visit_enum(self)
+/// Values can also be deserialized from by reference. This behaves identically to deserializing from an owned
+/// value, except that strings are handed out by reference rather than being moved out, and the value is left
+/// intact so that it can be deserialized from more than once.
+impl<'de, T> Deserializer<'de> for &'de Value<T> {
+	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Borrowed>::new(this));
+}
+
+impl<'de, T> Deserializer<'de> for &'de ValueDef<T> {
+	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Borrowed>::new(this));
+}
+
+impl<'de, T> Deserializer<'de> for &'de Composite<T> {
+	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Borrowed>::new(this));
+}
+
+impl<'de, T> Deserializer<'de> for &'de Variant<T> {
+	type Error = Error;
+	deserialize_via!(|this| ValueDe::<T, Borrowed>::new(this));
+}
+
+// Each of our option wrappers deserializes the value that it wraps using the options given here.
+macro_rules! deserialize_with_options {
+	($($wrapper:ident => |$this:ident| $options:expr;)+) => {
+		$(
+			impl<'de, T> Deserializer<'de> for $wrapper<T> {
+				type Error = Error;
+				deserialize_via!(|$this| ValueDe::<T, Owned>::with_options(Node::from($this.0), $options));
+			}
+
+			impl<'de, T> IntoDeserializer<'de, Error> for $wrapper<T> {
+				type Deserializer = $wrapper<T>;
+				fn into_deserializer(self) -> Self::Deserializer {
+					self
+				}
+			}
+		)+
+	};
+}
+
+deserialize_with_options! {
+	DeserializeWith => |this| this.1;
+	UnwrapSingleValues => |this| DeserializeOptions { unwrap_single_values: true, ..Default::default() };
+	UnwrapTupleStructs => |this| DeserializeOptions { unwrap_tuple_structs: true, ..Default::default() };
+	ByIndex => |this| DeserializeOptions { by_index: true, ..Default::default() };
+	WithVariantMatch => |this| DeserializeOptions { variant_match: this.1, ..Default::default() };
+	WithFallbackVariant => |this| DeserializeOptions { fallback_variant: Some(this.1), ..Default::default() };
+	ParseFloats => |this| DeserializeOptions { parse_floats: true, ..Default::default() };
+	LooseFieldNames => |this| DeserializeOptions { loose_field_names: true, ..Default::default() };
+	AnyBytes => |this| DeserializeOptions { any_bytes: true, ..Default::default() };
+	TaggedVariant => |this| DeserializeOptions { variant_tag: Some(this.1), ..Default::default() };
+}
+
+impl<'de, T> IntoDeserializer<'de, Error> for Value<T> {
+	type Deserializer = Value<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
 	}
+}
 
-	fn deserialize_enum<V>(
-		self,
-		_name: &'static str,
-		_variants: &'static [&'static str],
-		visitor: V,
-	) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		visitor.visit_enum(self)
+impl<'de, T> IntoDeserializer<'de, Error> for ValueDef<T> {
+	type Deserializer = ValueDef<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
 	}
+}
 
-	fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		visitor.visit_seq(de::value::SeqDeserializer::new(Some(self).into_iter()))
+impl<'de, T> IntoDeserializer<'de, Error> for Composite<T> {
+	type Deserializer = Composite<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
 	}
+}
 
-	// All of the below functions delegate to the Composite deserializing methods using the enum values.
+impl<'de, T> IntoDeserializer<'de, Error> for Variant<T> {
+	type Deserializer = Variant<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
+	}
+}
 
-	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.values.deserialize_tuple(len, visitor)
+impl<'de, T> IntoDeserializer<'de, Error> for &'de Value<T> {
+	type Deserializer = &'de Value<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
 	}
+}
 
-	fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.values.deserialize_tuple_struct(name, len, visitor)
+impl<'de, T> IntoDeserializer<'de, Error> for &'de ValueDef<T> {
+	type Deserializer = &'de ValueDef<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
 	}
+}
 
-	fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.values.deserialize_unit_struct(name, visitor)
+impl<'de, T> IntoDeserializer<'de, Error> for &'de Composite<T> {
+	type Deserializer = &'de Composite<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
 	}
+}
 
-	fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-	where
-		V: de::Visitor<'de>,
-	{
-		self.values.deserialize_unit(visitor)
+impl<'de, T> IntoDeserializer<'de, Error> for &'de Variant<T> {
+	type Deserializer = &'de Variant<T>;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
+	}
+}
+
+// The values of a variant are handed out as a composite, so we allow variant accesses to be called on one.
+impl<'de, T> VariantAccess<'de> for Composite<T> {
+	type Error = Error;
+
+	fn unit_variant(self) -> Result<(), Self::Error> {
+		ValueDe::<T, Owned>::new(self).unit_variant()
 	}
 
-	fn deserialize_struct<V>(
-		self,
-		name: &'static str,
-		fields: &'static [&'static str],
-		visitor: V,
-	) -> Result<V::Value, Self::Error>
+	fn newtype_variant_seed<S>(self, seed: S) -> Result<S::Value, Self::Error>
 	where
-		V: de::Visitor<'de>,
+		S: de::DeserializeSeed<'de>,
 	{
-		self.values.deserialize_struct(name, fields, visitor)
+		ValueDe::<T, Owned>::new(self).newtype_variant_seed(seed)
 	}
 
-	fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		self.values.deserialize_map(visitor)
+		ValueDe::<T, Owned>::new(self).tuple_variant(len, visitor)
 	}
 
-	fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		self.values.deserialize_seq(visitor)
-	}
-
-	forward_to_deserialize_any! {
-		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-		bytes byte_buf option identifier ignored_any
-	}
-}
-
-impl<'de, T> IntoDeserializer<'de, Error> for Variant<T> {
-	type Deserializer = Variant<T>;
-	fn into_deserializer(self) -> Self::Deserializer {
-		self
+		ValueDe::<T, Owned>::new(self).struct_variant(fields, visitor)
 	}
 }
 
@@ -648,6 +1335,59 @@ impl<'de, T> EnumAccess<'de> for Variant<T> {
 	}
 }
 
+// If a composite can't be deserialized into a tuple of the given length, hand back an error saying why.
+fn tuple_len_error<T>(composite: &Composite<T>, len: usize) -> Option<Error> {
+	if let Some(first_extra) = composite.get_index(len) {
+		Some(Error::from_string(format!(
+			"Cannot deserialize composite of length {} into tuple of length {}: there are {} trailing values, the first of which is {}",
+			composite.len(),
+			len,
+			composite.len() - len,
+			describe_value(&first_extra.value)
+		)))
+	} else if composite.len() < len {
+		Some(Error::from_string(format!(
+			"Cannot deserialize composite of length {} into tuple of length {}",
+			composite.len(),
+			len
+		)))
+	} else {
+		None
+	}
+}
+
+// A short description of some value, for use in error messages.
+fn describe_value<T>(value: &ValueDef<T>) -> String {
+	match value {
+		ValueDef::Composite(Composite::Named(_)) => "a named composite".into(),
+		ValueDef::Composite(Composite::Unnamed(_)) => "an unnamed composite".into(),
+		ValueDef::Variant(variant) => format!("the variant {:?}", variant.name),
+		ValueDef::BitSequence(_) => "a bit sequence".into(),
+		ValueDef::Primitive(prim) => format!("the primitive {:?}", prim),
+	}
+}
+
+// Chars can be deserialized into integers, in which case we hand back their
+// unicode code point, so long as it fits into the integer asked for.
+macro_rules! deserialize_char_as_int {
+	($($fn_name:ident $visit_fn:ident $ty:ty),*) => {$(
+		fn $fn_name<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			match self {
+				Primitive::Char(c) => {
+					let n = <$ty>::try_from(c as u32).map_err(|_| {
+						Error::from_string(format!("Cannot deserialize the char {:?} into a {}", c, stringify!($ty)))
+					})?;
+					visitor.$visit_fn(n)
+				}
+				other => other.deserialize_any(visitor),
+			}
+		}
+	)*}
+}
+
 impl<'de> Deserializer<'de> for Primitive {
 	type Error = Error;
 
@@ -671,6 +1411,8 @@ impl<'de> Deserializer<'de> for Primitive {
 			Primitive::I64(v) => visitor.visit_i64(v),
 			Primitive::I128(v) => visitor.visit_i128(v),
 			Primitive::I256(v) => visitor.visit_bytes(&v),
+			Primitive::F32(v) => visitor.visit_f32(v),
+			Primitive::F64(v) => visitor.visit_f64(v),
 		}
 	}
 
@@ -681,38 +1423,330 @@ impl<'de> Deserializer<'de> for Primitive {
 		visitor.visit_seq(de::value::SeqDeserializer::new(Some(self).into_iter()))
 	}
 
-	forward_to_deserialize_any! {
-		bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-		bytes byte_buf option unit unit_struct seq tuple
-		tuple_struct map struct enum identifier ignored_any
+	fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		// Strings of "true" or "false" (ignoring case) can be treated as bools, to accommodate
+		// values that originated from formats in which bools ended up as strings:
+		match self {
+			Primitive::Str(s) if s.eq_ignore_ascii_case("true") => visitor.visit_bool(true),
+			Primitive::Str(s) if s.eq_ignore_ascii_case("false") => visitor.visit_bool(false),
+			Primitive::Str(s) => Err(Error::from_string(format!("Cannot deserialize the string {:?} into a bool", s))),
+			other => other.deserialize_any(visitor),
+		}
 	}
-}
 
-impl<'de> IntoDeserializer<'de, Error> for Primitive {
-	type Deserializer = Primitive;
-	fn into_deserializer(self) -> Self::Deserializer {
-		self
+	fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		// Numbers of any width can be cast to floats (lossily if need be):
+		match self.as_f64() {
+			Some(n) => visitor.visit_f32(n as f32),
+			None => self.deserialize_any(visitor),
+		}
 	}
-}
-
-/// This is a somewhat insane approach to extracting the data that we need from a
-/// BitVec and allowing it to be deserialized from as part of the [`Value`] enum.
-/// First, we serialize the BitVec, which grabs the relevant data out of it (that isn't
-/// otherwise publically accessible), and then we implement a Deserializer that aligns
-/// with what the Deserialize impl for BitVec expects.
-///
-/// See <https://docs.rs/bitvec/0.20.2/src/bitvec/serdes.rs.html> for the Serialize/Deserialize
-/// impls we are aligning with.
-struct BitVecPieces {
-	head: u8,
-	bits: u64,
-	data: Vec<u8>,
-	// Track which field we're currently deserializing:
-	current_field: Option<Field>,
-}
 
-#[derive(PartialEq, Copy, Clone)]
-enum Field {
+	fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		// Numbers of any width can be cast to floats (lossily if need be):
+		match self.as_f64() {
+			Some(n) => visitor.visit_f64(n),
+			None => self.deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		// A primitive can be treated as a tuple of length 1:
+		if len == 1 {
+			visitor.visit_seq(de::value::SeqDeserializer::new(Some(self).into_iter()))
+		} else {
+			Err(Error::from_string(format!("Cannot deserialize primitive into tuple of length {}", len)))
+		}
+	}
+
+	fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self {
+			Primitive::Char(c) => visitor.visit_i128(c as i128),
+			other => other.deserialize_256_as_128(visitor, "i128"),
+		}
+	}
+
+	fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self {
+			Primitive::Char(c) => visitor.visit_u128(c as u128),
+			other => other.deserialize_256_as_128(visitor, "u128"),
+		}
+	}
+
+	deserialize_char_as_int! {
+		deserialize_i8 visit_i8 i8, deserialize_i16 visit_i16 i16, deserialize_i32 visit_i32 i32,
+		deserialize_i64 visit_i64 i64,
+		deserialize_u8 visit_u8 u8, deserialize_u16 visit_u16 u16, deserialize_u32 visit_u32 u32,
+		deserialize_u64 visit_u64 u64
+	}
+
+	fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		// Strings containing exactly one character, and integers which are valid unicode
+		// code points, can be treated as chars:
+		match self {
+			Primitive::Str(s) => {
+				let mut chars = s.chars();
+				match (chars.next(), chars.next()) {
+					(Some(c), None) => visitor.visit_char(c),
+					_ => Err(Error::from_string(format!(
+						"Cannot deserialize the string {:?} into a char; it must contain exactly one character",
+						s
+					))),
+				}
+			}
+			prim @ (Primitive::Char(_) | Primitive::Bool(_)) => prim.deserialize_any(visitor),
+			n => {
+				let code_point = u32::try_from(n)?;
+				match char::from_u32(code_point) {
+					Some(c) => visitor.visit_char(c),
+					None => Err(Error::from_string(format!("{} is not a valid unicode code point", code_point))),
+				}
+			}
+		}
+	}
+
+	fn deserialize_enum<V>(
+		self,
+		_name: &'static str,
+		_variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		// Fieldless variants are often represented as just their name (for instance when they've come from
+		// JSON), so strings are treated as unit variants of that name:
+		match self {
+			Primitive::Str(s) => visitor.visit_enum(s.into_deserializer()),
+			other => other.deserialize_any(visitor),
+		}
+	}
+
+	forward_to_deserialize_any! {
+		str string
+		bytes byte_buf option unit unit_struct seq
+		tuple_struct map struct identifier
+	}
+
+	deserialize_ignored_any_as_unit!();
+}
+
+impl Primitive {
+	// 256 bit numbers are handed to the visitor as 128 bit ones if they fit, so that they can be
+	// deserialized into types like `u128`. Other primitives are handed over as is.
+	fn deserialize_256_as_128<'de, V>(self, visitor: V, target: &str) -> Result<V::Value, Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self {
+			Primitive::U256(bytes) => match u256_to_u128(&bytes) {
+				Some(n) => visitor.visit_u128(n),
+				None => Err(Error::from_string(format!("Cannot deserialize U256 into a {}; it is too large", target))),
+			},
+			Primitive::I256(bytes) => match i256_to_i128(&bytes) {
+				Some(n) => visitor.visit_i128(n),
+				None => {
+					Err(Error::from_string(format!("Cannot deserialize I256 into a {}; it is out of range", target)))
+				}
+			},
+			other => other.deserialize_any(visitor),
+		}
+	}
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Primitive {
+	type Deserializer = Primitive;
+	fn into_deserializer(self) -> Self::Deserializer {
+		self
+	}
+}
+
+// Deserialize from a borrowed primitive by deserializing from a copy of it.
+macro_rules! deserialize_cloned {
+	($($fn_name:ident)+) => {
+		$(
+			fn $fn_name<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+			where
+				V: de::Visitor<'de>,
+			{
+				self.clone().$fn_name(visitor)
+			}
+		)+
+	};
+}
+
+/// Primitives can also be deserialized from by reference. This behaves identically to deserializing
+/// from an owned [`Primitive`], except that strings are handed out by reference rather than being
+/// copied, so that targets like `&str` can borrow from the primitive.
+///
+/// Deserializing by reference leaves the primitive intact, so if one attempt fails another target type can
+/// be tried. This is how targets like `Either<L, R>` are handled when deserialized untagged (see
+/// `either::serde_untagged`): `L` is tried first, and then `R`. Owned values are buffered by serde before
+/// trying each type in this case, and so this works for owned values too, but at the cost of copying.
+impl<'de> Deserializer<'de> for &'de Primitive {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		match self {
+			Primitive::Str(s) => visitor.visit_borrowed_str(s),
+			other => other.clone().deserialize_any(visitor),
+		}
+	}
+
+	fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.deserialize_any(visitor)
+	}
+
+	deserialize_cloned! {
+		deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_i128
+		deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_u128
+		deserialize_f32 deserialize_f64 deserialize_char deserialize_string deserialize_bytes
+		deserialize_byte_buf deserialize_option deserialize_unit deserialize_seq deserialize_map
+		deserialize_identifier
+	}
+
+	deserialize_ignored_any_as_unit!();
+
+	fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.clone().deserialize_unit_struct(name, visitor)
+	}
+
+	fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.clone().deserialize_newtype_struct(name, visitor)
+	}
+
+	fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.clone().deserialize_tuple(len, visitor)
+	}
+
+	fn deserialize_tuple_struct<V>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.clone().deserialize_tuple_struct(name, len, visitor)
+	}
+
+	fn deserialize_struct<V>(
+		self,
+		name: &'static str,
+		fields: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.clone().deserialize_struct(name, fields, visitor)
+	}
+
+	fn deserialize_enum<V>(
+		self,
+		name: &'static str,
+		variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.clone().deserialize_enum(name, variants, visitor)
+	}
+}
+
+/// The names of [`Composite::Named`] values are handed to serde as map keys via this. It behaves like a
+/// plain string, except that if an integer is asked for, we'll try to parse the name into one. This allows
+/// named composites with numeric names (like `"0"` and `"1"`) to be deserialized into maps with integer keys.
+struct MapKey<'a>(&'a str);
+
+macro_rules! deserialize_parsed_key {
+	($($fn_name:ident $visit_fn:ident $ty:ty),*) => {$(
+		fn $fn_name<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+		where
+			V: de::Visitor<'de>,
+		{
+			let n = self.0.parse::<$ty>().map_err(|_| {
+				Error::from_string(format!("Cannot parse the map key {:?} into a {}", self.0, stringify!($ty)))
+			})?;
+			visitor.$visit_fn(n)
+		}
+	)*}
+}
+
+impl<'de, 'a> Deserializer<'de> for MapKey<'a> {
+	type Error = Error;
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_str(self.0)
+	}
+
+	deserialize_parsed_key! {
+		deserialize_i8 visit_i8 i8, deserialize_i16 visit_i16 i16, deserialize_i32 visit_i32 i32,
+		deserialize_i64 visit_i64 i64, deserialize_i128 visit_i128 i128,
+		deserialize_u8 visit_u8 u8, deserialize_u16 visit_u16 u16, deserialize_u32 visit_u32 u32,
+		deserialize_u64 visit_u64 u64, deserialize_u128 visit_u128 u128
+	}
+
+	forward_to_deserialize_any! {
+		bool f32 f64 char str string bytes byte_buf option unit unit_struct newtype_struct
+		seq tuple tuple_struct map struct enum identifier ignored_any
+	}
+}
+
+/// This is a somewhat insane approach to extracting the data that we need from a
+/// BitVec and allowing it to be deserialized from as part of the [`Value`] enum.
+/// First, we serialize the BitVec, which grabs the relevant data out of it (that isn't
+/// otherwise publically accessible), and then we implement a Deserializer that aligns
+/// with what the Deserialize impl for BitVec expects.
+///
+/// See <https://docs.rs/bitvec/0.20.2/src/bitvec/serdes.rs.html> for the Serialize/Deserialize
+/// impls we are aligning with.
+struct BitVecPieces {
+	head: u8,
+	bits: u64,
+	data: Vec<u8>,
+	// Track which field we're currently deserializing:
+	current_field: Option<Field>,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+enum Field {
 	Head,
 	Bits,
 	Data,
@@ -767,7 +1801,7 @@ impl<'de> SeqAccess<'de> for BitVecPieces {
 }
 
 impl BitVecPieces {
-	fn new(bit_vec: BitSequence) -> Result<BitVecPieces, Error> {
+	fn new(bit_vec: &BitSequence) -> Result<BitVecPieces, Error> {
 		// Step 1. "Serialize" the bitvec into this struct. Essentially,
 		// we are just writing out the values we need for deserializing,
 		// but with a silly amount of boilerplate/indirection..
@@ -1066,6 +2100,29 @@ mod test {
 		assert_eq!(Foo::deserialize(val), Ok(Foo(123, true, "hello".into())))
 	}
 
+	#[test]
+	fn de_wrapped_into_tuple_struct() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Coords(u8, u8, u8);
+
+		let val = Value::unnamed_composite(vec![Value::u8(1), Value::u8(2), Value::u8(3)]);
+		assert_eq!(Coords::deserialize(val), Ok(Coords(1, 2, 3)));
+
+		// One layer of wrapping is removed if asked for:
+		let val =
+			Value::unnamed_composite(vec![Value::unnamed_composite(vec![Value::u8(1), Value::u8(2), Value::u8(3)])]);
+		Coords::deserialize(val.clone()).expect_err("wrapping isn't removed by default");
+		assert_eq!(Coords::deserialize(UnwrapTupleStructs(val)), Ok(Coords(1, 2, 3)));
+
+		// But no more than that:
+		let val = Value::unnamed_composite(vec![Value::unnamed_composite(vec![Value::unnamed_composite(vec![
+			Value::u8(1),
+			Value::u8(2),
+			Value::u8(3),
+		])])]);
+		Coords::deserialize(UnwrapTupleStructs(val)).expect_err("only one layer is unwrapped");
+	}
+
 	#[test]
 	fn de_into_newtype_struct() {
 		#[derive(Deserialize, Debug, PartialEq)]
@@ -1089,124 +2146,780 @@ mod test {
 		let val = ValueDef::Variant(Variant {
 			name: "Foo".into(),
 			values: Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]),
+			index: None,
 		});
 		assert_eq!(FooVar::deserialize(val), Ok(FooVar(MyEnum::Foo(1, 2, 3))));
 	}
 
 	#[test]
-	fn de_unwrapped_into_newtype_struct() {
-		#[derive(Deserialize, Debug, PartialEq)]
-		struct FooStr(String);
-		let val = Primitive::Str("hello".into());
-		assert_eq!(FooStr::deserialize(val), Ok(FooStr("hello".into())));
+	fn de_unwrapped_into_newtype_struct() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct FooStr(String);
+		let val = Primitive::Str("hello".into());
+		assert_eq!(FooStr::deserialize(val), Ok(FooStr("hello".into())));
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct FooVecU8(Vec<u8>);
+		let val = Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]);
+		assert_eq!(FooVecU8::deserialize(val), Ok(FooVecU8(vec![1, 2, 3])));
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum MyEnum {
+			Foo(u8, u8, u8),
+		}
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct FooVar(MyEnum);
+		let val = Variant {
+			name: "Foo".into(),
+			values: Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]),
+			index: None,
+		};
+		assert_eq!(FooVar::deserialize(val), Ok(FooVar(MyEnum::Foo(1, 2, 3))));
+	}
+
+	#[test]
+	fn de_into_vec() {
+		let val = ValueDef::Composite(Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]));
+		assert_eq!(<Vec<u8>>::deserialize(val), Ok(vec![1, 2, 3]));
+
+		let val = ValueDef::Composite(Composite::Unnamed(vec![
+			Value::str("a".into()),
+			Value::str("b".into()),
+			Value::str("c".into()),
+		]));
+		assert_eq!(<Vec<String>>::deserialize(val), Ok(vec!["a".into(), "b".into(), "c".into()]));
+	}
+
+	#[test]
+	fn de_into_vec_with_exact_size_hint() {
+		// Serde caps the size hint it'll preallocate from, so we stay well below that cap here:
+		let values: Vec<_> = (0..1000).map(Value::u32).collect();
+
+		let unnamed = Value::unnamed_composite(values.clone());
+		let vec = <Vec<u32>>::deserialize(&unnamed).expect("can deserialize");
+		assert_eq!(vec.len(), 1000);
+		assert_eq!(vec.capacity(), 1000);
+		let vec = <Vec<u32>>::deserialize(unnamed).expect("can deserialize");
+		assert_eq!(vec.len(), 1000);
+		assert_eq!(vec.capacity(), 1000);
+
+		// Names are ignored, but the size hint is still exact:
+		let named: Vec<_> = values.into_iter().enumerate().map(|(idx, v)| (idx.to_string(), v)).collect();
+		let vec = <Vec<u32>>::deserialize(Value::named_composite(named)).expect("can deserialize");
+		assert_eq!(vec.len(), 1000);
+		assert_eq!(vec.capacity(), 1000);
+	}
+
+	#[test]
+	fn de_unwrapped_into_vec() {
+		let val = Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]);
+		assert_eq!(<Vec<u8>>::deserialize(val), Ok(vec![1, 2, 3]));
+
+		let val =
+			Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::u8(2)), ("c".into(), Value::u8(3))]);
+		assert_eq!(<Vec<u8>>::deserialize(val), Ok(vec![1, 2, 3]));
+
+		let val = Composite::Unnamed(vec![Value::str("a".into()), Value::str("b".into()), Value::str("c".into())]);
+		assert_eq!(<Vec<String>>::deserialize(val), Ok(vec!["a".into(), "b".into(), "c".into()]));
+	}
+
+	#[test]
+	fn de_into_map() {
+		use std::collections::HashMap;
+
+		let val = ValueDef::Composite(Composite::Named(vec![
+			("a".into(), Value::u8(1)),
+			("b".into(), Value::u8(2)),
+			("c".into(), Value::u8(3)),
+		]));
+		assert_eq!(
+			<HashMap<String, u8>>::deserialize(val),
+			Ok(vec![("a".into(), 1), ("b".into(), 2), ("c".into(), 3)].into_iter().collect())
+		);
+
+		let val = ValueDef::Composite(Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]));
+		<HashMap<String, u8>>::deserialize(val).expect_err("no names; can't be map");
+	}
+
+	#[test]
+	fn de_into_struct_with_flattened_map() {
+		use std::collections::BTreeMap;
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Foo {
+			a: u8,
+			#[serde(flatten)]
+			rest: BTreeMap<String, u8>,
+		}
+
+		let val = ValueDef::Composite(Composite::Named(vec![
+			("b".into(), Value::u8(2)),
+			("a".into(), Value::u8(1)),
+			("c".into(), Value::u8(3)),
+		]));
+
+		assert_eq!(
+			Foo::deserialize(val),
+			Ok(Foo { a: 1, rest: vec![("b".into(), 2), ("c".into(), 3)].into_iter().collect() })
+		);
+
+		// Leftover fields can also be kept around as values:
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Bar {
+			a: u8,
+			#[serde(flatten)]
+			rest: BTreeMap<String, Value<()>>,
+		}
+
+		let val = Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]);
+
+		assert_eq!(
+			Bar::deserialize(val),
+			Ok(Bar { a: 1, rest: vec![("b".into(), Value::bool(true))].into_iter().collect() })
+		);
+	}
+
+	#[test]
+	fn de_into_generic_array() {
+		use generic_array::{typenum::U32, GenericArray};
+
+		let bytes: Vec<_> = (0..32u8).map(Value::u8).collect();
+		let arr = GenericArray::<u8, U32>::deserialize(Value::unnamed_composite(bytes)).expect("can deserialize");
+		assert_eq!(arr.as_slice(), (0..32u8).collect::<Vec<_>>().as_slice());
+
+		// Too few or too many values should fail:
+		let bytes: Vec<_> = (0..31u8).map(Value::u8).collect();
+		GenericArray::<u8, U32>::deserialize(Value::unnamed_composite(bytes)).expect_err("too few values");
+		let bytes: Vec<_> = (0..33u8).map(Value::u8).collect();
+		GenericArray::<u8, U32>::deserialize(Value::unnamed_composite(bytes)).expect_err("too many values");
+	}
+
+	#[test]
+	fn de_into_byte_arrays() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Ip([u8; 4]);
+
+		let val = Value::unnamed_composite(vec![Value::u8(127), Value::u8(0), Value::u8(0), Value::u8(1)]);
+		assert_eq!(<[u8; 4]>::deserialize(val.clone()), Ok([127, 0, 0, 1]));
+		assert_eq!(Ip::deserialize(val), Ok(Ip([127, 0, 0, 1])));
+
+		let val = Value::unnamed_composite((0..16).map(Value::u8).collect());
+		assert_eq!(<[u8; 16]>::deserialize(val), Ok([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]));
+
+		// The number of values must line up with the array length:
+		let val = Value::unnamed_composite(vec![Value::u8(127), Value::u8(0), Value::u8(1)]);
+		assert_eq!(
+			<[u8; 4]>::deserialize(val.clone()),
+			Err(Error::from_str("Cannot deserialize composite of length 3 into tuple of length 4"))
+		);
+		Ip::deserialize(val).expect_err("wrong length");
+	}
+
+	#[test]
+	fn de_into_tuple() {
+		let val = ValueDef::Composite(Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true)]));
+		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+
+		// names will just be ignored:
+		let val = ValueDef::Composite(Composite::Named(vec![
+			("a".into(), Value::str("hello".into())),
+			("b".into(), Value::bool(true)),
+		]));
+		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+
+		// Enum variants are allowed! The variant name will be ignored:
+		let val = ValueDef::Variant(Variant {
+			name: "Foo".into(),
+			values: Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true)]),
+			index: None,
+		});
+		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+
+		// Enum variants with names values are allowed! The variant name will be ignored:
+		let val = ValueDef::Variant(Variant {
+			name: "Foo".into(),
+			values: Composite::Named(vec![("a".into(), Value::str("hello".into())), ("b".into(), Value::bool(true))]),
+			index: None,
+		});
+		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+
+		// Wrong number of values should fail:
+		let val = ValueDef::Composite(Composite::Unnamed(vec![
+			Value::str("hello".into()),
+			Value::bool(true),
+			Value::u8(123),
+		]));
+		<(String, bool)>::deserialize(val).expect_err("Wrong length, should err");
+	}
+
+	#[test]
+	fn de_unwrapped_into_tuple() {
+		let val = Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true)]);
+		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+
+		// names will just be ignored:
+		let val = Composite::Named(vec![("a".into(), Value::str("hello".into())), ("b".into(), Value::bool(true))]);
+		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+
+		// Wrong number of values should fail:
+		let val = Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true), Value::u8(123)]);
+		<(String, bool)>::deserialize(val).expect_err("Wrong length, should err");
+	}
+
+	#[test]
+	fn de_integers_into_floats() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Foo {
+			a: f64,
+			b: f32,
+		}
+
+		let val = Value::named_composite(vec![("a".into(), Value::u64(123)), ("b".into(), Value::i8(-5))]);
+		assert_eq!(Foo::deserialize(val), Ok(Foo { a: 123.0, b: -5.0 }));
+
+		assert_eq!(f64::deserialize(Primitive::U32(5)), Ok(5.0));
+		f64::deserialize(Value::str("5".into())).expect_err("strings are not numbers");
+		f64::deserialize(Value::bool(true)).expect_err("bools are not numbers");
+	}
+
+	#[test]
+	fn de_single_value_composite_into_scalar() {
+		let val = Value::unnamed_composite(vec![Value::u8(5)]);
+		u8::deserialize(val.clone()).expect_err("composites aren't unwrapped by default");
+		assert_eq!(u8::deserialize(UnwrapSingleValues(val)), Ok(5));
+
+		let val = Value::named_composite(vec![("a".into(), Value::bool(true))]);
+		assert_eq!(bool::deserialize(UnwrapSingleValues(val)), Ok(true));
+
+		// More than one value can't be treated as a scalar:
+		let val = Value::unnamed_composite(vec![Value::u8(5), Value::u8(6)]);
+		u8::deserialize(UnwrapSingleValues(val)).expect_err("Too many values, should err");
+		let val = Value::unnamed_composite(vec![]);
+		u8::deserialize(UnwrapSingleValues(val)).expect_err("No values, should err");
+	}
+
+	#[test]
+	fn de_single_field_wrapper_transparently() {
+		// A one-field wrapper type, as decoded from some runtime type like `struct Wrapper(u32)`. This can't be
+		// told apart from a genuine one-element sequence, so it's only unwrapped into a scalar if asked to:
+		let plain = Value::unnamed_composite(vec![Value::u32(5)]);
+		assert!(u32::deserialize(plain.clone()).is_err());
+		assert_eq!(<(u32,)>::deserialize(plain), Ok((5,)));
+
+		let val = UnwrapSingleValues(Value::unnamed_composite(vec![Value::u32(5)]));
+		assert_eq!(u32::deserialize(val.clone()), Ok(5));
+		// Genuine one-tuples are still handled:
+		assert_eq!(<(u32,)>::deserialize(val.clone()), Ok((5,)));
+		// As are newtype structs and sequences:
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Wrapper(u32);
+		assert_eq!(Wrapper::deserialize(val.clone()), Ok(Wrapper(5)));
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Bytes(Vec<u32>);
+		assert_eq!(Bytes::deserialize(val.clone()), Ok(Bytes(vec![5])));
+		assert_eq!(Vec::<u32>::deserialize(val), Ok(vec![5]));
+
+		// A wrapper around a wrapper unwraps all the way to a scalar:
+		let val = UnwrapSingleValues(Value::unnamed_composite(vec![Value::named_composite(vec![(
+			"inner".into(),
+			Value::u32(5),
+		)])]));
+		assert_eq!(u32::deserialize(val.clone()), Ok(5));
+		assert_eq!(<(u32,)>::deserialize(val.clone()), Ok((5,)));
+		assert_eq!(Wrapper::deserialize(val), Ok(Wrapper(5)));
+	}
+
+	#[test]
+	fn de_any_byte_composites() {
+		// A generic value type which asks for whatever we have via `deserialize_any`:
+		#[derive(Debug, PartialEq)]
+		enum Any {
+			Bytes(Vec<u8>),
+			Seq(Vec<Any>),
+			U8(u8),
+			U16(u16),
+		}
+		impl<'de> Deserialize<'de> for Any {
+			fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				struct AnyVisitor;
+				impl<'de> de::Visitor<'de> for AnyVisitor {
+					type Value = Any;
+					fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+						f.write_str("bytes, a sequence, a u8 or a u16")
+					}
+					fn visit_u8<E: de::Error>(self, v: u8) -> Result<Any, E> {
+						Ok(Any::U8(v))
+					}
+					fn visit_u16<E: de::Error>(self, v: u16) -> Result<Any, E> {
+						Ok(Any::U16(v))
+					}
+					fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Any, E> {
+						Ok(Any::Bytes(v))
+					}
+					fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Any, A::Error> {
+						let mut values = Vec::new();
+						while let Some(value) = seq.next_element()? {
+							values.push(value);
+						}
+						Ok(Any::Seq(values))
+					}
+				}
+				deserializer.deserialize_any(AnyVisitor)
+			}
+		}
+
+		let val = Value::unnamed_composite(vec![
+			Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)]),
+			Value::unnamed_composite(vec![Value::u8(3), Value::u16(4)]),
+		]);
+
+		// By default, bytes are handed back as a sequence of values:
+		assert_eq!(
+			Any::deserialize(val.clone()),
+			Ok(Any::Seq(vec![Any::Seq(vec![Any::U8(1), Any::U8(2)]), Any::Seq(vec![Any::U8(3), Any::U16(4)])]))
+		);
+
+		// Wrapped, composites made up entirely of U8s are handed back as bytes:
+		assert_eq!(
+			Any::deserialize(AnyBytes(val)),
+			Ok(Any::Seq(vec![Any::Bytes(vec![1, 2]), Any::Seq(vec![Any::U8(3), Any::U16(4)])]))
+		);
+	}
+
+	#[test]
+	fn de_borrowed_primitive() {
+		let prim = Primitive::Str("hello".into());
+		assert_eq!(<&str>::deserialize(&prim), Ok("hello"));
+		assert_eq!(String::deserialize(&prim), Ok("hello".to_string()));
+
+		// Other primitives behave as they would if they were owned:
+		assert_eq!(u64::deserialize(&Primitive::U8(1)), Ok(1));
+		assert_eq!(f64::deserialize(&Primitive::U8(1)), Ok(1.0));
+		assert_eq!(bool::deserialize(&Primitive::Str("true".into())), Ok(true));
+		<&str>::deserialize(&Primitive::U8(1)).expect_err("not a string");
+	}
+
+	#[test]
+	fn de_ignored_fields_are_skipped() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Foo {
+			a: u8,
+		}
+
+		// A deeply nested value, which would be costly to walk through:
+		let mut big = Value::unnamed_composite(vec![Value::u8(1); 1000]);
+		for n in 0..100 {
+			big = Value::variant("Nested".into(), Composite::Named(vec![(n.to_string(), big)]));
+		}
+		let value = Value::named_composite(vec![("a".into(), Value::u8(1)), ("big".into(), big)]);
+		assert_eq!(Foo::deserialize(&value), Ok(Foo { a: 1 }));
+		assert_eq!(Foo::deserialize(value.clone()), Ok(Foo { a: 1 }));
+
+		// Ignored values are handed over as units, whatever they contain:
+		struct OnlyUnit;
+		impl<'de> Deserialize<'de> for OnlyUnit {
+			fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+				struct UnitVisitor;
+				impl<'de> de::Visitor<'de> for UnitVisitor {
+					type Value = OnlyUnit;
+					fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+						formatter.write_str("a unit")
+					}
+					fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+						Ok(OnlyUnit)
+					}
+				}
+				deserializer.deserialize_ignored_any(UnitVisitor)
+			}
+		}
+		assert!(OnlyUnit::deserialize(&value).is_ok());
+		assert!(OnlyUnit::deserialize(value).is_ok());
+		assert!(OnlyUnit::deserialize(Value::bit_sequence(BitSequence::new())).is_ok());
+		assert!(OnlyUnit::deserialize(&Primitive::Str("hi".into())).is_ok());
+		assert!(OnlyUnit::deserialize(Primitive::U8(1)).is_ok());
+	}
+
+	#[test]
+	fn de_borrowed_str_fields() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Named<'a> {
+			name: &'a str,
+			tags: Vec<&'a str>,
+		}
+
+		let val = Value::named_composite(vec![
+			("name".into(), Value::str("alice".into())),
+			("tags".into(), Value::unnamed_composite(vec![Value::str("a".into()), Value::str("b".into())])),
+		]);
+		let named = Named::deserialize(&val).expect("can deserialize");
+		assert_eq!(named, Named { name: "alice", tags: vec!["a", "b"] });
+
+		// The string is borrowed from the value rather than copied out of it:
+		let original = match &val.value {
+			ValueDef::Composite(Composite::Named(vals)) => match &vals[0].1.value {
+				ValueDef::Primitive(Primitive::Str(s)) => s.as_ptr(),
+				_ => panic!("expected a string"),
+			},
+			_ => panic!("expected a named composite"),
+		};
+		assert_eq!(named.name.as_ptr(), original);
+	}
+
+	#[test]
+	fn de_owned_strings_are_moved_out() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Named {
+			name: String,
+			tags: Vec<Option<String>>,
+		}
+
+		let str_ptr = |val: &Value<()>| match &val.value {
+			ValueDef::Primitive(Primitive::Str(s)) => s.as_ptr(),
+			_ => panic!("expected a string"),
+		};
+		let name = Value::str("alice".into());
+		let tag = Value::str("a".into());
+		let (name_ptr, tag_ptr) = (str_ptr(&name), str_ptr(&tag));
+
+		let val = Value::named_composite(vec![
+			("name".into(), name),
+			(
+				"tags".into(),
+				Value::unnamed_composite(vec![Value::variant("Some".into(), Composite::Unnamed(vec![tag]))]),
+			),
+		]);
+		let named = Named::deserialize(val).expect("can deserialize");
+		assert_eq!(named, Named { name: "alice".into(), tags: vec![Some("a".into())] });
+
+		// Owned values are taken apart, so strings (however deeply nested) are moved out rather than copied:
+		assert_eq!(named.name.as_ptr(), name_ptr);
+		assert_eq!(named.tags[0].as_ref().unwrap().as_ptr(), tag_ptr);
+	}
+
+	#[test]
+	fn de_into_either() {
+		use either::Either;
+		fn de<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Either<u32, String>, D::Error> {
+			either::serde_untagged::deserialize(deserializer)
+		}
+
+		let num = Primitive::U32(5);
+		let string = Primitive::Str("hello".into());
+		assert_eq!(de(&num), Ok(Either::Left(5)));
+		assert_eq!(de(&string), Ok(Either::Right("hello".into())));
+
+		assert_eq!(de(Value::u32(5)), Ok(Either::Left(5)));
+		assert_eq!(de(Value::str("hello".into())), Ok(Either::Right("hello".into())));
+
+		de(&Primitive::Bool(true)).expect_err("neither arm accepts a bool");
+	}
+
+	#[test]
+	fn de_256_bit_into_128_bit() {
+		let mut bytes = [0u8; 32];
+		bytes[..16].copy_from_slice(&u128::MAX.to_le_bytes());
+		assert_eq!(u128::deserialize(Primitive::U256(bytes)), Ok(u128::MAX));
+		assert_eq!(u128::deserialize(Value::primitive(Primitive::U256(bytes))), Ok(u128::MAX));
+
+		let mut bytes = [0xFFu8; 32];
+		bytes[..16].copy_from_slice(&(-5i128).to_le_bytes());
+		assert_eq!(i128::deserialize(Primitive::I256(bytes)), Ok(-5));
+
+		let mut bytes = [0u8; 32];
+		bytes[..16].copy_from_slice(&i128::MAX.to_le_bytes());
+		assert_eq!(i128::deserialize(Primitive::I256(bytes)), Ok(i128::MAX));
+	}
+
+	#[test]
+	fn de_256_bit_into_128_bit_overflow() {
+		let mut bytes = [0u8; 32];
+		bytes[16] = 1;
+		u128::deserialize(Primitive::U256(bytes)).expect_err("too large for a u128");
+
+		// Positive, but the high bytes aren't a sign extension:
+		let mut bytes = [0u8; 32];
+		bytes[..16].copy_from_slice(&(-5i128).to_le_bytes());
+		i128::deserialize(Primitive::I256(bytes)).expect_err("out of range for an i128");
+	}
+
+	#[test]
+	fn de_into_tuple_with_trailing_values() {
+		let val = Composite::Unnamed(vec![Value::u8(1), Value::bool(true), Value::str("extra".into()), Value::u8(2)]);
+		assert_eq!(
+			<(u8, bool)>::deserialize(val),
+			Err(Error::from_str(
+				"Cannot deserialize composite of length 4 into tuple of length 2: there are 2 trailing values, the first of which is the primitive Str(\"extra\")"
+			))
+		);
+	}
+
+	#[test]
+	fn de_into_recursive_type() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum Tree {
+			Leaf(u8),
+			Node(Box<Tree>, Box<Tree>),
+		}
+
+		// Build matching balanced trees of the given depth, numbering the leaves from left to right:
+		fn trees(depth: usize, next_leaf: &mut u8) -> (Value<()>, Tree) {
+			if depth == 0 {
+				let n = *next_leaf;
+				*next_leaf += 1;
+				return (Value::variant("Leaf".into(), Composite::Unnamed(vec![Value::u8(n)])), Tree::Leaf(n));
+			}
+			let (left_val, left_tree) = trees(depth - 1, next_leaf);
+			let (right_val, right_tree) = trees(depth - 1, next_leaf);
+			let val = Value::variant("Node".into(), Composite::Unnamed(vec![left_val, right_val]));
+			(val, Tree::Node(Box::new(left_tree), Box::new(right_tree)))
+		}
+
+		let (val, expected) = trees(6, &mut 0);
+		assert_eq!(Tree::deserialize(val), Ok(expected));
+	}
+
+	#[test]
+	fn de_by_reference() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Transfer<'a> {
+			dest: &'a str,
+			amount: u128,
+		}
+		#[derive(Deserialize, Debug, PartialEq)]
+		#[serde(deny_unknown_fields)]
+		struct Remark {
+			remark: Vec<u8>,
+		}
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Amount {
+			amount: u64,
+		}
+
+		let val = Value::named_composite(vec![
+			("dest".into(), Value::str("alice".into())),
+			("amount".into(), Value::u128(100)),
+		]);
+
+		// Failing to deserialize leaves the value intact, so that we can try again:
+		Remark::deserialize(&val).expect_err("not a remark");
+		assert_eq!(Transfer::deserialize(&val), Ok(Transfer { dest: "alice", amount: 100 }));
+		assert_eq!(Amount::deserialize(&val), Ok(Amount { amount: 100 }));
+		assert_eq!(crate::value::from_value_ref(&val), Ok(Amount { amount: 100 }));
+	}
+
+	#[test]
+	fn de_by_reference_matches_owned() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum MyEnum {
+			Foo(u8, Option<bool>),
+			Bar { a: String, b: (u8, u8) },
+		}
+
+		let vals = vec![
+			Value::variant(
+				"Foo".into(),
+				Composite::Unnamed(vec![Value::u8(1), Value::variant("None".into(), Composite::Unnamed(vec![]))]),
+			),
+			Value::variant(
+				"Foo".into(),
+				Composite::Unnamed(vec![
+					Value::unnamed_composite(vec![Value::u8(1)]),
+					Value::variant("Some".into(), Composite::Unnamed(vec![Value::bool(true)])),
+				]),
+			),
+			Value::variant(
+				"Bar".into(),
+				Composite::Named(vec![
+					("a".into(), Value::str("hi".into())),
+					("b".into(), Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)])),
+				]),
+			),
+			Value::variant("Bar".into(), Composite::Named(vec![("a".into(), Value::str("hi".into()))])),
+			Value::variant("Foo".into(), Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)])),
+		];
+
+		for val in vals {
+			assert_eq!(MyEnum::deserialize(&val), MyEnum::deserialize(val.clone()));
+		}
+	}
+
+	#[test]
+	fn de_floats() {
+		assert_eq!(f32::deserialize(Value::f32(1.5)), Ok(1.5));
+		assert_eq!(f64::deserialize(Value::f64(-2.25)), Ok(-2.25));
+		assert_eq!(f64::deserialize(Value::f32(1.5)), Ok(1.5));
+		u8::deserialize(Value::f64(1.0)).expect_err("floats aren't integers");
+
+		// Floats from other formats round trip through a Value:
+		let value: Value<()> = serde_json::from_str("[1.5, 2]").expect("valid JSON");
+		assert_eq!(value, Value::unnamed_composite(vec![Value::f64(1.5), Value::u64(2)]));
+		assert_eq!(serde_json::to_string(&value).expect("can serialize"), "[1.5,2]");
+		assert_eq!(<(f64, u8)>::deserialize(value), Ok((1.5, 2)));
+
+		// As with the float types themselves, NaN is not equal to itself:
+		assert_ne!(Value::f64(f64::NAN), Value::f64(f64::NAN));
+	}
+
+	#[test]
+	fn de_str_into_float() {
+		assert_eq!(f64::deserialize(ParseFloats(Value::str("2.75".into()))), Ok(2.75));
+		assert_eq!(f32::deserialize(ParseFloats(Value::str(" -2.5 ".into()))), Ok(-2.5));
+		// Numbers are still converted as before:
+		assert_eq!(f64::deserialize(ParseFloats(Value::u8(3))), Ok(3.0));
+		assert_eq!(f64::deserialize(Value::u8(3)), Ok(3.0));
+
+		f64::deserialize(ParseFloats(Value::str("2.75.1".into()))).expect_err("not a number");
+		f32::deserialize(ParseFloats(Value::str("".into()))).expect_err("not a number");
+
+		// Strings aren't parsed into floats unless asked for:
+		f64::deserialize(Value::str("2.75".into())).expect_err("strings aren't floats");
+	}
 
+	#[test]
+	fn de_nested_str_into_float() {
 		#[derive(Deserialize, Debug, PartialEq)]
-		struct FooVecU8(Vec<u8>);
-		let val = Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]);
-		assert_eq!(FooVecU8::deserialize(val), Ok(FooVecU8(vec![1, 2, 3])));
+		struct Price {
+			amount: f64,
+			fee: f32,
+		}
 
 		#[derive(Deserialize, Debug, PartialEq)]
-		enum MyEnum {
-			Foo(u8, u8, u8),
+		struct Order {
+			price: Price,
+			quantity: u8,
 		}
+
+		// Strings are parsed wherever they are nested, including inside single value composites:
+		let val = Value::named_composite(vec![
+			(
+				"price".into(),
+				Value::named_composite(vec![
+					("amount".into(), Value::str("2.75".into())),
+					("fee".into(), Value::unnamed_composite(vec![Value::str("0.5".into())])),
+				]),
+			),
+			("quantity".into(), Value::u8(3)),
+		]);
+		Order::deserialize(val.clone()).expect_err("strings aren't floats");
+		assert_eq!(
+			Order::deserialize(ParseFloats(val)),
+			Ok(Order { price: Price { amount: 2.75, fee: 0.5 }, quantity: 3 })
+		);
+
+		// Composites containing more than one value still can't be treated as a float:
+		let val = Value::named_composite(vec![(
+			"price".into(),
+			Value::unnamed_composite(vec![Value::str("2.75".into()), Value::str("0.5".into())]),
+		)]);
 		#[derive(Deserialize, Debug, PartialEq)]
-		struct FooVar(MyEnum);
-		let val =
-			Variant { name: "Foo".into(), values: Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]) };
-		assert_eq!(FooVar::deserialize(val), Ok(FooVar(MyEnum::Foo(1, 2, 3))));
+		struct Total {
+			price: f64,
+		}
+		Total::deserialize(ParseFloats(val)).expect_err("too many values");
 	}
 
 	#[test]
-	fn de_into_vec() {
-		let val = ValueDef::Composite(Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]));
-		assert_eq!(<Vec<u8>>::deserialize(val), Ok(vec![1, 2, 3]));
+	fn de_str_and_int_into_char() {
+		assert_eq!(char::deserialize(Value::str("a".into())), Ok('a'));
+		assert_eq!(char::deserialize(Value::str("é".into())), Ok('é'));
+		char::deserialize(Value::str("ab".into())).expect_err("too many chars");
+		char::deserialize(Value::str("".into())).expect_err("no chars");
 
-		let val = ValueDef::Composite(Composite::Unnamed(vec![
-			Value::str("a".into()),
-			Value::str("b".into()),
-			Value::str("c".into()),
-		]));
-		assert_eq!(<Vec<String>>::deserialize(val), Ok(vec!["a".into(), "b".into(), "c".into()]));
+		assert_eq!(char::deserialize(Value::u32(97)), Ok('a'));
+		assert_eq!(char::deserialize(Value::u8(0x41)), Ok('A'));
+		char::deserialize(Value::u32(0xD800)).expect_err("surrogates are not chars");
+		char::deserialize(Value::u32(0x110000)).expect_err("out of range");
+		char::deserialize(Value::i32(-1)).expect_err("negative");
+
+		assert_eq!(char::deserialize(Value::char('z')), Ok('z'));
 	}
 
 	#[test]
-	fn de_unwrapped_into_vec() {
-		let val = Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]);
-		assert_eq!(<Vec<u8>>::deserialize(val), Ok(vec![1, 2, 3]));
+	fn de_struct_variant_into_map() {
+		use std::collections::HashMap;
 
-		let val =
-			Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::u8(2)), ("c".into(), Value::u8(3))]);
-		assert_eq!(<Vec<u8>>::deserialize(val), Ok(vec![1, 2, 3]));
+		let variant = Variant {
+			name: "Foo".into(),
+			values: Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::u8(2))]),
+			index: None,
+		};
+		let expected: HashMap<String, u8> = vec![("a".to_string(), 1), ("b".to_string(), 2)].into_iter().collect();
 
-		let val = Composite::Unnamed(vec![Value::str("a".into()), Value::str("b".into()), Value::str("c".into())]);
-		assert_eq!(<Vec<String>>::deserialize(val), Ok(vec!["a".into(), "b".into(), "c".into()]));
+		assert_eq!(HashMap::<String, u8>::deserialize(variant.clone()), Ok(expected.clone()));
+		assert_eq!(HashMap::<String, u8>::deserialize(Value::with_context(variant.into(), ())), Ok(expected));
 	}
 
 	#[test]
-	fn de_into_map() {
-		use std::collections::HashMap;
+	fn de_composite_into_map() {
+		use std::collections::BTreeMap;
 
-		let val = ValueDef::Composite(Composite::Named(vec![
-			("a".into(), Value::u8(1)),
-			("b".into(), Value::u8(2)),
-			("c".into(), Value::u8(3)),
-		]));
-		assert_eq!(
-			<HashMap<String, u8>>::deserialize(val),
-			Ok(vec![("a".into(), 1), ("b".into(), 2), ("c".into(), 3)].into_iter().collect())
-		);
+		let named = Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::u8(2))]);
+		let expected: BTreeMap<String, u8> = vec![("a".to_string(), 1), ("b".to_string(), 2)].into_iter().collect();
+		assert_eq!(BTreeMap::<String, u8>::deserialize(&named), Ok(expected.clone()));
+		assert_eq!(BTreeMap::<String, u8>::deserialize(named), Ok(expected));
 
-		let val = ValueDef::Composite(Composite::Unnamed(vec![Value::u8(1), Value::u8(2), Value::u8(3)]));
-		<HashMap<String, u8>>::deserialize(val).expect_err("no names; can't be map");
+		let unnamed = Composite::Unnamed(vec![Value::u8(1), Value::u8(2)]);
+		let expected_err = Error::from_str("Cannot deserialize an unnamed composite into a map");
+		assert_eq!(BTreeMap::<String, u8>::deserialize(&unnamed), Err(expected_err.clone()));
+		assert_eq!(BTreeMap::<String, u8>::deserialize(unnamed), Err(expected_err));
+
+		// Empty composites are unit-like, and so can be treated as empty maps:
+		assert_eq!(BTreeMap::<String, u8>::deserialize(Composite::<()>::Unnamed(vec![])), Ok(BTreeMap::new()));
 	}
 
 	#[test]
-	fn de_into_tuple() {
-		let val = ValueDef::Composite(Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true)]));
-		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+	fn de_char_into_int() {
+		assert_eq!(u32::deserialize(Value::char('a')), Ok(97));
+		assert_eq!(u8::deserialize(Value::char('a')), Ok(97));
+		assert_eq!(u32::deserialize(Value::char('€')), Ok(0x20AC));
+		u8::deserialize(Value::char('€')).expect_err("code point too large for u8");
 
-		// names will just be ignored:
-		let val = ValueDef::Composite(Composite::Named(vec![
-			("a".into(), Value::str("hello".into())),
-			("b".into(), Value::bool(true)),
-		]));
-		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+		// Chars are still chars when asked for:
+		assert_eq!(char::deserialize(Value::char('a')), Ok('a'));
+	}
 
-		// Enum variants are allowed! The variant name will be ignored:
-		let val = ValueDef::Variant(Variant {
-			name: "Foo".into(),
-			values: Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true)]),
-		});
-		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+	#[test]
+	fn de_str_into_bool() {
+		assert_eq!(bool::deserialize(Value::str("true".into())), Ok(true));
+		assert_eq!(bool::deserialize(Value::str("False".into())), Ok(false));
+		assert_eq!(bool::deserialize(Value::bool(true)), Ok(true));
+		bool::deserialize(Value::str("yes".into())).expect_err("not a bool");
 
-		// Enum variants with names values are allowed! The variant name will be ignored:
-		let val = ValueDef::Variant(Variant {
-			name: "Foo".into(),
-			values: Composite::Named(vec![("a".into(), Value::str("hello".into())), ("b".into(), Value::bool(true))]),
-		});
-		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+		// Strings are still strings if that's what's asked for:
+		assert_eq!(String::deserialize(Value::str("true".into())), Ok("true".to_string()));
+	}
 
-		// Wrong number of values should fail:
-		let val = ValueDef::Composite(Composite::Unnamed(vec![
-			Value::str("hello".into()),
-			Value::bool(true),
-			Value::u8(123),
-		]));
-		<(String, bool)>::deserialize(val).expect_err("Wrong length, should err");
+	#[test]
+	fn de_option_variants_into_option() {
+		let none = Variant { name: "None".into(), values: Composite::Unnamed(vec![]), index: Some(0) };
+		let some = Variant { name: "Some".into(), values: Composite::Unnamed(vec![Value::u8(42)]), index: Some(1) };
+
+		// Directly from the variants..
+		assert_eq!(Option::<u8>::deserialize(none.clone()), Ok(None));
+		assert_eq!(Option::<u8>::deserialize(some.clone()), Ok(Some(42)));
+
+		// ..and from values wrapping them:
+		assert_eq!(Option::<u8>::deserialize(Value::with_context(ValueDef::Variant(none), ())), Ok(None));
+		assert_eq!(Option::<u8>::deserialize(Value::with_context(ValueDef::Variant(some), ())), Ok(Some(42)));
 	}
 
 	#[test]
-	fn de_unwrapped_into_tuple() {
-		let val = Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true)]);
-		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+	fn de_nested_option_variants_into_nested_options() {
+		let none = || Variant { name: "None".into(), values: Composite::Unnamed(vec![]), index: Some(0) };
+		let some = |v: Value<()>| Variant { name: "Some".into(), values: Composite::Unnamed(vec![v]), index: Some(1) };
+		let val = |v: Variant<()>| Value::with_context(ValueDef::Variant(v), ());
 
-		// names will just be ignored:
-		let val = Composite::Named(vec![("a".into(), Value::str("hello".into())), ("b".into(), Value::bool(true))]);
-		assert_eq!(<(String, bool)>::deserialize(val), Ok(("hello".into(), true)));
+		assert_eq!(<Option<Option<u32>>>::deserialize(val(none())), Ok(None));
+		assert_eq!(<Option<Option<u32>>>::deserialize(val(some(val(none())))), Ok(Some(None)));
+		assert_eq!(<Option<Option<u32>>>::deserialize(val(some(val(some(Value::u32(1)))))), Ok(Some(Some(1))));
+	}
 
-		// Wrong number of values should fail:
-		let val = Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true), Value::u8(123)]);
-		<(String, bool)>::deserialize(val).expect_err("Wrong length, should err");
+	#[test]
+	fn de_primitive_into_one_tuple() {
+		assert_eq!(<(u32,)>::deserialize(Primitive::U32(5)), Ok((5,)));
+		assert_eq!(<(u32,)>::deserialize(Value::u32(5)), Ok((5,)));
+
+		<(u32, u32)>::deserialize(Primitive::U32(5)).expect_err("Wrong length, should err");
 	}
 
 	#[test]
@@ -1233,6 +2946,7 @@ mod test {
 		let val = ValueDef::Variant(Variant {
 			name: "Foo".into(),
 			values: Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true), Value::u8(123)]),
+			index: None,
 		});
 		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Foo("hello".into(), true, 123)));
 
@@ -1244,6 +2958,7 @@ mod test {
 				("b".into(), Value::bool(true)),
 				("c".into(), Value::u8(123)),
 			]),
+			index: None,
 		});
 		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Foo("hello".into(), true, 123)));
 	}
@@ -1258,6 +2973,7 @@ mod test {
 		let val = Variant {
 			name: "Foo".into(),
 			values: Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true), Value::u8(123)]),
+			index: None,
 		};
 		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Foo("hello".into(), true, 123)));
 
@@ -1269,6 +2985,7 @@ mod test {
 				("b".into(), Value::bool(true)),
 				("c".into(), Value::u8(123)),
 			]),
+			index: None,
 		};
 		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Foo("hello".into(), true, 123)));
 	}
@@ -1289,6 +3006,7 @@ mod test {
 				("a".into(), Value::bool(true)),
 				("hi".into(), Value::str("hello".into())),
 			]),
+			index: None,
 		});
 		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Foo { hi: "hello".into(), a: true, b: 123 }));
 
@@ -1296,6 +3014,7 @@ mod test {
 		let val = ValueDef::Variant(Variant {
 			name: "Foo".into(),
 			values: Composite::Unnamed(vec![Value::str("hello".into()), Value::bool(true), Value::u8(123)]),
+			index: None,
 		});
 		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Foo { hi: "hello".into(), a: true, b: 123 }));
 
@@ -1303,6 +3022,7 @@ mod test {
 		let val = ValueDef::Variant(Variant {
 			name: "Foo".into(),
 			values: Composite::Unnamed(vec![Value::bool(true), Value::u8(123), Value::str("hello".into())]),
+			index: None,
 		});
 		MyEnum::deserialize(val).expect_err("Wrong order shouldn't work");
 
@@ -1315,6 +3035,7 @@ mod test {
 				("c".into(), Value::bool(true)),
 				("hi".into(), Value::str("hello".into())),
 			]),
+			index: None,
 		});
 		MyEnum::deserialize(val).expect_err("Wrong names shouldn't work");
 
@@ -1328,14 +3049,375 @@ mod test {
 				("bar".into(), Value::bool(false)),
 				("hi".into(), Value::str("hello".into())),
 			]),
+			index: None,
 		});
 		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Foo { hi: "hello".into(), a: true, b: 123 }));
 	}
 
+	#[test]
+	fn de_into_variant_by_index() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum MyEnum {
+			First,
+			Renamed(u8, bool),
+		}
+
+		let val = Value::with_context(
+			ValueDef::Variant(Variant {
+				name: "Second".into(),
+				values: Composite::Unnamed(vec![Value::u8(1), Value::bool(true)]),
+				index: Some(1),
+			}),
+			(),
+		);
+
+		// The name doesn't line up, so this won't work normally..
+		MyEnum::deserialize(val.clone()).expect_err("names don't match");
+		// ..but the index does:
+		assert_eq!(MyEnum::deserialize(ByIndex(val)), Ok(MyEnum::Renamed(1, true)));
+
+		let val = Value::variant("Zeroth".into(), Composite::Unnamed(vec![]));
+		MyEnum::deserialize(ByIndex(val)).expect_err("no index to go by");
+
+		let val = Value::with_context(
+			ValueDef::Variant(Variant { name: "Third".into(), values: Composite::Unnamed(vec![]), index: Some(2) }),
+			(),
+		);
+		MyEnum::deserialize(ByIndex(val)).expect_err("index is out of bounds");
+	}
+
+	#[test]
+	fn de_into_variant_by_index_with_explicit_codec_indexes() {
+		use codec::Encode;
+
+		#[derive(Encode, scale_info::TypeInfo)]
+		enum Encoded {
+			#[codec(index = 7)]
+			Foo(u8),
+			#[codec(index = 3)]
+			Bar(bool),
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum Target {
+			First(u8),
+			Second(bool),
+		}
+
+		let mut registry = scale_info::Registry::new();
+		let id: crate::TypeId = registry.register_type(&scale_info::MetaType::new::<Encoded>()).into();
+		let types: scale_info::PortableRegistry = registry.into();
+
+		let decode = |encoded: Encoded| {
+			let bytes = encoded.encode();
+			crate::decoder::decode_value::decode_value_by_id(&mut &*bytes, id, &types).expect("can decode")
+		};
+
+		// Variants are matched on their position in the enum, and not on the index they are encoded with:
+		assert_eq!(Target::deserialize(ByIndex(decode(Encoded::Foo(1)))), Ok(Target::First(1)));
+		assert_eq!(Target::deserialize(ByIndex(decode(Encoded::Bar(true)))), Ok(Target::Second(true)));
+	}
+
+	#[test]
+	fn de_unknown_field_lists_present_fields() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		#[serde(deny_unknown_fields)]
+		struct Foo {
+			a: u8,
+		}
+
+		let val = Value::named_composite(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]);
+		let err = Foo::deserialize(val).expect_err("b is an unknown field").to_string();
+
+		assert!(err.contains("unknown field `b`"), "error was: {}", err);
+		assert!(err.contains("the fields present are `a`, `b`"), "error was: {}", err);
+	}
+
+	#[test]
+	fn de_named_composite_into_map_with_int_keys() {
+		use std::collections::BTreeMap;
+
+		let val = Value::named_composite(vec![
+			("0".into(), Value::bool(true)),
+			("1".into(), Value::bool(false)),
+			("10".into(), Value::bool(true)),
+		]);
+		let expected: BTreeMap<u32, bool> = vec![(0, true), (1, false), (10, true)].into_iter().collect();
+		assert_eq!(BTreeMap::<u32, bool>::deserialize(val), Ok(expected));
+
+		// String keys still work as before:
+		let val = Value::named_composite(vec![("a".into(), Value::bool(true))]);
+		let expected: BTreeMap<String, bool> = vec![("a".to_string(), true)].into_iter().collect();
+		assert_eq!(BTreeMap::<String, bool>::deserialize(val.clone()), Ok(expected));
+
+		// But non-numeric keys can't be parsed into integers:
+		BTreeMap::<u32, bool>::deserialize(val).expect_err("key is not numeric");
+	}
+
+	#[test]
+	fn de_into_variant_with_loose_matching() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		#[serde(rename_all = "snake_case")]
+		enum MyEnum {
+			AllGood(u8),
+			Allgood(u8),
+			NotGood,
+		}
+
+		let val = Value::variant("NotGood".into(), Composite::Unnamed(vec![]));
+
+		// The names differ in case, so this won't work normally..
+		MyEnum::deserialize(val.clone()).expect_err("names don't match");
+		MyEnum::deserialize(WithVariantMatch(val.clone(), VariantMatch::Exact)).expect_err("names don't match");
+		// ..but will with loose matching:
+		assert_eq!(MyEnum::deserialize(WithVariantMatch(val, VariantMatch::Loose)), Ok(MyEnum::NotGood));
+
+		// Exact matches take priority, so there's no ambiguity here:
+		let val = Value::variant("allgood".into(), Composite::Unnamed(vec![Value::u8(1)]));
+		assert_eq!(MyEnum::deserialize(WithVariantMatch(val, VariantMatch::Loose)), Ok(MyEnum::Allgood(1)));
+
+		// But without an exact match, "all_good" and "allgood" are ambiguous, so neither is picked:
+		let val = Value::variant("AllGood".into(), Composite::Unnamed(vec![Value::u8(1)]));
+		MyEnum::deserialize(WithVariantMatch(val, VariantMatch::Loose)).expect_err("ambiguous match");
+	}
+
+	#[test]
+	fn de_loose_field_names() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Header {
+			#[serde(rename = "block_number")]
+			block_number: u32,
+			parent_hash: String,
+		}
+
+		let val = Value::named_composite(vec![
+			("blockNumber".into(), Value::u32(100)),
+			("ParentHash".into(), Value::str("0x00".into())),
+		]);
+		Header::deserialize(val.clone()).expect_err("names don't match exactly");
+		assert_eq!(
+			Header::deserialize(LooseFieldNames(val)),
+			Ok(Header { block_number: 100, parent_hash: "0x00".into() })
+		);
+
+		// Exact matches are left alone, and ambiguous names aren't matched:
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Ambiguous {
+			a_b: u8,
+			ab: u8,
+		}
+		let val = Value::named_composite(vec![("ab".into(), Value::u8(1)), ("A_B".into(), Value::u8(2))]);
+		Ambiguous::deserialize(LooseFieldNames(val)).expect_err("A_B matches both fields");
+		let val = Value::named_composite(vec![("ab".into(), Value::u8(1)), ("a_b".into(), Value::u8(2))]);
+		assert_eq!(Ambiguous::deserialize(LooseFieldNames(val)), Ok(Ambiguous { a_b: 2, ab: 1 }));
+	}
+
+	#[test]
+	fn de_nested_loose_field_names() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Header {
+			block_number: u32,
+			parent_hash: String,
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Block {
+			header: Header,
+			extrinsics_count: u32,
+		}
+
+		// Field names are matched loosely at every level, and not just the outermost one:
+		let val = Value::named_composite(vec![
+			(
+				"Header".into(),
+				Value::named_composite(vec![
+					("blockNumber".into(), Value::u32(100)),
+					("ParentHash".into(), Value::str("0x00".into())),
+				]),
+			),
+			("extrinsicsCount".into(), Value::u32(2)),
+		]);
+		Block::deserialize(val.clone()).expect_err("names don't match exactly");
+		assert_eq!(
+			Block::deserialize(LooseFieldNames(val)),
+			Ok(Block { header: Header { block_number: 100, parent_hash: "0x00".into() }, extrinsics_count: 2 })
+		);
+	}
+
+	#[test]
+	fn de_loose_field_names_into_map() {
+		use std::collections::BTreeMap;
+
+		// Maps don't say which keys they expect, so names are converted to snake case:
+		let val = Value::named_composite(vec![
+			("blockNumber".into(), Value::u32(100)),
+			("Parent-Hash".into(), Value::u32(0)),
+			("extrinsics_count".into(), Value::u32(2)),
+		]);
+		let expected: BTreeMap<String, u32> = vec![
+			("block_number".to_string(), 100),
+			("parent_hash".to_string(), 0),
+			("extrinsics_count".to_string(), 2),
+		]
+		.into_iter()
+		.collect();
+		assert_eq!(BTreeMap::<String, u32>::deserialize(LooseFieldNames(val)), Ok(expected));
+	}
+
+	#[test]
+	fn de_loose_variant_match_single_candidate() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		#[serde(rename_all = "snake_case")]
+		enum MyEnum {
+			AllGood,
+			NotGood,
+		}
+
+		let val = Value::variant("AllGood".into(), Composite::Unnamed(vec![]));
+		assert_eq!(MyEnum::deserialize(WithVariantMatch(val, VariantMatch::Loose)), Ok(MyEnum::AllGood));
+	}
+
+	#[test]
+	fn de_tagged_variant_into_flattened_row() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Row {
+			#[serde(flatten)]
+			call: Call,
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Remark {
+			remark: String,
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		#[serde(tag = "call")]
+		enum Call {
+			Transfer { dest: String, value: u64 },
+			Remark { remark: String },
+		}
+
+		let call = Value::variant(
+			"Transfer".into(),
+			Composite::Named(vec![("dest".into(), Value::str("Alice".into())), ("value".into(), Value::u64(100))]),
+		);
+
+		// The tagged variant can be deserialized into an internally tagged enum..
+		assert_eq!(
+			Call::deserialize(TaggedVariant(call.clone(), "call")),
+			Ok(Call::Transfer { dest: "Alice".into(), value: 100 })
+		);
+
+		// ..and in the same way, into a row with that enum flattened into it:
+		assert_eq!(
+			Row::deserialize(TaggedVariant(call, "call")),
+			Ok(Row { call: Call::Transfer { dest: "Alice".into(), value: 100 } })
+		);
+
+		// Non-variants are deserialized as normal:
+		let val = Value::named_composite(vec![("remark".into(), Value::str("hi".into()))]);
+		assert_eq!(Remark::deserialize(TaggedVariant(val, "call")), Ok(Remark { remark: "hi".into() }));
+	}
+
+	#[test]
+	fn de_unknown_variant_into_fallback() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum MyEnum {
+			Known(u8),
+			Unknown,
+		}
+
+		let val = Value::variant("AddedLater".into(), Composite::Unnamed(vec![Value::bool(true)]));
+
+		MyEnum::deserialize(val.clone()).expect_err("unknown variant");
+		assert_eq!(MyEnum::deserialize(WithFallbackVariant(val, "Unknown")), Ok(MyEnum::Unknown));
+
+		// Known variants are deserialized as normal:
+		let val = Value::variant("Known".into(), Composite::Unnamed(vec![Value::u8(1)]));
+		assert_eq!(MyEnum::deserialize(WithFallbackVariant(val, "Unknown")), Ok(MyEnum::Known(1)));
+
+		// Variants nested inside other values fall back too:
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Events {
+			events: Vec<MyEnum>,
+		}
+		let val = Value::named_composite(vec![(
+			"events".into(),
+			Value::unnamed_composite(vec![
+				Value::variant("Known".into(), Composite::Unnamed(vec![Value::u8(1)])),
+				Value::variant("AddedLater".into(), Composite::Unnamed(vec![Value::bool(true)])),
+			]),
+		)]);
+		Events::deserialize(val.clone()).expect_err("unknown nested variant");
+		assert_eq!(
+			Events::deserialize(WithFallbackVariant(val, "Unknown")),
+			Ok(Events { events: vec![MyEnum::Known(1), MyEnum::Unknown] })
+		);
+	}
+
+	#[test]
+	fn de_into_newtype_variant() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		struct Inner {
+			a: u8,
+			b: bool,
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum MyEnum {
+			Foo(Inner),
+			Bar(u8),
+		}
+
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum MyStructEnum {
+			Foo { a: u8, b: bool },
+		}
+
+		let inner = Value::named_composite(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]);
+
+		// A decoded `Foo(Inner)` has a single unnamed value, which is the struct:
+		let val = Value::variant("Foo".into(), Composite::Unnamed(vec![inner.clone()]));
+		assert_eq!(MyEnum::deserialize(val.clone()), Ok(MyEnum::Foo(Inner { a: 1, b: true })));
+		MyStructEnum::deserialize(val).expect_err("a struct variant has 2 fields, not 1");
+
+		// A decoded `Foo { a, b }` has named values, which can be deserialized into either shape:
+		let val = Value::variant(
+			"Foo".into(),
+			Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]),
+		);
+		assert_eq!(MyEnum::deserialize(val.clone()), Ok(MyEnum::Foo(Inner { a: 1, b: true })));
+		assert_eq!(MyStructEnum::deserialize(val), Ok(MyStructEnum::Foo { a: 1, b: true }));
+
+		// Single primitive values are unwrapped, too:
+		let val = Value::variant("Bar".into(), Composite::Unnamed(vec![Value::u8(123)]));
+		assert_eq!(MyEnum::deserialize(val), Ok(MyEnum::Bar(123)));
+	}
+
+	#[test]
+	fn de_strings_into_unit_variants() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum MyEnum {
+			Foo,
+			Bar,
+			Baz(u8),
+		}
+
+		assert_eq!(MyEnum::deserialize(Primitive::Str("Foo".into())), Ok(MyEnum::Foo));
+		assert_eq!(MyEnum::deserialize(&Primitive::Str("Bar".into())), Ok(MyEnum::Bar));
+		assert_eq!(MyEnum::deserialize(Value::str("Foo".into())), Ok(MyEnum::Foo));
+		assert_eq!(MyEnum::deserialize(&Value::str("Bar".into())), Ok(MyEnum::Bar));
+
+		// Unknown names and variants with fields can't be deserialized from strings:
+		MyEnum::deserialize(Value::str("Qux".into())).expect_err("no such variant");
+		MyEnum::deserialize(Value::str("Baz".into())).expect_err("not a unit variant");
+		MyEnum::deserialize(Value::u8(1)).expect_err("not a string");
+	}
+
 	#[test]
 	fn de_into_unit_variants() {
 		let val = Value::variant("Foo".into(), Composite::Named(vec![]));
-		let unwrapped_val = Variant::<()> { name: "Foo".into(), values: Composite::Named(vec![]) };
+		let unwrapped_val = Variant::<()> { name: "Foo".into(), values: Composite::Named(vec![]), index: None };
 
 		#[derive(Deserialize, Debug, PartialEq)]
 		enum MyEnum {
@@ -0,0 +1,174 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{Composite, PathSegment, Value, ValueDef};
+
+/// A point at which two values differ. See [`Value::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference<'a, T> {
+	/// The path to the differing values, which can be handed to [`Value::at`] on either side.
+	pub path: Vec<PathSegment>,
+	/// The value found at this path on the left hand side.
+	pub left: &'a Value<T>,
+	/// The value found at this path on the right hand side.
+	pub right: &'a Value<T>,
+}
+
+impl<T> Value<T> {
+	/// Compare this value with another, returning each point at which they differ. Composites and variants
+	/// of the same shape (ie with the same field names, or the same number of unnamed fields, and for variants
+	/// the same name and index) are compared field by field, and otherwise the values are reported as a single
+	/// difference at that point. Contexts are not compared. Two equal values have no differences.
+	pub fn diff<'a>(&'a self, other: &'a Value<T>) -> Vec<Difference<'a, T>> {
+		let mut differences = Vec::new();
+		diff_values(self, other, &mut Vec::new(), &mut differences);
+		differences
+	}
+}
+
+fn diff_values<'a, T>(
+	left: &'a Value<T>,
+	right: &'a Value<T>,
+	path: &mut Vec<PathSegment>,
+	differences: &mut Vec<Difference<'a, T>>,
+) {
+	let same = match (&left.value, &right.value) {
+		(ValueDef::Composite(l), ValueDef::Composite(r)) => diff_composites(l, r, path, differences),
+		(ValueDef::Variant(l), ValueDef::Variant(r)) => {
+			l.name == r.name && l.index == r.index && diff_composites(&l.values, &r.values, path, differences)
+		}
+		(ValueDef::BitSequence(l), ValueDef::BitSequence(r)) => l == r,
+		(ValueDef::Primitive(l), ValueDef::Primitive(r)) => l == r,
+		_ => false,
+	};
+	if !same {
+		differences.push(Difference { path: path.clone(), left, right });
+	}
+}
+
+// Diff the fields of two composites, returning false without diffing anything if their shapes don't match.
+fn diff_composites<'a, T>(
+	left: &'a Composite<T>,
+	right: &'a Composite<T>,
+	path: &mut Vec<PathSegment>,
+	differences: &mut Vec<Difference<'a, T>>,
+) -> bool {
+	match (left, right) {
+		(Composite::Named(l), Composite::Named(r)) => {
+			if l.len() != r.len() || l.iter().zip(r).any(|((l_name, _), (r_name, _))| l_name != r_name) {
+				return false;
+			}
+			for ((name, l), (_, r)) in l.iter().zip(r) {
+				path.push(PathSegment::Field(name.clone()));
+				diff_values(l, r, path, differences);
+				path.pop();
+			}
+			true
+		}
+		(Composite::Unnamed(l), Composite::Unnamed(r)) => {
+			if l.len() != r.len() {
+				return false;
+			}
+			for (idx, (l, r)) in l.iter().zip(r).enumerate() {
+				path.push(PathSegment::Index(idx));
+				diff_values(l, r, path, differences);
+				path.pop();
+			}
+			true
+		}
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	fn call(amount: u128, memo: &str) -> Value<()> {
+		Value::variant(
+			"transfer".into(),
+			Composite::Named(vec![
+				("dest".into(), Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)])),
+				(
+					"details".into(),
+					Value::named_composite(vec![
+						("amount".into(), Value::u128(amount)),
+						("memo".into(), Value::str(memo.into())),
+					]),
+				),
+			]),
+		)
+	}
+
+	fn paths<T>(differences: &[Difference<'_, T>]) -> Vec<Vec<PathSegment>> {
+		differences.iter().map(|d| d.path.clone()).collect()
+	}
+
+	#[test]
+	fn equal_values_have_no_differences() {
+		assert_eq!(call(100, "hi").diff(&call(100, "hi")), vec![]);
+		// Contexts are ignored:
+		let left = call(100, "hi").map_context(|_| 1u8);
+		let right = call(100, "hi").map_context(|_| 2u8);
+		assert_eq!(left.diff(&right), vec![]);
+	}
+
+	#[test]
+	fn nested_differences_are_reported_with_paths() {
+		let left = call(100, "hi");
+		let right = call(200, "hello");
+		let differences = left.diff(&right);
+		assert_eq!(
+			paths(&differences),
+			vec![vec!["details".into(), "amount".into()], vec!["details".into(), "memo".into()]]
+		);
+		assert_eq!(differences[0].left, &Value::u128(100));
+		assert_eq!(differences[0].right, &Value::u128(200));
+
+		// Each path leads to the differing values:
+		for d in &differences {
+			assert_eq!(left.at(&d.path), Ok(d.left));
+			assert_eq!(right.at(&d.path), Ok(d.right));
+		}
+
+		let mut right = call(100, "hi");
+		right.replace_at(&["dest".into(), 1.into()], Value::u8(3)).expect("path exists");
+		assert_eq!(paths(&left.diff(&right)), vec![vec!["dest".into(), 1.into()]]);
+	}
+
+	#[test]
+	fn shape_mismatches_are_reported_once() {
+		let left = call(100, "hi");
+
+		// A different number of unnamed fields:
+		let mut right = call(200, "hi");
+		right.replace_at(&["dest".into()], Value::unnamed_composite(vec![Value::u8(1)])).expect("path exists");
+		assert_eq!(paths(&left.diff(&right)), vec![vec!["dest".into()], vec!["details".into(), "amount".into()]]);
+
+		// Different field names:
+		let mut right = call(100, "hi");
+		right.replace_at(&["details".into()], Value::named_composite(vec![("other".into(), Value::u8(1))])).unwrap();
+		let differences = left.diff(&right);
+		assert_eq!(paths(&differences), vec![vec!["details".into()]]);
+		assert_eq!(differences[0].left, left.at(&["details".into()]).unwrap());
+
+		// Different variants or kinds of value differ at the top:
+		let right = Value::variant("other".into(), Composite::Named(vec![]));
+		assert_eq!(paths(&left.diff(&right)), vec![vec![]]);
+		assert_eq!(paths(&left.diff(&Value::u8(1))), vec![vec![]]);
+	}
+}
@@ -20,14 +20,30 @@ representations of SCALE encoded data (much like `serde_json::Value` is a runtim
 of JSON data).
 */
 
+mod builder;
+mod convert;
 mod deserialize;
 mod deserializer;
+mod diff;
+mod parse;
+mod path;
 mod serialize;
+mod stringify;
+#[cfg(feature = "chrono")]
+mod timestamp;
 
+use crate::decoder::DecodeValueError;
+use crate::encoder::EncodeError;
+use crate::TypeId;
 use bitvec::{order::Lsb0, vec::BitVec};
+use codec::Encode;
+use scale_info::PortableRegistry;
 use serde::Deserialize;
+use sp_core::U256;
+use std::borrow::Cow;
 use std::convert::From;
 use std::fmt::Debug;
+use std::ops::{Index, Range};
 
 /// [`Value`] holds a representation of some value that has been decoded, as well as some arbitrary context.
 ///
@@ -63,7 +79,7 @@ impl Value<()> {
 	}
 	/// Create a new variant value without additional context.
 	pub fn variant(name: String, values: Composite<()>) -> Value<()> {
-		Value { value: ValueDef::Variant(Variant { name, values }), context: () }
+		Value { value: ValueDef::Variant(Variant { name, values, index: None }), context: () }
 	}
 	/// Create a new bit sequence value without additional context.
 	pub fn bit_sequence(bitseq: BitSequence) -> Value<()> {
@@ -77,6 +93,11 @@ impl Value<()> {
 	pub fn str(val: String) -> Value<()> {
 		Value { value: ValueDef::Primitive(Primitive::Str(val)), context: () }
 	}
+	/// Create a new string value without additional context from some UTF-8 bytes. See
+	/// [`Primitive::str_from_utf8_lossy`].
+	pub fn str_from_utf8_lossy(bytes: &[u8]) -> Value<()> {
+		Value::primitive(Primitive::str_from_utf8_lossy(bytes))
+	}
 
 	value_prim_method!(bool Bool);
 	value_prim_method!(char Char);
@@ -90,6 +111,8 @@ impl Value<()> {
 	value_prim_method!(i32 I32);
 	value_prim_method!(i64 I64);
 	value_prim_method!(i128 I128);
+	value_prim_method!(f32 F32);
+	value_prim_method!(f64 F64);
 }
 
 impl<T> Value<T> {
@@ -108,6 +131,616 @@ impl<T> Value<T> {
 	{
 		Value { context: f(self.context), value: self.value.map_context(f) }
 	}
+	/// Transform every [`Primitive::Str`] found in this value using the function provided, leaving
+	/// everything else untouched. If `include_field_names` is true, the names of any named composite
+	/// fields are transformed too. Variant names are never transformed.
+	pub fn map_strings<F>(mut self, mut f: F, include_field_names: bool) -> Value<T>
+	where
+		F: FnMut(String) -> String,
+	{
+		map_strings_in_place(&mut self, &mut f, include_field_names);
+		self
+	}
+	/// A short, human readable name for the shape of this value, like `"named composite"`, `"variant"`
+	/// or `"u32"`. Primitive values are described using [`Primitive::type_name`]. This is useful when
+	/// constructing error messages.
+	pub fn type_name(&self) -> &'static str {
+		match &self.value {
+			ValueDef::Composite(Composite::Named(_)) => "named composite",
+			ValueDef::Composite(Composite::Unnamed(_)) => "unnamed composite",
+			ValueDef::Variant(_) => "variant",
+			ValueDef::BitSequence(_) => "bit sequence",
+			ValueDef::Primitive(prim) => prim.type_name(),
+		}
+	}
+	/// Is this value "unit-like"? That is, is it an empty composite (named or unnamed), or a variant
+	/// with no values? This lines up with the values that can be deserialized into `()`.
+	pub fn is_unit(&self) -> bool {
+		match &self.value {
+			ValueDef::Composite(composite) => composite.is_empty(),
+			ValueDef::Variant(variant) => variant.values.is_empty(),
+			_ => false,
+		}
+	}
+	/// Is this value a [`ValueDef::Composite`]?
+	pub fn is_composite(&self) -> bool {
+		matches!(self.value, ValueDef::Composite(_))
+	}
+	/// Is this value a [`ValueDef::Variant`]?
+	pub fn is_variant(&self) -> bool {
+		matches!(self.value, ValueDef::Variant(_))
+	}
+	/// Is this value a [`ValueDef::BitSequence`]?
+	pub fn is_bit_sequence(&self) -> bool {
+		matches!(self.value, ValueDef::BitSequence(_))
+	}
+	/// Is this value a [`ValueDef::Primitive`]?
+	pub fn is_primitive(&self) -> bool {
+		matches!(self.value, ValueDef::Primitive(_))
+	}
+	/// If this value is a [`ValueDef::Primitive`], return it. Otherwise, hand back this value unchanged.
+	pub fn into_primitive(self) -> Result<Primitive, Value<T>> {
+		match self.value {
+			ValueDef::Primitive(prim) => Ok(prim),
+			value => Err(Value { value, context: self.context }),
+		}
+	}
+	/// If this value is a [`ValueDef::Composite`], return it. Otherwise, hand back this value unchanged.
+	pub fn into_composite(self) -> Result<Composite<T>, Value<T>> {
+		match self.value {
+			ValueDef::Composite(composite) => Ok(composite),
+			value => Err(Value { value, context: self.context }),
+		}
+	}
+	/// If this value is a [`ValueDef::Variant`], return it. Otherwise, hand back this value unchanged.
+	pub fn into_variant(self) -> Result<Variant<T>, Value<T>> {
+		match self.value {
+			ValueDef::Variant(variant) => Ok(variant),
+			value => Err(Value { value, context: self.context }),
+		}
+	}
+	/// If this value is a [`ValueDef::BitSequence`], return it. Otherwise, hand back this value unchanged.
+	pub fn into_bit_sequence(self) -> Result<BitSequence, Value<T>> {
+		match self.value {
+			ValueDef::BitSequence(bits) => Ok(bits),
+			value => Err(Value { value, context: self.context }),
+		}
+	}
+	/// If this value looks like a SCALE `Option` (that is, a variant called `None` with no values, or a
+	/// variant called `Some` with exactly one value), return `Some(None)` or `Some(Some(value))` respectively.
+	/// If the value doesn't look like an `Option`, `None` is returned.
+	pub fn as_option(&self) -> Option<Option<&Value<T>>> {
+		match &self.value {
+			ValueDef::Variant(Variant { name, values, .. }) if name == "None" && values.is_empty() => Some(None),
+			ValueDef::Variant(Variant { name, values, .. }) if name == "Some" && values.len() == 1 => {
+				let inner = match values {
+					Composite::Named(values) => &values[0].1,
+					Composite::Unnamed(values) => &values[0],
+				};
+				Some(Some(inner))
+			}
+			_ => None,
+		}
+	}
+	/// An owned version of [`Value::as_option`]. Returns `Some(None)` or `Some(Some(value))` if this
+	/// value looks like a SCALE `Option`, and `None` otherwise.
+	pub fn into_option(self) -> Option<Option<Value<T>>> {
+		match self.value {
+			ValueDef::Variant(Variant { name, values, .. }) if name == "None" && values.is_empty() => Some(None),
+			ValueDef::Variant(Variant { name, values, .. }) if name == "Some" && values.len() == 1 => {
+				Some(values.into_unnamed().pop())
+			}
+			_ => None,
+		}
+	}
+	/// If this value is an unsigned integer primitive, return it as a [`U256`]. See [`Primitive::as_u256`].
+	pub fn as_u256(&self) -> Option<U256> {
+		match &self.value {
+			ValueDef::Primitive(prim) => prim.as_u256(),
+			_ => None,
+		}
+	}
+	/// If this value is an integer primitive, return its sign and magnitude. See [`Primitive::as_i256`].
+	pub fn as_i256(&self) -> Option<(bool, U256)> {
+		match &self.value {
+			ValueDef::Primitive(prim) => prim.as_i256(),
+			_ => None,
+		}
+	}
+	/// If this value is a numeric primitive, return it as an `f64`. See [`Primitive::as_f64`].
+	pub fn as_f64(&self) -> Option<f64> {
+		match &self.value {
+			ValueDef::Primitive(prim) => prim.as_f64(),
+			_ => None,
+		}
+	}
+	/// If this value is an integer primitive which fits into an `i64`, return it. This is intended for values
+	/// holding a number of milliseconds since the Unix epoch (1970-01-01T00:00:00 UTC), as stored by (for
+	/// example) `pallet_timestamp`, and the result can be handed to something like `chrono`'s
+	/// `Utc.timestamp_millis_opt` to obtain a date and time.
+	///
+	/// When deserializing, integer primitives can also be deserialized into a `chrono::DateTime` directly using
+	/// `#[serde(with = "chrono::serde::ts_milliseconds")]`, or, with the `chrono` feature enabled, into a
+	/// `UtcMillis` wrapper.
+	pub fn as_unix_millis(&self) -> Option<i64> {
+		match &self.value {
+			// Avoid cloning strings just to find that they aren't integers:
+			ValueDef::Primitive(Primitive::Str(_)) => None,
+			ValueDef::Primitive(prim) => i64::try_from(prim.clone()).ok(),
+			_ => None,
+		}
+	}
+	/// If this value is a string, return it. If it is an unnamed composite containing only `U8` values (which is how
+	/// a `Vec<u8>` is decoded), interpret these bytes as UTF-8 and return the resulting string, replacing
+	/// any invalid sequences with `U+FFFD REPLACEMENT CHARACTER`. Otherwise, return `None`. This is useful for
+	/// fields which hold text but are typed as bytes, like those in `pallet-identity`.
+	pub fn as_str_lossy(&self) -> Option<Cow<'_, str>> {
+		match &self.value {
+			ValueDef::Primitive(Primitive::Str(s)) => Some(Cow::Borrowed(s)),
+			ValueDef::Composite(Composite::Unnamed(values)) => {
+				let bytes = values
+					.iter()
+					.map(|val| match val.value {
+						ValueDef::Primitive(Primitive::U8(b)) => Some(b),
+						_ => None,
+					})
+					.collect::<Option<Vec<u8>>>()?;
+				Some(Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()))
+			}
+			_ => None,
+		}
+	}
+	/// Is this value semantically equivalent to another? Unlike `==`, which compares values structurally,
+	/// this ignores the contexts of the values, and compares integer primitives numerically regardless of
+	/// their width or signedness (so `U8(1)` is equivalent to `U64(1)` and `I32(1)`, but not to `I8(-1)`).
+	/// Named and unnamed composites are still considered different, as are variants with different names.
+	pub fn equivalent<U>(&self, other: &Value<U>) -> bool {
+		match (&self.value, &other.value) {
+			(ValueDef::Composite(a), ValueDef::Composite(b)) => composites_equivalent(a, b),
+			(ValueDef::Variant(a), ValueDef::Variant(b)) => {
+				a.name == b.name && composites_equivalent(&a.values, &b.values)
+			}
+			(ValueDef::BitSequence(a), ValueDef::BitSequence(b)) => a == b,
+			(ValueDef::Primitive(a), ValueDef::Primitive(b)) => match (integer_bytes(a), integer_bytes(b)) {
+				(Some(a), Some(b)) => a == b,
+				_ => a == b,
+			},
+			_ => false,
+		}
+	}
+	/// Do this value and another have the same structure, regardless of the data they hold? Composites must be
+	/// of the same kind, with the same number of values (and for named composites, the same field names in the
+	/// same order), and variants must have the same names. Primitives must be of the same type (so `U8(1)` has
+	/// the same shape as `U8(2)`, but not as `U16(1)`), and all bit sequences have the same shape. Contexts are
+	/// ignored.
+	pub fn same_shape<U>(&self, other: &Value<U>) -> bool {
+		match (&self.value, &other.value) {
+			(ValueDef::Composite(a), ValueDef::Composite(b)) => composites_same_shape(a, b),
+			(ValueDef::Variant(a), ValueDef::Variant(b)) => {
+				a.name == b.name && composites_same_shape(&a.values, &b.values)
+			}
+			(ValueDef::BitSequence(_), ValueDef::BitSequence(_)) => true,
+			(ValueDef::Primitive(a), ValueDef::Primitive(b)) => std::mem::discriminant(a) == std::mem::discriminant(b),
+			_ => false,
+		}
+	}
+	/// If this value looks like a SCALE encoded `Result` (that is, it's an `Ok` or `Err` variant with exactly
+	/// one value), return `Some(Ok(inner))` or `Some(Err(inner))` respectively. Return `None` if this value is
+	/// not shaped like a `Result`. This mirrors [`Value::as_option`].
+	pub fn as_result(&self) -> Option<Result<&Value<T>, &Value<T>>> {
+		let variant = match &self.value {
+			ValueDef::Variant(variant) => variant,
+			_ => return None,
+		};
+		let inner = match &variant.values {
+			Composite::Named(values) if values.len() == 1 => &values[0].1,
+			Composite::Unnamed(values) if values.len() == 1 => &values[0],
+			_ => return None,
+		};
+		match &*variant.name {
+			"Ok" => Some(Ok(inner)),
+			"Err" => Some(Err(inner)),
+			_ => None,
+		}
+	}
+	/// Repeatedly unwrap `Some` and `Ok` variants that contain exactly one value (named or unnamed), handing
+	/// back the innermost value that is not one of these. Anything else (including `None` and `Err` variants)
+	/// stops the unwrapping, and is handed back as is. For instance, `Some(Ok(Some(42)))` becomes `42`, and
+	/// `Some(Err(1))` becomes `Err(1)`. As with [`Value::as_option`], variants are recognised by name only.
+	pub fn unwrap_options_and_results(self) -> Value<T> {
+		let mut value = self;
+		loop {
+			match value.value {
+				ValueDef::Variant(variant)
+					if (variant.name == "Some" || variant.name == "Ok") && variant.values.len() == 1 =>
+				{
+					value = variant.values.into_unnamed().remove(0);
+				}
+				other => return Value { value: other, context: value.context },
+			}
+		}
+	}
+	/// Replace any named composite with exactly one field with the value of that field, throughout this value.
+	/// So, `{ inner: { value: 1 } }` becomes `1`. Unnamed composites, and named composites with any other number
+	/// of fields, are left as they are (though the values within them are still flattened).
+	///
+	/// The context of the innermost value is kept; for decoded values, this means that the [`TypeId`] in the
+	/// context will continue to describe the value it's attached to.
+	pub fn flatten_single_field(self) -> Value<T> {
+		let mut value = self;
+		while let ValueDef::Composite(Composite::Named(fields)) = &mut value.value {
+			if fields.len() != 1 {
+				break;
+			}
+			value = fields.pop().expect("one field; qed").1;
+		}
+
+		let flatten_composite = |composite: Composite<T>| match composite {
+			Composite::Named(vals) => {
+				Composite::Named(vals.into_iter().map(|(name, v)| (name, v.flatten_single_field())).collect())
+			}
+			Composite::Unnamed(vals) => Composite::Unnamed(vals.into_iter().map(Value::flatten_single_field).collect()),
+		};
+		let def = match value.value {
+			ValueDef::Composite(composite) => ValueDef::Composite(flatten_composite(composite)),
+			ValueDef::Variant(variant) => {
+				ValueDef::Variant(Variant { values: flatten_composite(variant.values), ..variant })
+			}
+			other => other,
+		};
+		Value { value: def, context: value.context }
+	}
+	/// If this value is a named composite with a field called `tag_key` whose value is a string, convert it
+	/// into a variant whose name is that string, and whose values are the remaining fields. This allows values
+	/// which represent enums in the form `{ "type": "Foo", ...fields }` to be deserialized into enums. Any other
+	/// value is handed back unchanged.
+	pub fn named_to_variant(self, tag_key: &str) -> Value<T> {
+		let mut fields = match self.value {
+			ValueDef::Composite(Composite::Named(fields)) => fields,
+			value => return Value { value, context: self.context },
+		};
+
+		let tag_idx = fields
+			.iter()
+			.position(|(name, value)| name == tag_key && matches!(value.value, ValueDef::Primitive(Primitive::Str(_))));
+		let name = match tag_idx.map(|idx| fields.remove(idx).1.value) {
+			Some(ValueDef::Primitive(Primitive::Str(name))) => name,
+			_ => return Value { value: ValueDef::Composite(Composite::Named(fields)), context: self.context },
+		};
+
+		Value {
+			value: ValueDef::Variant(Variant { name, values: Composite::Named(fields), index: None }),
+			context: self.context,
+		}
+	}
+	/// Deep-merge `other` into this value. If both values are named composites, the result is a named
+	/// composite containing the fields of this value followed by any fields only found in `other`. Where a
+	/// field is present in both, the two values are merged in the same way, so nested named composites are
+	/// merged field by field. In every other case (including named composites nested in variants or unnamed
+	/// composites), `other` is returned as is.
+	///
+	/// When two named composites are merged, the context of this value is kept.
+	pub fn merge(self, other: Value<T>) -> Value<T> {
+		let (fields, mut other_fields) = match (self.value, other.value) {
+			(ValueDef::Composite(Composite::Named(a)), ValueDef::Composite(Composite::Named(b))) => (a, b),
+			(_, value) => return Value { value, context: other.context },
+		};
+
+		let mut fields: Vec<_> = fields
+			.into_iter()
+			.map(|(name, value)| match other_fields.iter().position(|(n, _)| *n == name) {
+				Some(idx) => {
+					let (_, other_value) = other_fields.remove(idx);
+					(name, value.merge(other_value))
+				}
+				None => (name, value),
+			})
+			.collect();
+		fields.extend(other_fields);
+
+		Value { value: ValueDef::Composite(Composite::Named(fields)), context: self.context }
+	}
+	/// Sort the fields of any named composites within this value by name. See [`Composite::sort_named_fields`].
+	pub fn sort_named_fields(&mut self) {
+		match &mut self.value {
+			ValueDef::Composite(composite) => composite.sort_named_fields(),
+			ValueDef::Variant(variant) => variant.values.sort_named_fields(),
+			ValueDef::BitSequence(_) | ValueDef::Primitive(_) => {}
+		}
+	}
+	/// Return a canonical form of this value, in which the fields of every named composite (including those
+	/// of variants) are sorted by name. Unnamed composites keep their order. Two values which only differ in
+	/// the order of their named fields have the same canonical form, which makes this useful before comparing,
+	/// hashing or signing some representation of a value.
+	pub fn into_canonical(mut self) -> Value<T> {
+		self.sort_named_fields();
+		self
+	}
+	/// The maximum nesting depth of this value. A value with no values nested inside it (for instance
+	/// a primitive, or an empty composite) has a depth of 1. This is computed without recursing, so it's
+	/// safe to call on arbitrarily deeply nested values.
+	pub fn depth(&self) -> usize {
+		let mut max_depth = 0;
+		let mut stack = vec![(self, 1)];
+		while let Some((value, depth)) = stack.pop() {
+			max_depth = max_depth.max(depth);
+			stack.extend(value.child_values().map(|v| (v, depth + 1)));
+		}
+		max_depth
+	}
+	/// The total number of values that make up this one, including itself. As with [`Value::depth`],
+	/// this is computed without recursing.
+	pub fn node_count(&self) -> usize {
+		let mut count = 0;
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			count += 1;
+			stack.extend(value.child_values());
+		}
+		count
+	}
+	/// An estimate of the number of bytes of memory that this value takes up, which is useful when deciding how
+	/// many values to keep in a cache. This is the size of the value itself (including its context) plus the
+	/// heap allocations it owns: the capacity of each string and composite, and the bytes of each bit sequence.
+	/// Any heap allocations owned by the context, and any overhead from the allocator itself, are not counted.
+	/// As with [`Value::depth`], this is computed without recursing.
+	pub fn approx_memory_size(&self) -> usize {
+		let mut size = std::mem::size_of::<Value<T>>();
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			let composite = match &value.value {
+				ValueDef::Composite(composite) => composite,
+				ValueDef::Variant(variant) => {
+					size += variant.name.capacity();
+					&variant.values
+				}
+				ValueDef::BitSequence(bits) => {
+					size += (bits.capacity() + 7) / 8;
+					continue;
+				}
+				ValueDef::Primitive(Primitive::Str(s)) => {
+					size += s.capacity();
+					continue;
+				}
+				ValueDef::Primitive(_) => continue,
+			};
+			match composite {
+				Composite::Named(values) => {
+					size += values.capacity() * std::mem::size_of::<(String, Value<T>)>();
+					size += values.iter().map(|(name, _)| name.capacity()).sum::<usize>();
+				}
+				Composite::Unnamed(values) => size += values.capacity() * std::mem::size_of::<Value<T>>(),
+			}
+			stack.extend(value.child_values());
+		}
+		size
+	}
+	/// Call the function provided on every [`Primitive`] found within this value, in order, allowing each to
+	/// be modified in place (for instance, to redact them). As with [`Value::depth`], this is done without
+	/// recursing, and the only allocation is a stack of references to the values that are yet to be visited.
+	pub fn walk_mut<F: FnMut(&mut Primitive)>(&mut self, mut f: F) {
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			let composite = match &mut value.value {
+				ValueDef::Primitive(prim) => {
+					f(prim);
+					continue;
+				}
+				ValueDef::Composite(composite) => composite,
+				ValueDef::Variant(variant) => &mut variant.values,
+				ValueDef::BitSequence(_) => continue,
+			};
+			// Push children in reverse so that they are popped (and so visited) in order:
+			match composite {
+				Composite::Named(values) => stack.extend(values.iter_mut().rev().map(|(_, v)| v)),
+				Composite::Unnamed(values) => stack.extend(values.iter_mut().rev()),
+			}
+		}
+	}
+	/// Replace every [`Primitive::Str`] found within this value with the mask given, leaving everything else
+	/// (including field and variant names) untouched. This is useful before logging values which may contain
+	/// personal information, like identity fields or remarks.
+	pub fn redact_strings(self, mask: &str) -> Value<T> {
+		self.redact_where(mask, |prim| matches!(prim, Primitive::Str(_)))
+	}
+	/// Replace every [`Primitive`] found within this value for which the predicate returns true with a
+	/// [`Primitive::Str`] containing the mask given. See [`Value::redact_strings`].
+	pub fn redact_where<F: FnMut(&Primitive) -> bool>(mut self, mask: &str, mut f: F) -> Value<T> {
+		self.walk_mut(|prim| {
+			if f(prim) {
+				*prim = Primitive::Str(mask.to_string());
+			}
+		});
+		self
+	}
+	/// An iterator over every [`Primitive`] found within this value, in order (that is, a pre-order traversal
+	/// of the value). Bit sequences are not primitives and so are skipped over. As with [`Value::depth`], this
+	/// doesn't recurse, and so it's safe to use on deeply nested values.
+	pub fn primitives(&self) -> impl Iterator<Item = &Primitive> {
+		let mut stack = vec![self];
+		std::iter::from_fn(move || {
+			while let Some(value) = stack.pop() {
+				if let ValueDef::Primitive(prim) = &value.value {
+					return Some(prim);
+				}
+				// Push children in reverse so that they are popped (and so visited) in order:
+				stack.extend(value.child_values().rev());
+			}
+			None
+		})
+	}
+	/// Combine every value found within this one (including this value itself, and composites and variants
+	/// as well as the values nested inside them) into a single result, by calling the function provided
+	/// with the result so far and each value in turn. Values are visited in pre-order; a composite or variant
+	/// is visited before the values inside it. As with [`Value::depth`], this doesn't recurse.
+	pub fn fold<A, F: FnMut(A, &ValueDef<T>) -> A>(&self, init: A, mut f: F) -> A {
+		let mut acc = init;
+		let mut stack = vec![self];
+		while let Some(value) = stack.pop() {
+			acc = f(acc, &value.value);
+			// Push children in reverse so that they are popped (and so visited) in order:
+			stack.extend(value.child_values().rev());
+		}
+		acc
+	}
+	// An iterator over the values nested directly inside this one.
+	fn child_values(&self) -> impl DoubleEndedIterator<Item = &Value<T>> {
+		let composite = match &self.value {
+			ValueDef::Composite(composite) => Some(composite),
+			ValueDef::Variant(variant) => Some(&variant.values),
+			ValueDef::BitSequence(_) | ValueDef::Primitive(_) => None,
+		};
+		let (named, unnamed) = match composite {
+			Some(Composite::Named(values)) => (Some(values.iter().map(|(_, v)| v)), None),
+			Some(Composite::Unnamed(values)) => (None, Some(values.iter())),
+			None => (None, None),
+		};
+		named.into_iter().flatten().chain(unnamed.into_iter().flatten())
+	}
+	/// SCALE encode this value as the type with the given ID in the registry provided, appending
+	/// the resulting bytes to `out`. This is the inverse of decoding a value, and so a decoded value
+	/// can be re-encoded (perhaps after being edited) using the [`TypeId`] in its context.
+	pub fn scale_encode<Id: Into<TypeId>>(
+		&self,
+		ty: Id,
+		types: &PortableRegistry,
+		out: &mut Vec<u8>,
+	) -> Result<(), EncodeError> {
+		crate::encoder::encode_value::encode_value_by_id(self, ty, types, out)
+	}
+	/// Return the exact number of bytes that [`Value::scale_encode`] would write given the same type,
+	/// without allocating the output. Useful for pre-sizing the output buffer.
+	pub fn scale_size_hint<Id: Into<TypeId>>(&self, ty: Id, types: &PortableRegistry) -> Result<usize, EncodeError> {
+		crate::encoder::encode_value::scale_size_hint(self, ty, types)
+	}
+	/// SCALE encode this value as the type with the given ID (see [`Value::scale_encode`]), and return
+	/// the resulting bytes as a `0x` prefixed, lowercase hex string (as is commonly used in RPC calls).
+	pub fn encode_to_hex<Id: Into<TypeId>>(&self, ty: Id, types: &PortableRegistry) -> Result<String, EncodeError> {
+		let mut bytes = Vec::new();
+		self.scale_encode(ty, types, &mut bytes)?;
+		Ok(format!("0x{}", hex::encode(bytes)))
+	}
+	/// SCALE encode this value as the type with the given ID (see [`Value::scale_encode`]), and then
+	/// return the blake2-256 hash of the encoded bytes. This is how Substrate computes the hash of an
+	/// extrinsic, for instance, given an extrinsic encoded as a `Vec<u8>`.
+	#[cfg(feature = "hashing")]
+	pub fn encode_and_hash<Id: Into<TypeId>>(&self, ty: Id, types: &PortableRegistry) -> Result<[u8; 32], EncodeError> {
+		let mut bytes = Vec::new();
+		self.scale_encode(ty, types, &mut bytes)?;
+		Ok(sp_core::blake2_256(&bytes))
+	}
+}
+
+impl Value<TypeId> {
+	/// Decode a value of the type with the given ID from a hex string (optionally prefixed with `0x`).
+	/// This is the counterpart to [`Value::encode_to_hex`]. An error is returned if the string is not
+	/// valid hex, or if the bytes do not decode into exactly one value of the given type.
+	pub fn decode_from_hex<Id: Into<TypeId>>(
+		hex: &str,
+		ty: Id,
+		types: &PortableRegistry,
+	) -> Result<Value<TypeId>, DecodeValueError> {
+		let bytes = hex::decode(hex.strip_prefix("0x").unwrap_or(hex))?;
+		let data = &mut &*bytes;
+		let value = crate::decoder::decode_value::decode_value_by_id(data, ty, types)?;
+		if !data.is_empty() {
+			return Err(DecodeValueError::ExcessBytes(data.len()));
+		}
+		Ok(value)
+	}
+}
+
+impl Value<Range<usize>> {
+	/// Given the bytes that this value was decoded from (see [`crate::decoder::decode_value_spanned`]),
+	/// return the exact bytes that this value was decoded from. This returns `None` if the range that
+	/// the value was decoded from is not within the bytes provided.
+	pub fn raw_bytes<'a>(&self, source: &'a [u8]) -> Option<&'a [u8]> {
+		source.get(self.context.clone())
+	}
+}
+
+fn composites_equivalent<T, U>(a: &Composite<T>, b: &Composite<U>) -> bool {
+	match (a, b) {
+		(Composite::Named(a), Composite::Named(b)) => {
+			a.len() == b.len() && a.iter().zip(b).all(|((an, av), (bn, bv))| an == bn && av.equivalent(bv))
+		}
+		(Composite::Unnamed(a), Composite::Unnamed(b)) => {
+			a.len() == b.len() && a.iter().zip(b).all(|(av, bv)| av.equivalent(bv))
+		}
+		_ => false,
+	}
+}
+
+fn composites_same_shape<T, U>(a: &Composite<T>, b: &Composite<U>) -> bool {
+	match (a, b) {
+		(Composite::Named(a), Composite::Named(b)) => {
+			a.len() == b.len() && a.iter().zip(b).all(|((an, av), (bn, bv))| an == bn && av.same_shape(bv))
+		}
+		(Composite::Unnamed(a), Composite::Unnamed(b)) => {
+			a.len() == b.len() && a.iter().zip(b).all(|(av, bv)| av.same_shape(bv))
+		}
+		_ => false,
+	}
+}
+
+// If the primitive is an integer, return whether it is negative along with its
+// value sign extended into 32 little endian bytes, so that integers of different
+// widths can be compared.
+fn integer_bytes(prim: &Primitive) -> Option<(bool, [u8; 32])> {
+	fn extend(n: i128) -> (bool, [u8; 32]) {
+		let mut bytes = if n < 0 { [0xFF; 32] } else { [0; 32] };
+		bytes[..16].copy_from_slice(&n.to_le_bytes());
+		(n < 0, bytes)
+	}
+	let res = match *prim {
+		Primitive::U8(v) => extend(v.into()),
+		Primitive::U16(v) => extend(v.into()),
+		Primitive::U32(v) => extend(v.into()),
+		Primitive::U64(v) => extend(v.into()),
+		Primitive::U128(v) => {
+			let mut bytes = [0; 32];
+			bytes[..16].copy_from_slice(&v.to_le_bytes());
+			(false, bytes)
+		}
+		Primitive::U256(v) => (false, v),
+		Primitive::I8(v) => extend(v.into()),
+		Primitive::I16(v) => extend(v.into()),
+		Primitive::I32(v) => extend(v.into()),
+		Primitive::I64(v) => extend(v.into()),
+		Primitive::I128(v) => extend(v),
+		Primitive::I256(v) => (v[31] & 0b1000_0000 != 0, v),
+		Primitive::Bool(_) | Primitive::Char(_) | Primitive::Str(_) | Primitive::F32(_) | Primitive::F64(_) => {
+			return None
+		}
+	};
+	Some(res)
+}
+
+fn map_strings_in_place<T>(value: &mut Value<T>, f: &mut dyn FnMut(String) -> String, include_field_names: bool) {
+	let composite = match &mut value.value {
+		ValueDef::Primitive(Primitive::Str(s)) => {
+			*s = f(std::mem::take(s));
+			return;
+		}
+		ValueDef::Composite(composite) => composite,
+		ValueDef::Variant(variant) => &mut variant.values,
+		ValueDef::Primitive(_) | ValueDef::BitSequence(_) => return,
+	};
+	match composite {
+		Composite::Named(vals) => {
+			for (name, val) in vals {
+				if include_field_names {
+					*name = f(std::mem::take(name));
+				}
+				map_strings_in_place(val, f, include_field_names);
+			}
+		}
+		Composite::Unnamed(vals) => {
+			for val in vals {
+				map_strings_in_place(val, f, include_field_names);
+			}
+		}
+	}
 }
 
 /// The underlying shape of a given value.
@@ -177,6 +810,145 @@ impl<T> Composite<T> {
 		}
 	}
 
+	/// Is this a [`Composite::Named`] composite?
+	pub fn is_named(&self) -> bool {
+		matches!(self, Composite::Named(_))
+	}
+
+	/// Is this a [`Composite::Unnamed`] composite?
+	pub fn is_unnamed(&self) -> bool {
+		matches!(self, Composite::Unnamed(_))
+	}
+
+	/// Return the value of the field with the given name, or `None` if there is no such field. Unnamed
+	/// composites have no field names, and so this always returns `None` for them.
+	pub fn get(&self, name: &str) -> Option<&Value<T>> {
+		match self {
+			Composite::Named(values) => values.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+			Composite::Unnamed(_) => None,
+		}
+	}
+
+	/// Return the value at the given position (ignoring any field names), or `None` if it's out of range.
+	pub fn get_index(&self, idx: usize) -> Option<&Value<T>> {
+		match self {
+			Composite::Named(values) => values.get(idx).map(|(_, v)| v),
+			Composite::Unnamed(values) => values.get(idx),
+		}
+	}
+
+	/// Return the values in this composite, discarding any field names.
+	pub fn into_unnamed(self) -> Vec<Value<T>> {
+		match self {
+			Composite::Named(values) => values.into_iter().map(|(_, v)| v).collect(),
+			Composite::Unnamed(values) => values,
+		}
+	}
+
+	/// Return the values in this composite alongside their field names. Unnamed
+	/// composites are given positional names (`"0"`, `"1"` and so on). Calling this
+	/// on a [`Composite::Named`] is a no-op; existing names are preserved as they are.
+	pub fn into_named(self) -> Vec<(String, Value<T>)> {
+		match self {
+			Composite::Named(values) => values,
+			Composite::Unnamed(values) => values.into_iter().enumerate().map(|(idx, v)| (idx.to_string(), v)).collect(),
+		}
+	}
+
+	/// Keep only the values for which the predicate returns true. The predicate is given the name of
+	/// each field, or `None` for the values of unnamed composites (whose positions will shift down
+	/// as values are removed).
+	pub fn retain<F: FnMut(Option<&str>, &Value<T>) -> bool>(&mut self, mut f: F) {
+		match self {
+			Composite::Named(values) => values.retain(|(n, v)| f(Some(n), v)),
+			Composite::Unnamed(values) => values.retain(|v| f(None, v)),
+		}
+	}
+
+	/// Append a value to an unnamed composite. If this is a non-empty named composite, the value is handed
+	/// back in the error. An empty named composite is turned into an unnamed one before the value is added.
+	pub fn push(&mut self, value: Value<T>) -> Result<(), Value<T>> {
+		if let Composite::Named(values) = self {
+			if !values.is_empty() {
+				return Err(value);
+			}
+			*self = Composite::Unnamed(Vec::new());
+		}
+		match self {
+			Composite::Unnamed(values) => values.push(value),
+			Composite::Named(_) => unreachable!("named composites were converted above"),
+		}
+		Ok(())
+	}
+
+	/// Append a named field to a named composite, without checking whether a field with the same name
+	/// already exists (see [`Composite::insert`] for that). If this is a non-empty unnamed composite, the
+	/// value is handed back in the error. An empty unnamed composite is turned into a named one before the
+	/// field is added.
+	pub fn push_field(&mut self, name: impl Into<String>, value: Value<T>) -> Result<(), Value<T>> {
+		match self.named_values_mut() {
+			Some(values) => {
+				values.push((name.into(), value));
+				Ok(())
+			}
+			None => Err(value),
+		}
+	}
+
+	/// Set the value of a named field, replacing and returning the existing value if there is a field with
+	/// this name already, and otherwise appending a new field. This behaves like [`Composite::push_field`]
+	/// when called on unnamed composites.
+	pub fn insert(&mut self, name: impl Into<String>, value: Value<T>) -> Result<Option<Value<T>>, Value<T>> {
+		let values = match self.named_values_mut() {
+			Some(values) => values,
+			None => return Err(value),
+		};
+		let name = name.into();
+		match values.iter_mut().find(|(n, _)| *n == name) {
+			Some((_, existing)) => Ok(Some(std::mem::replace(existing, value))),
+			None => {
+				values.push((name, value));
+				Ok(None)
+			}
+		}
+	}
+
+	// The named values of this composite, converting empty unnamed composites into named ones first.
+	fn named_values_mut(&mut self) -> Option<&mut Vec<(String, Value<T>)>> {
+		if let Composite::Unnamed(values) = self {
+			if !values.is_empty() {
+				return None;
+			}
+			*self = Composite::Named(Vec::new());
+		}
+		match self {
+			Composite::Named(values) => Some(values),
+			Composite::Unnamed(_) => None,
+		}
+	}
+
+	/// Sort the fields of any named composites by name, both in this composite and in any values nested
+	/// within it. Unnamed composites keep their order. This is useful for comparing values whose named
+	/// fields may have been provided in different orders.
+	pub fn sort_named_fields(&mut self) {
+		match self {
+			Composite::Named(values) => {
+				values.sort_by(|(a, _), (b, _)| a.cmp(b));
+				values.iter_mut().for_each(|(_, v)| v.sort_named_fields());
+			}
+			Composite::Unnamed(values) => values.iter_mut().for_each(|v| v.sort_named_fields()),
+		}
+	}
+	/// A non-mutating version of [`Composite::sort_named_fields`], which returns a sorted copy of this composite.
+	pub fn sorted(&self) -> Composite<T>
+	where
+		T: Clone,
+	{
+		let mut composite = self.clone();
+		composite.sort_named_fields();
+		composite
+	}
+
 	/// Map the context to some different type.
 	pub fn map_context<F, U>(self, f: F) -> Composite<U>
 	where
@@ -220,6 +992,25 @@ impl<T: Debug> Debug for Composite<T> {
 	}
 }
 
+/// Access the value of the named field given. This panics if there is no field with that name
+/// (which is always the case for [`Composite::Unnamed`]); see [`Composite::get`] for a fallible alternative.
+impl<T> Index<&str> for Composite<T> {
+	type Output = Value<T>;
+	fn index(&self, name: &str) -> &Self::Output {
+		self.get(name).unwrap_or_else(|| panic!("No field called {:?} in this composite", name))
+	}
+}
+
+/// Access the value at the position given (ignoring any field names). This panics if the index is out
+/// of range; see [`Composite::get_index`] for a fallible alternative.
+impl<T> Index<usize> for Composite<T> {
+	type Output = Value<T>;
+	fn index(&self, idx: usize) -> &Self::Output {
+		self.get_index(idx)
+			.unwrap_or_else(|| panic!("Index {} is out of range for a composite of length {}", idx, self.len()))
+	}
+}
+
 impl<T> From<Composite<T>> for ValueDef<T> {
 	fn from(val: Composite<T>) -> Self {
 		ValueDef::Composite(val)
@@ -234,6 +1025,10 @@ pub struct Variant<T> {
 	pub name: String,
 	/// Values for each of the named or unnamed fields associated with this variant.
 	pub values: Composite<T>,
+	/// The position of the variant within its enum, if known. This is populated when decoding SCALE
+	/// bytes, and is `None` otherwise. Note that this is not necessarily the index that the variant is
+	/// encoded with, which can differ if `#[codec(index = N)]` is used.
+	pub index: Option<u32>,
 }
 
 impl<T> Variant<T> {
@@ -242,13 +1037,16 @@ impl<T> Variant<T> {
 	where
 		F: Clone + FnMut(T) -> U,
 	{
-		Variant { name: self.name, values: self.values.map_context(f) }
+		Variant { name: self.name, values: self.values.map_context(f), index: self.index }
 	}
 }
 
 impl<T: Debug> Debug for Variant<T> {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		f.write_str(&self.name)?;
+		if let Some(index) = self.index {
+			write!(f, "#{}", index)?;
+		}
 		f.write_str(" ")?;
 		Debug::fmt(&self.values, f)
 	}
@@ -261,6 +1059,10 @@ impl<T> From<Variant<T>> for ValueDef<T> {
 }
 
 /// A "primitive" value (this includes strings).
+///
+/// SCALE has no floating point types, so the `F32` and `F64` variants never come from decoding, but
+/// they allow floats from other formats to round trip through a [`Value`]. Equality for them follows
+/// IEEE 754 (as with the float types themselves), so a value containing `NaN` is not equal to itself.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Primitive {
 	Bool(bool),
@@ -278,6 +1080,148 @@ pub enum Primitive {
 	I64(i64),
 	I128(i128),
 	I256([u8; 32]),
+	F32(f32),
+	F64(f64),
+}
+
+impl Primitive {
+	/// Create a string primitive from some UTF-8 bytes, replacing any invalid
+	/// sequences with `U+FFFD REPLACEMENT CHARACTER`.
+	pub fn str_from_utf8_lossy(bytes: &[u8]) -> Primitive {
+		Primitive::Str(String::from_utf8_lossy(bytes).into_owned())
+	}
+	/// SCALE encode this primitive on its own, based only on its own type. Integers are encoded as little
+	/// endian bytes of their native width (32 bytes for `U256` and `I256`), bools as a single byte, chars as
+	/// a `u32`, and strings as a compact encoded length followed by their UTF-8 bytes. SCALE has no floats,
+	/// so these are given as the little endian bytes of their IEEE 754 representation.
+	///
+	/// Compact encoding is never used for integers here; to encode a value as some specific type (compact
+	/// or otherwise), use [`Value::scale_encode`] instead.
+	pub fn to_scale_bytes(&self) -> Vec<u8> {
+		match self {
+			Primitive::Bool(v) => v.encode(),
+			Primitive::Char(v) => (*v as u32).encode(),
+			Primitive::Str(v) => v.encode(),
+			Primitive::U8(v) => v.encode(),
+			Primitive::U16(v) => v.encode(),
+			Primitive::U32(v) => v.encode(),
+			Primitive::U64(v) => v.encode(),
+			Primitive::U128(v) => v.encode(),
+			Primitive::U256(v) => v.to_vec(),
+			Primitive::I8(v) => v.encode(),
+			Primitive::I16(v) => v.encode(),
+			Primitive::I32(v) => v.encode(),
+			Primitive::I64(v) => v.encode(),
+			Primitive::I128(v) => v.encode(),
+			Primitive::I256(v) => v.to_vec(),
+			Primitive::F32(v) => v.to_le_bytes().to_vec(),
+			Primitive::F64(v) => v.to_le_bytes().to_vec(),
+		}
+	}
+	/// A short, human readable name for the type of this primitive, like `"u32"` or `"string"`.
+	pub fn type_name(&self) -> &'static str {
+		match self {
+			Primitive::Bool(_) => "bool",
+			Primitive::Char(_) => "char",
+			Primitive::Str(_) => "string",
+			Primitive::U8(_) => "u8",
+			Primitive::U16(_) => "u16",
+			Primitive::U32(_) => "u32",
+			Primitive::U64(_) => "u64",
+			Primitive::U128(_) => "u128",
+			Primitive::U256(_) => "u256",
+			Primitive::I8(_) => "i8",
+			Primitive::I16(_) => "i16",
+			Primitive::I32(_) => "i32",
+			Primitive::I64(_) => "i64",
+			Primitive::I128(_) => "i128",
+			Primitive::I256(_) => "i256",
+			Primitive::F32(_) => "f32",
+			Primitive::F64(_) => "f64",
+		}
+	}
+	/// Return the value of this primitive as an `f64`, if it is a number (integer or float). `None` is returned
+	/// for strings, chars and bools.
+	///
+	/// Note that this is a lossy conversion; integers above 2^53 cannot all be represented exactly
+	/// by an `f64`, and so will be rounded, and this is especially true for the 256 bit values (which
+	/// are interpreted as little endian, and in the case of `I256`, two's complement, integers).
+	pub fn as_f64(&self) -> Option<f64> {
+		let n = match *self {
+			Primitive::U8(v) => v as f64,
+			Primitive::U16(v) => v as f64,
+			Primitive::U32(v) => v as f64,
+			Primitive::U64(v) => v as f64,
+			Primitive::U128(v) => v as f64,
+			Primitive::U256(v) => u256_to_f64(&v),
+			Primitive::I8(v) => v as f64,
+			Primitive::I16(v) => v as f64,
+			Primitive::I32(v) => v as f64,
+			Primitive::I64(v) => v as f64,
+			Primitive::I128(v) => v as f64,
+			Primitive::I256(v) => {
+				// If the most significant bit is set, the number is negative, and
+				// its magnitude is given by inverting the bits and adding 1:
+				if v[31] & 0b1000_0000 != 0 {
+					let inverted = v.map(|b| !b);
+					-(u256_to_f64(&inverted) + 1.0)
+				} else {
+					u256_to_f64(&v)
+				}
+			}
+			Primitive::F32(v) => v as f64,
+			Primitive::F64(v) => v,
+			Primitive::Bool(_) | Primitive::Char(_) | Primitive::Str(_) => return None,
+		};
+		Some(n)
+	}
+	/// Return the value of this primitive as a [`U256`], if it is an unsigned integer. Narrower integers are
+	/// widened, and the bytes of a `U256` primitive are interpreted as a little endian integer. Unlike
+	/// [`Primitive::as_f64`], this is lossless, and so is suitable for doing arithmetic on balances.
+	///
+	/// Signed integers aren't converted, since there is no corresponding signed 256 bit integer type; see
+	/// [`Primitive::as_i256`] for those.
+	pub fn as_u256(&self) -> Option<U256> {
+		let n = match *self {
+			Primitive::U8(v) => v.into(),
+			Primitive::U16(v) => v.into(),
+			Primitive::U32(v) => v.into(),
+			Primitive::U64(v) => v.into(),
+			Primitive::U128(v) => v.into(),
+			Primitive::U256(v) => U256::from_little_endian(&v),
+			_ => return None,
+		};
+		Some(n)
+	}
+	/// Return the value of this primitive as a sign and a [`U256`] magnitude, if it is an integer. The sign is
+	/// `true` if the number is negative. The bytes of an `I256` primitive are interpreted as a little endian
+	/// two's complement integer. Every signed or unsigned integer primitive can be represented losslessly this way.
+	pub fn as_i256(&self) -> Option<(bool, U256)> {
+		let n = match *self {
+			Primitive::I8(v) => (v < 0, v.unsigned_abs().into()),
+			Primitive::I16(v) => (v < 0, v.unsigned_abs().into()),
+			Primitive::I32(v) => (v < 0, v.unsigned_abs().into()),
+			Primitive::I64(v) => (v < 0, v.unsigned_abs().into()),
+			Primitive::I128(v) => (v < 0, v.unsigned_abs().into()),
+			Primitive::I256(v) => {
+				let n = U256::from_little_endian(&v);
+				// If the most significant bit is set, the number is negative, and
+				// its magnitude is given by inverting the bits and adding 1:
+				if v[31] & 0b1000_0000 != 0 {
+					(true, (!n).overflowing_add(U256::one()).0)
+				} else {
+					(false, n)
+				}
+			}
+			_ => return Some((false, self.as_u256()?)),
+		};
+		Some(n)
+	}
+}
+
+// Interpret 32 little endian bytes as an unsigned integer.
+fn u256_to_f64(bytes: &[u8; 32]) -> f64 {
+	bytes.iter().rev().fold(0f64, |n, &b| n * 256f64 + b as f64)
 }
 
 impl<T> From<Primitive> for ValueDef<T> {
@@ -292,7 +1236,919 @@ pub type BitSequence = BitVec<Lsb0, u8>;
 /// An opaque error that is returned if we cannot deserialize the [`Value`] type.
 pub use deserializer::Error as DeserializeError;
 
+pub use builder::{NamedBuilder, UnnamedBuilder, ValueBuilder, VariantBuilder};
+pub use convert::CompositeIntoIter;
+pub(crate) use convert::{i256_to_i128, u256_to_u128};
+pub use deserializer::{
+	AnyBytes, ByIndex, DeserializeOptions, DeserializeWith, LooseFieldNames, ParseFloats, TaggedVariant,
+	UnwrapSingleValues, UnwrapTupleStructs, VariantMatch, WithFallbackVariant, WithVariantMatch,
+};
+pub use diff::Difference;
+pub use parse::{parse_value_string, parse_value_string_with_max_depth, ParseError};
+pub(crate) use path::path_to_pointer;
+pub use path::{PathError, PathSegment};
+pub use serialize::{SerializeOptions, SerializeWith, VariantTagging};
+
+#[cfg(feature = "chrono")]
+pub use timestamp::UtcMillis;
+
 /// Attempt to deserialize a [`Value`] into some type that has [`serde::Deserialize`] implemented on it.
 pub fn from_value<'de, Ctx, T: Deserialize<'de>>(value: Value<Ctx>) -> Result<T, DeserializeError> {
 	T::deserialize(value)
 }
+
+/// Attempt to deserialize a [`Value`] into some type that has [`serde::Deserialize`] implemented on it, without
+/// consuming the value. This allows several target types to be tried against the same value, and allows the
+/// target type to borrow strings from the value.
+pub fn from_value_ref<'de, Ctx, T: Deserialize<'de>>(value: &'de Value<Ctx>) -> Result<T, DeserializeError> {
+	T::deserialize(value)
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	fn none() -> Value<()> {
+		Value::variant("None".into(), Composite::Unnamed(vec![]))
+	}
+
+	fn some(val: Value<()>) -> Value<()> {
+		Value::variant("Some".into(), Composite::Unnamed(vec![val]))
+	}
+
+	#[test]
+	fn value_shape_predicates() {
+		let composite = Value::unnamed_composite(vec![Value::u8(1)]);
+		let variant = Value::variant("Foo".into(), Composite::Unnamed(vec![Value::u8(1)]));
+		let bit_sequence = Value::bit_sequence(BitSequence::new());
+		let primitive = Value::u8(1);
+
+		assert!(composite.is_composite());
+		assert!(variant.is_variant());
+		assert!(bit_sequence.is_bit_sequence());
+		assert!(primitive.is_primitive());
+
+		for val in [&variant, &bit_sequence, &primitive] {
+			assert!(!val.is_composite());
+		}
+		for val in [&composite, &bit_sequence, &primitive] {
+			assert!(!val.is_variant());
+		}
+		for val in [&composite, &variant, &primitive] {
+			assert!(!val.is_bit_sequence());
+		}
+		for val in [&composite, &variant, &bit_sequence] {
+			assert!(!val.is_primitive());
+		}
+	}
+
+	#[test]
+	fn value_is_unit() {
+		assert!(Value::unnamed_composite(vec![]).is_unit());
+		assert!(Value::named_composite(vec![]).is_unit());
+		assert!(Value::variant("Foo".into(), Composite::Unnamed(vec![])).is_unit());
+		assert!(Value::variant("Foo".into(), Composite::Named(vec![])).is_unit());
+
+		assert!(!Value::unnamed_composite(vec![Value::u8(1)]).is_unit());
+		assert!(!Value::named_composite(vec![("a".into(), Value::u8(1))]).is_unit());
+		assert!(!Value::variant("Foo".into(), Composite::Unnamed(vec![Value::u8(1)])).is_unit());
+		assert!(!Value::bit_sequence(BitSequence::new()).is_unit());
+		assert!(!Value::u8(1).is_unit());
+	}
+
+	#[test]
+	fn primitive_as_f64() {
+		assert_eq!(Primitive::U8(1).as_f64(), Some(1.0));
+		assert_eq!(Primitive::U64(123).as_f64(), Some(123.0));
+		assert_eq!(Primitive::I32(-5).as_f64(), Some(-5.0));
+		assert_eq!(Primitive::I128(-5).as_f64(), Some(-5.0));
+
+		let mut one = [0; 32];
+		one[0] = 1;
+		assert_eq!(Primitive::U256(one).as_f64(), Some(1.0));
+		assert_eq!(Primitive::I256(one).as_f64(), Some(1.0));
+		let mut big = [0; 32];
+		big[8] = 1;
+		assert_eq!(Primitive::U256(big).as_f64(), Some(2f64.powi(64)));
+		assert_eq!(Primitive::I256([0xFF; 32]).as_f64(), Some(-1.0));
+
+		assert_eq!(Primitive::Bool(true).as_f64(), None);
+		assert_eq!(Primitive::Char('a').as_f64(), None);
+		assert_eq!(Primitive::Str("1".into()).as_f64(), None);
+
+		assert_eq!(Value::u16(2).as_f64(), Some(2.0));
+		assert_eq!(Value::unnamed_composite(vec![Value::u16(2)]).as_f64(), None);
+	}
+
+	#[test]
+	fn primitive_as_u256() {
+		assert_eq!(Primitive::U64(u64::MAX).as_u256(), Some(U256::from(u64::MAX)));
+		assert_eq!(Value::u128(u128::MAX).as_u256(), Some(U256::from(u128::MAX)));
+
+		// A stored U256 is read as little endian, and so round trips:
+		let big = U256::from(u128::MAX) * U256::from(1000u32);
+		let mut bytes = [0u8; 32];
+		big.to_little_endian(&mut bytes);
+		assert_eq!(Primitive::U256(bytes).as_u256(), Some(big));
+		assert_eq!(Primitive::U256([0xFF; 32]).as_u256(), Some(U256::MAX));
+
+		// The result can be used for arithmetic without losing precision:
+		let total = Value::u64(u64::MAX).as_u256().unwrap() + Value::u64(1).as_u256().unwrap();
+		assert_eq!(total, U256::from(u64::MAX as u128 + 1));
+
+		assert_eq!(Primitive::I8(1).as_u256(), None);
+		assert_eq!(Primitive::I256([0; 32]).as_u256(), None);
+		assert_eq!(Primitive::Str("1".into()).as_u256(), None);
+		assert_eq!(Value::unnamed_composite(vec![]).as_u256(), None);
+	}
+
+	#[test]
+	fn primitive_as_i256() {
+		assert_eq!(Primitive::I8(-1).as_i256(), Some((true, U256::one())));
+		assert_eq!(Primitive::I128(i128::MIN).as_i256(), Some((true, U256::from(i128::MIN.unsigned_abs()))));
+		assert_eq!(Value::u64(5).as_i256(), Some((false, U256::from(5u8))));
+
+		// A stored I256 is read as little endian two's complement:
+		assert_eq!(Primitive::I256([0xFF; 32]).as_i256(), Some((true, U256::one())));
+		let mut big_negative = [0xFF; 32];
+		big_negative[16] = 0xFE;
+		assert_eq!(Primitive::I256(big_negative).as_i256(), Some((true, U256::from(u128::MAX) + 2)));
+		let mut min = [0u8; 32];
+		min[31] = 0x80;
+		assert_eq!(Primitive::I256(min).as_i256(), Some((true, U256::one() << 255)));
+		assert_eq!(Primitive::I256([0; 32]).as_i256(), Some((false, U256::zero())));
+
+		assert_eq!(Primitive::Str("1".into()).as_i256(), None);
+		assert_eq!(Value::unnamed_composite(vec![]).as_i256(), None);
+	}
+
+	#[test]
+	fn option_accessors() {
+		assert_eq!(none().as_option(), Some(None));
+		assert_eq!(none().into_option(), Some(None));
+
+		assert_eq!(some(Value::u32(1)).as_option(), Some(Some(&Value::u32(1))));
+		assert_eq!(some(Value::u32(1)).into_option(), Some(Some(Value::u32(1))));
+
+		// Named values are fine too:
+		let named_some = Value::variant("Some".into(), Composite::Named(vec![("a".into(), Value::u32(1))]));
+		assert_eq!(named_some.as_option(), Some(Some(&Value::u32(1))));
+		assert_eq!(named_some.into_option(), Some(Some(Value::u32(1))));
+
+		// Things that don't look like options:
+		let not_options = vec![
+			Value::u32(1),
+			Value::unnamed_composite(vec![]),
+			Value::variant("None".into(), Composite::Unnamed(vec![Value::u32(1)])),
+			Value::variant("Some".into(), Composite::Unnamed(vec![])),
+			Value::variant("Some".into(), Composite::Unnamed(vec![Value::u32(1), Value::u32(2)])),
+			Value::variant("Foo".into(), Composite::Unnamed(vec![Value::u32(1)])),
+		];
+		for val in not_options {
+			assert_eq!(val.as_option(), None);
+			assert_eq!(val.into_option(), None);
+		}
+	}
+
+	#[test]
+	fn option_accessors_agree_with_deserializing() {
+		// For option shaped values, deserializing should hand back the same thing as our accessors:
+		let val = none();
+		assert_eq!(val.clone().into_option(), Some(None));
+		assert_eq!(from_value::<_, Option<u32>>(val), Ok(None));
+
+		let val = some(Value::u32(1));
+		assert_eq!(val.clone().into_option(), Some(Some(Value::u32(1))));
+		assert_eq!(from_value::<_, Option<u32>>(val), Ok(Some(1)));
+
+		// Values that don't look like options are not options according to our accessors,
+		// but (like `serde_json`) will deserialize into `Some(value)`:
+		let val = Value::u32(1);
+		assert_eq!(val.clone().into_option(), None);
+		assert_eq!(from_value::<_, Option<u32>>(val), Ok(Some(1)));
+
+		// Nested options line up too:
+		let val = some(some(Value::u32(1)));
+		assert_eq!(val.clone().into_option(), Some(Some(some(Value::u32(1)))));
+		assert_eq!(from_value::<_, Option<Option<u32>>>(val), Ok(Some(Some(1))));
+
+		let val = some(none());
+		assert_eq!(val.clone().into_option(), Some(Some(none())));
+		assert_eq!(from_value::<_, Option<Option<u32>>>(val), Ok(Some(None)));
+
+		let val = none();
+		assert_eq!(from_value::<_, Option<Option<u32>>>(val), Ok(None));
+	}
+
+	#[test]
+	fn equivalent_values() {
+		assert!(Value::u8(1).equivalent(&Value::u64(1)));
+		assert!(Value::u8(1).equivalent(&Value::i32(1)));
+		assert!(!Value::u8(1).equivalent(&Value::i8(-1)));
+		assert!(!Value::u8(255).equivalent(&Value::i8(-1)));
+		assert!(Value::i8(-1).equivalent(&Value::i128(-1)));
+		assert!(Value::i8(-1).equivalent(&Value::primitive(Primitive::I256([0xFF; 32]))));
+		assert!(
+			!Value::primitive(Primitive::U256([0xFF; 32])).equivalent(&Value::primitive(Primitive::I256([0xFF; 32])))
+		);
+		assert!(Value::u128(u128::MAX).equivalent(&Value::primitive(Primitive::U256({
+			let mut bytes = [0; 32];
+			bytes[..16].copy_from_slice(&[0xFF; 16]);
+			bytes
+		}))));
+		assert!(!Value::u8(1).equivalent(&Value::bool(true)));
+
+		// Composites are compared recursively, and context is ignored:
+		let a = Value::named_composite(vec![("a".into(), Value::u8(1)), ("b".into(), some(Value::u16(2)))]);
+		let b = Value::named_composite(vec![("a".into(), Value::u64(1)), ("b".into(), some(Value::u32(2)))])
+			.map_context(|_| 123u32);
+		assert!(a.equivalent(&b));
+
+		// But named and unnamed composites differ:
+		let a = Value::named_composite(vec![("0".into(), Value::u8(1))]);
+		let b = Value::unnamed_composite(vec![Value::u8(1)]);
+		assert!(!a.equivalent(&b));
+	}
+
+	#[test]
+	fn named_composite_to_variant() {
+		#[derive(Deserialize, Debug, PartialEq)]
+		enum Animal {
+			Dog { name: String, age: u8 },
+			Cat,
+		}
+
+		let val = Value::named_composite(vec![
+			("name".into(), Value::str("Rex".into())),
+			("type".into(), Value::str("Dog".into())),
+			("age".into(), Value::u8(3)),
+		])
+		.named_to_variant("type");
+
+		assert_eq!(
+			val,
+			Value::variant(
+				"Dog".into(),
+				Composite::Named(vec![("name".into(), Value::str("Rex".into())), ("age".into(), Value::u8(3))])
+			)
+		);
+		assert_eq!(from_value(val), Ok(Animal::Dog { name: "Rex".into(), age: 3 }));
+
+		let val = Value::named_composite(vec![("type".into(), Value::str("Cat".into()))]).named_to_variant("type");
+		assert_eq!(from_value(val), Ok(Animal::Cat));
+
+		// Values without a string tag are left alone:
+		let val = Value::named_composite(vec![("type".into(), Value::u8(1))]);
+		assert_eq!(val.clone().named_to_variant("type"), val);
+		let val = Value::unnamed_composite(vec![Value::str("Dog".into())]);
+		assert_eq!(val.clone().named_to_variant("type"), val);
+	}
+
+	#[test]
+	fn hex_round_trip() {
+		let mut registry = scale_info::Registry::new();
+		let id: TypeId = registry.register_type(&scale_info::MetaType::new::<(u8, bool, String)>()).into();
+		let types: PortableRegistry = registry.into();
+
+		let value = Value::unnamed_composite(vec![Value::u8(255), Value::bool(true), Value::str("hi".into())]);
+		let hex = value.encode_to_hex(id, &types).expect("can encode");
+		assert_eq!(hex, "0xff01086869");
+
+		// We can decode with or without the 0x prefix:
+		let decoded = Value::decode_from_hex(&hex, id, &types).expect("can decode");
+		assert_eq!(decoded.without_context(), value);
+		let decoded = Value::decode_from_hex(&hex[2..], id, &types).expect("can decode");
+		assert_eq!(decoded.without_context(), value);
+
+		// Bad hex, or the wrong number of bytes, are errors:
+		Value::decode_from_hex("0xff01086", id, &types).expect_err("odd length");
+		Value::decode_from_hex("0xff01086g69", id, &types).expect_err("not hex");
+		Value::decode_from_hex("0xff0108686900", id, &types).expect_err("too many bytes");
+		Value::decode_from_hex("0xff010868", id, &types).expect_err("too few bytes");
+	}
+
+	#[test]
+	fn sort_named_fields() {
+		let a = Composite::Named(vec![
+			("b".into(), Value::u8(2)),
+			("a".into(), Value::named_composite(vec![("y".into(), Value::u8(1)), ("x".into(), Value::u8(0))])),
+			("c".into(), Value::unnamed_composite(vec![Value::u8(3), Value::u8(1)])),
+		]);
+		let b = Composite::Named(vec![
+			("c".into(), Value::unnamed_composite(vec![Value::u8(3), Value::u8(1)])),
+			("a".into(), Value::named_composite(vec![("x".into(), Value::u8(0)), ("y".into(), Value::u8(1))])),
+			("b".into(), Value::u8(2)),
+		]);
+
+		assert_ne!(a, b);
+		assert_eq!(a.sorted(), b.sorted());
+
+		// Unnamed values aren't reordered:
+		let mut a = a;
+		a.sort_named_fields();
+		assert_eq!(
+			a,
+			Composite::Named(vec![
+				("a".into(), Value::named_composite(vec![("x".into(), Value::u8(0)), ("y".into(), Value::u8(1))])),
+				("b".into(), Value::u8(2)),
+				("c".into(), Value::unnamed_composite(vec![Value::u8(3), Value::u8(1)])),
+			])
+		);
+	}
+
+	#[test]
+	fn depth_and_node_count() {
+		// Flat values:
+		let val = Value::u8(1);
+		assert_eq!((val.depth(), val.node_count()), (1, 1));
+		let val = Value::unnamed_composite(vec![]);
+		assert_eq!((val.depth(), val.node_count()), (1, 1));
+
+		// A nested composite:
+		let val = Value::named_composite(vec![
+			("a".into(), Value::u8(1)),
+			("b".into(), Value::unnamed_composite(vec![Value::bool(true), Value::unnamed_composite(vec![])])),
+		]);
+		assert_eq!((val.depth(), val.node_count()), (3, 5));
+
+		// A variant inside a composite:
+		let val = Value::unnamed_composite(vec![Value::u8(1), some(some(Value::u8(2)))]);
+		assert_eq!((val.depth(), val.node_count()), (4, 5));
+	}
+
+	#[test]
+	fn depth_of_very_deep_value_does_not_overflow() {
+		let mut val = Value::u8(0);
+		for _ in 0..100_000 {
+			val = Value::unnamed_composite(vec![val]);
+		}
+		assert_eq!((val.depth(), val.node_count()), (100_001, 100_001));
+
+		// Dropping is recursive, so unwrap the value again before it's dropped:
+		while let ValueDef::Composite(Composite::Unnamed(mut values)) = val.value {
+			val = values.pop().expect("one value");
+		}
+	}
+
+	#[test]
+	fn composite_into_named_and_unnamed() {
+		let unnamed = Composite::Unnamed(vec![Value::u8(1), Value::bool(true)]);
+		assert!(unnamed.is_unnamed());
+		assert!(!unnamed.is_named());
+		assert_eq!(unnamed.clone().into_unnamed(), vec![Value::u8(1), Value::bool(true)]);
+		assert_eq!(unnamed.into_named(), vec![("0".into(), Value::u8(1)), ("1".into(), Value::bool(true))]);
+
+		let named = Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]);
+		assert!(named.is_named());
+		assert!(!named.is_unnamed());
+		assert_eq!(named.clone().into_unnamed(), vec![Value::u8(1), Value::bool(true)]);
+		// Existing names are left alone:
+		assert_eq!(named.into_named(), vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]);
+	}
+
+	#[test]
+	fn approx_memory_size_grows_with_fields() {
+		let value_size = std::mem::size_of::<Value<()>>();
+		assert_eq!(Value::u8(1).approx_memory_size(), value_size);
+		assert_eq!(Value::str("hello".into()).approx_memory_size(), value_size + 5);
+
+		let mut fields = vec![("a".into(), Value::u8(1))];
+		let mut last_size = Value::named_composite(fields.clone()).approx_memory_size();
+		assert!(last_size > value_size);
+		for name in ["b", "c", "d"] {
+			fields.push((name.into(), Value::str("hello".into())));
+			let size = Value::named_composite(fields.clone()).approx_memory_size();
+			assert!(size > last_size, "size should grow as fields are added");
+			last_size = size;
+		}
+
+		// Nested values count too:
+		let nested = Value::unnamed_composite(vec![Value::named_composite(fields.clone())]);
+		assert!(nested.approx_memory_size() > last_size);
+	}
+
+	#[test]
+	fn into_inner_values() {
+		assert_eq!(Value::u8(1).into_primitive(), Ok(Primitive::U8(1)));
+		let composite = Composite::Unnamed(vec![Value::u8(1)]);
+		assert_eq!(Value::unnamed_composite(vec![Value::u8(1)]).into_composite(), Ok(composite.clone()));
+		let variant = Value::variant("Foo".into(), composite.clone());
+		assert_eq!(variant.clone().into_variant().map(|v| v.name), Ok("Foo".to_string()));
+		assert_eq!(Value::bit_sequence(BitSequence::new()).into_bit_sequence(), Ok(BitSequence::new()));
+
+		// On a mismatch, the original value (including its context) is handed back:
+		let value = Value::unnamed_composite(vec![Value::u8(1)]).map_context(|_| 123u32);
+		assert_eq!(value.clone().into_primitive(), Err(value.clone()));
+		assert_eq!(value.clone().into_variant(), Err(value.clone()));
+		assert_eq!(value.clone().into_bit_sequence(), Err(value));
+		assert_eq!(Value::u8(1).into_composite(), Err(Value::u8(1)));
+		assert_eq!(variant.clone().into_primitive(), Err(variant));
+	}
+
+	#[test]
+	fn variant_index_is_preserved_and_shown() {
+		let variant = Value::variant("Foo".into(), Composite::Unnamed(vec![Value::u8(1)]));
+		assert!(format!("{:?}", variant.value).starts_with("Foo ("));
+
+		let mut variant = variant.into_variant().expect("is a variant");
+		assert_eq!(variant.index, None);
+		variant.index = Some(3);
+		assert!(format!("{:?}", variant).starts_with("Foo#3 ("));
+
+		let variant = variant.map_context(|_| 1u8);
+		assert_eq!(variant.index, Some(3));
+	}
+
+	#[test]
+	fn composite_push_unnamed() {
+		let mut composite = Composite::Unnamed(vec![]);
+		composite.push(Value::u8(1)).expect("unnamed");
+		composite.push(Value::bool(true)).expect("unnamed");
+		assert_eq!(composite, Composite::Unnamed(vec![Value::u8(1), Value::bool(true)]));
+
+		// Empty named composites are converted:
+		let mut composite = Composite::Named(vec![]);
+		composite.push(Value::u8(1)).expect("empty");
+		assert_eq!(composite, Composite::Unnamed(vec![Value::u8(1)]));
+
+		// Other named composites hand the value back:
+		let mut composite = Composite::Named(vec![("a".into(), Value::u8(1))]);
+		assert_eq!(composite.push(Value::u8(2)), Err(Value::u8(2)));
+		assert_eq!(composite, Composite::Named(vec![("a".into(), Value::u8(1))]));
+	}
+
+	#[test]
+	fn composite_push_and_insert_named() {
+		let mut composite = Composite::Unnamed(vec![]);
+		composite.push_field("a", Value::u8(1)).expect("empty");
+		composite.push_field(String::from("b"), Value::u8(2)).expect("named");
+		assert_eq!(composite.insert("a", Value::u8(3)), Ok(Some(Value::u8(1))));
+		assert_eq!(composite.insert("c", Value::u8(4)), Ok(None));
+		assert_eq!(
+			composite,
+			Composite::Named(vec![("a".into(), Value::u8(3)), ("b".into(), Value::u8(2)), ("c".into(), Value::u8(4))])
+		);
+
+		// push_field doesn't check for existing fields:
+		composite.push_field("a", Value::u8(5)).expect("named");
+		assert_eq!(composite.len(), 4);
+
+		// Non-empty unnamed composites hand the value back:
+		let mut composite = Composite::Unnamed(vec![Value::u8(1)]);
+		assert_eq!(composite.push_field("a", Value::u8(2)), Err(Value::u8(2)));
+		assert_eq!(composite.insert("a", Value::u8(2)), Err(Value::u8(2)));
+		assert_eq!(composite, Composite::Unnamed(vec![Value::u8(1)]));
+	}
+
+	#[test]
+	fn strings_from_utf8_bytes() {
+		assert_eq!(Value::str_from_utf8_lossy("hello".as_bytes()), Value::str("hello".into()));
+		assert_eq!(Primitive::str_from_utf8_lossy(&[b'h', 0xFF, b'i']), Primitive::Str("h\u{FFFD}i".into()));
+
+		let u8s = |bytes: &[u8]| Value::unnamed_composite(bytes.iter().map(|&b| Value::u8(b)).collect());
+		assert_eq!(u8s("Alice".as_bytes()).as_str_lossy().as_deref(), Some("Alice"));
+		assert_eq!(u8s(&[0xF0, 0x9F, 0x92, 0x96]).as_str_lossy().as_deref(), Some("\u{1F496}"));
+		assert_eq!(u8s(&[b'a', 0xC3]).as_str_lossy().as_deref(), Some("a\u{FFFD}"));
+		assert_eq!(u8s(&[]).as_str_lossy().as_deref(), Some(""));
+		assert_eq!(Value::str("hi".into()).as_str_lossy().as_deref(), Some("hi"));
+
+		// Anything else isn't a string:
+		assert_eq!(Value::unnamed_composite(vec![Value::u8(1), Value::u16(2)]).as_str_lossy(), None);
+		assert_eq!(Value::named_composite(vec![("a".into(), Value::u8(1))]).as_str_lossy(), None);
+		assert_eq!(Value::u8(1).as_str_lossy(), None);
+	}
+
+	#[test]
+	fn canonical_forms_ignore_named_field_order() {
+		let a = Value::variant(
+			"Foo".into(),
+			Composite::Named(vec![
+				("b".into(), Value::named_composite(vec![("y".into(), Value::u8(1)), ("x".into(), Value::u8(0))])),
+				("a".into(), Value::unnamed_composite(vec![Value::u8(2), Value::u8(1)])),
+			]),
+		);
+		let b = Value::variant(
+			"Foo".into(),
+			Composite::Named(vec![
+				("a".into(), Value::unnamed_composite(vec![Value::u8(2), Value::u8(1)])),
+				("b".into(), Value::named_composite(vec![("x".into(), Value::u8(0)), ("y".into(), Value::u8(1))])),
+			]),
+		);
+		assert_ne!(a, b);
+
+		let (a, b) = (a.into_canonical(), b.into_canonical());
+		assert_eq!(a, b);
+		// Representations of the canonical forms (which can be hashed) line up too:
+		assert_eq!(a.to_scale_value_string(), "Foo { a: (2, 1), b: { x: 0, y: 1 } }");
+		assert_eq!(a.to_scale_value_string(), b.to_scale_value_string());
+
+		// Unnamed values keep their order:
+		let c = Value::unnamed_composite(vec![Value::u8(2), Value::u8(1)]);
+		assert_eq!(c.clone().into_canonical(), c);
+	}
+
+	#[test]
+	fn fold_sums_integers() {
+		let value = Value::named_composite(vec![
+			("a".into(), Value::u64(1)),
+			("b".into(), Value::unnamed_composite(vec![Value::u64(2), Value::u8(3), Value::str("4".into())])),
+			("c".into(), Value::variant("Foo".into(), Composite::Unnamed(vec![Value::u64(4)]))),
+		]);
+		let sum = value.fold(0u64, |sum, value| match value {
+			ValueDef::Primitive(Primitive::U64(n)) => sum + n,
+			_ => sum,
+		});
+		assert_eq!(sum, 7);
+
+		// Every node is visited, including the composites and variants:
+		assert_eq!(value.fold(0, |count, _| count + 1), value.node_count());
+	}
+
+	#[test]
+	fn fold_collects_variant_names_in_pre_order() {
+		let value = Value::variant(
+			"Outer".into(),
+			Composite::Named(vec![
+				("a".into(), Value::variant("First".into(), Composite::Unnamed(vec![]))),
+				(
+					"b".into(),
+					Value::unnamed_composite(vec![Value::variant(
+						"Second".into(),
+						Composite::Unnamed(vec![Value::variant("Third".into(), Composite::Unnamed(vec![]))]),
+					)]),
+				),
+				("c".into(), Value::variant("Fourth".into(), Composite::Unnamed(vec![]))),
+			]),
+		);
+		let names = value.fold(Vec::new(), |mut names, value| {
+			if let ValueDef::Variant(variant) = value {
+				names.push(variant.name.clone());
+			}
+			names
+		});
+		assert_eq!(names, vec!["Outer", "First", "Second", "Third", "Fourth"]);
+	}
+
+	#[test]
+	fn as_unix_millis() {
+		assert_eq!(Value::u64(1_600_000_000_123).as_unix_millis(), Some(1_600_000_000_123));
+		assert_eq!(Value::u128(1_000).as_unix_millis(), Some(1_000));
+		assert_eq!(Value::i32(-1_000).as_unix_millis(), Some(-1_000));
+		assert_eq!(Value::u64(u64::MAX).as_unix_millis(), None);
+		assert_eq!(Value::str("1000".into()).as_unix_millis(), None);
+		assert_eq!(Value::f64(1000.0).as_unix_millis(), None);
+		assert_eq!(Value::unnamed_composite(vec![Value::u64(1)]).as_unix_millis(), None);
+	}
+
+	#[test]
+	fn same_shape_ignores_data() {
+		use bitvec::{bitvec, order::Lsb0};
+		let value = |amount: u128, memo: &str, bits: BitSequence| {
+			Value::variant(
+				"Transfer".into(),
+				Composite::Named(vec![
+					("amount".into(), Value::u128(amount)),
+					("memo".into(), Value::unnamed_composite(vec![Value::str(memo.into())])),
+					("bits".into(), Value::bit_sequence(bits)),
+				]),
+			)
+		};
+		let a = value(1, "hello", bitvec![Lsb0, u8; 1, 0]);
+		let b = value(1000, "bye", bitvec![Lsb0, u8; 1, 1, 1]);
+		assert!(a.same_shape(&b));
+		assert!(a.same_shape(&b.clone().map_context(|_| 1u8)));
+
+		// Different primitive types, names or lengths give different shapes:
+		assert!(!Value::u8(1).same_shape(&Value::u16(1)));
+		assert!(!a.same_shape(&Value::variant("Other".into(), Composite::Named(vec![]))));
+		assert!(!Value::unnamed_composite(vec![Value::u8(1)]).same_shape(&Value::unnamed_composite(vec![])));
+		assert!(!Value::named_composite(vec![("a".into(), Value::u8(1))])
+			.same_shape(&Value::named_composite(vec![("b".into(), Value::u8(1))])));
+		assert!(!Value::unnamed_composite(vec![]).same_shape(&Value::named_composite(vec![])));
+	}
+
+	#[test]
+	fn composite_retain() {
+		let mut unnamed =
+			Composite::Unnamed(vec![Value::u8(1), Value::bool(true), Value::u8(2), Value::str("a".into())]);
+		unnamed.retain(|name, v| {
+			assert_eq!(name, None);
+			matches!(v.value, ValueDef::Primitive(Primitive::U8(_)))
+		});
+		assert_eq!(unnamed, Composite::Unnamed(vec![Value::u8(1), Value::u8(2)]));
+		// Positions shift down as values are removed:
+		assert_eq!(unnamed.get_index(1), Some(&Value::u8(2)));
+
+		let mut named = Composite::Named(vec![
+			("a".into(), Value::u8(1)),
+			("secret".into(), Value::str("hunter2".into())),
+			("b".into(), Value::bool(true)),
+		]);
+		named.retain(|name, _| name != Some("secret"));
+		assert_eq!(named, Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]));
+	}
+
+	#[test]
+	fn map_strings_uppercase() {
+		let val = Value::variant(
+			"Foo".into(),
+			Composite::Named(vec![
+				("name".into(), Value::str("alice".into())),
+				("age".into(), Value::u8(30)),
+				("tags".into(), Value::unnamed_composite(vec![Value::str("a".into()), Value::bool(true)])),
+			]),
+		);
+
+		let expected = Value::variant(
+			"Foo".into(),
+			Composite::Named(vec![
+				("name".into(), Value::str("ALICE".into())),
+				("age".into(), Value::u8(30)),
+				("tags".into(), Value::unnamed_composite(vec![Value::str("A".into()), Value::bool(true)])),
+			]),
+		);
+		assert_eq!(val.clone().map_strings(|s| s.to_uppercase(), false), expected);
+
+		let expected = Value::variant(
+			"Foo".into(),
+			Composite::Named(vec![
+				("NAME".into(), Value::str("ALICE".into())),
+				("AGE".into(), Value::u8(30)),
+				("TAGS".into(), Value::unnamed_composite(vec![Value::str("A".into()), Value::bool(true)])),
+			]),
+		);
+		assert_eq!(val.map_strings(|s| s.to_uppercase(), true), expected);
+	}
+
+	#[test]
+	fn merge_named_composites() {
+		let a = Value::named_composite(vec![
+			("a".into(), Value::u8(1)),
+			("b".into(), Value::named_composite(vec![("x".into(), Value::u8(2)), ("y".into(), Value::u8(3))])),
+			("c".into(), Value::unnamed_composite(vec![Value::u8(4)])),
+		]);
+		let b = Value::named_composite(vec![
+			("b".into(), Value::named_composite(vec![("y".into(), Value::u8(30)), ("z".into(), Value::u8(40))])),
+			("c".into(), Value::unnamed_composite(vec![Value::u8(5), Value::u8(6)])),
+			("d".into(), Value::bool(true)),
+		]);
+
+		let expected = Value::named_composite(vec![
+			("a".into(), Value::u8(1)),
+			(
+				"b".into(),
+				Value::named_composite(vec![
+					("x".into(), Value::u8(2)),
+					("y".into(), Value::u8(30)),
+					("z".into(), Value::u8(40)),
+				]),
+			),
+			// Unnamed composites aren't merged; `other` wins:
+			("c".into(), Value::unnamed_composite(vec![Value::u8(5), Value::u8(6)])),
+			("d".into(), Value::bool(true)),
+		]);
+		assert_eq!(a.merge(b), expected);
+	}
+
+	#[test]
+	fn merge_different_shapes_returns_other() {
+		let a = Value::named_composite(vec![("a".into(), Value::u8(1))]);
+		let b = Value::unnamed_composite(vec![Value::u8(2)]);
+		assert_eq!(a.clone().merge(b.clone()), b);
+		assert_eq!(b.merge(a.clone()), a);
+
+		// A nested named composite is replaced wholesale by a value of a different shape:
+		let a = Value::named_composite(vec![("a".into(), Value::named_composite(vec![("x".into(), Value::u8(1))]))]);
+		let b = Value::named_composite(vec![("a".into(), Value::u8(2))]);
+		assert_eq!(a.merge(b.clone()), b);
+	}
+
+	#[test]
+	fn as_result() {
+		let ok = Value::variant("Ok".into(), Composite::Unnamed(vec![Value::u8(5)]));
+		assert_eq!(ok.as_result(), Some(Ok(&Value::u8(5))));
+
+		let err = Value::variant("Err".into(), Composite::Unnamed(vec![Value::str("bad".into())]));
+		assert_eq!(err.as_result(), Some(Err(&Value::str("bad".into()))));
+
+		// Not shaped like a result:
+		assert_eq!(Value::u8(5).as_result(), None);
+		assert_eq!(Value::variant("Foo".into(), Composite::Unnamed(vec![Value::u8(5)])).as_result(), None);
+		assert_eq!(Value::variant("Ok".into(), Composite::Unnamed(vec![])).as_result(), None);
+	}
+
+	#[test]
+	fn redact_strings_and_other_primitives() {
+		let value = Value::variant(
+			"set_identity".into(),
+			Composite::Named(vec![
+				("display".into(), Value::str("Alice".into())),
+				("nested".into(), Value::unnamed_composite(vec![Value::u32(1), Value::str("secret".into())])),
+				("amount".into(), Value::u128(100)),
+			]),
+		);
+		let expected = Value::variant(
+			"set_identity".into(),
+			Composite::Named(vec![
+				("display".into(), Value::str("***".into())),
+				("nested".into(), Value::unnamed_composite(vec![Value::u32(1), Value::str("***".into())])),
+				("amount".into(), Value::u128(100)),
+			]),
+		);
+		assert_eq!(value.clone().redact_strings("***"), expected);
+
+		let redacted = value.redact_where("?", |prim| matches!(prim, Primitive::U128(_) | Primitive::U32(_)));
+		assert_eq!(redacted.at(&["amount".into()]), Ok(&Value::str("?".into())));
+		assert_eq!(redacted.at(&["nested".into(), 0.into()]), Ok(&Value::str("?".into())));
+		assert_eq!(redacted.at(&["display".into()]), Ok(&Value::str("Alice".into())));
+	}
+
+	#[test]
+	fn walk_mut_zeroes_u8s() {
+		let mut val = Value::named_composite(vec![
+			("a".into(), Value::u8(1)),
+			(
+				"b".into(),
+				Value::variant(
+					"Foo".into(),
+					Composite::Unnamed(vec![Value::u8(2), Value::u16(3), Value::unnamed_composite(vec![Value::u8(4)])]),
+				),
+			),
+		]);
+
+		let mut seen = Vec::new();
+		val.walk_mut(|prim| {
+			seen.push(prim.clone());
+			if let Primitive::U8(n) = prim {
+				*n = 0;
+			}
+		});
+
+		// Primitives are visited in order:
+		assert_eq!(seen, vec![Primitive::U8(1), Primitive::U8(2), Primitive::U16(3), Primitive::U8(4)]);
+		assert_eq!(
+			val,
+			Value::named_composite(vec![
+				("a".into(), Value::u8(0)),
+				(
+					"b".into(),
+					Value::variant(
+						"Foo".into(),
+						Composite::Unnamed(vec![
+							Value::u8(0),
+							Value::u16(3),
+							Value::unnamed_composite(vec![Value::u8(0)])
+						]),
+					),
+				),
+			])
+		);
+	}
+
+	#[test]
+	fn index_composites() {
+		let named = Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]);
+		assert_eq!(named["b"], Value::bool(true));
+		assert_eq!(named[0], Value::u8(1));
+		assert_eq!(named.get("c"), None);
+		assert_eq!(named.get_index(2), None);
+
+		let unnamed = Composite::Unnamed(vec![Value::u8(1), Value::bool(true)]);
+		assert_eq!(unnamed[1], Value::bool(true));
+		assert_eq!(unnamed.get("0"), None);
+	}
+
+	#[test]
+	#[should_panic(expected = "No field called \"c\"")]
+	fn index_missing_field_panics() {
+		let named = Composite::Named(vec![("a".into(), Value::u8(1))]);
+		let _ = &named["c"];
+	}
+
+	#[test]
+	#[should_panic(expected = "Index 1 is out of range")]
+	fn index_out_of_range_panics() {
+		let unnamed = Composite::Unnamed(vec![Value::u8(1)]);
+		let _ = &unnamed[1];
+	}
+
+	#[test]
+	fn type_names() {
+		assert_eq!(Value::named_composite(vec![]).type_name(), "named composite");
+		assert_eq!(Value::unnamed_composite(vec![]).type_name(), "unnamed composite");
+		assert_eq!(Value::variant("Foo".into(), Composite::Unnamed(vec![])).type_name(), "variant");
+		assert_eq!(Value::bit_sequence(BitSequence::new()).type_name(), "bit sequence");
+		assert_eq!(Value::u32(1).type_name(), "u32");
+		assert_eq!(Value::str("hi".into()).type_name(), "string");
+		assert_eq!(Primitive::I256([0; 32]).type_name(), "i256");
+	}
+
+	#[test]
+	fn unwrap_options_and_results() {
+		let wrap = |name: &str, val: Value<()>| Value::variant(name.into(), Composite::Unnamed(vec![val]));
+
+		let val = wrap("Some", wrap("Ok", wrap("Some", Value::u8(42))));
+		assert_eq!(val.unwrap_options_and_results(), Value::u8(42));
+
+		// Unwrapping stops at `None` and `Err`:
+		let err = wrap("Err", Value::u8(1));
+		assert_eq!(wrap("Some", err.clone()).unwrap_options_and_results(), err);
+		let none = Value::variant("None".into(), Composite::Unnamed(vec![]));
+		assert_eq!(wrap("Ok", none.clone()).unwrap_options_and_results(), none);
+
+		// Other values are left alone:
+		assert_eq!(Value::u8(1).unwrap_options_and_results(), Value::u8(1));
+	}
+
+	#[test]
+	fn primitive_to_scale_bytes() {
+		assert_eq!(Primitive::Bool(true).to_scale_bytes(), vec![1]);
+		assert_eq!(Primitive::Char('a').to_scale_bytes(), vec![97, 0, 0, 0]);
+		assert_eq!(Primitive::Str("hi".into()).to_scale_bytes(), vec![8, b'h', b'i']);
+		assert_eq!(Primitive::U8(1).to_scale_bytes(), vec![1]);
+		assert_eq!(Primitive::U16(1).to_scale_bytes(), vec![1, 0]);
+		assert_eq!(Primitive::U32(1).to_scale_bytes(), vec![1, 0, 0, 0]);
+		assert_eq!(Primitive::U64(1).to_scale_bytes(), vec![1, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(Primitive::U128(1).to_scale_bytes(), 1u128.to_le_bytes().to_vec());
+		assert_eq!(Primitive::U256([7; 32]).to_scale_bytes(), vec![7; 32]);
+		assert_eq!(Primitive::I8(-1).to_scale_bytes(), vec![0xFF]);
+		assert_eq!(Primitive::I16(-2).to_scale_bytes(), vec![0xFE, 0xFF]);
+		assert_eq!(Primitive::I32(-1).to_scale_bytes(), vec![0xFF; 4]);
+		assert_eq!(Primitive::I64(-1).to_scale_bytes(), vec![0xFF; 8]);
+		assert_eq!(Primitive::I128(-1).to_scale_bytes(), vec![0xFF; 16]);
+		assert_eq!(Primitive::I256([0xFF; 32]).to_scale_bytes(), vec![0xFF; 32]);
+	}
+
+	#[test]
+	fn flatten_single_field() {
+		let val = Value::named_composite(vec![(
+			"inner".into(),
+			Value::named_composite(vec![("value".into(), Value::u8(1))]),
+		)]);
+		assert_eq!(val.flatten_single_field(), Value::u8(1));
+
+		// Values nested in other composites and variants are flattened too:
+		let val = Value::variant(
+			"Foo".into(),
+			Composite::Unnamed(vec![
+				Value::named_composite(vec![("a".into(), Value::u8(1))]),
+				Value::named_composite(vec![
+					("b".into(), Value::u8(2)),
+					("c".into(), Value::unnamed_composite(vec![Value::u8(3)])),
+				]),
+			]),
+		);
+		let expected = Value::variant(
+			"Foo".into(),
+			Composite::Unnamed(vec![
+				Value::u8(1),
+				Value::named_composite(vec![
+					("b".into(), Value::u8(2)),
+					("c".into(), Value::unnamed_composite(vec![Value::u8(3)])),
+				]),
+			]),
+		);
+		assert_eq!(val.flatten_single_field(), expected);
+
+		// The innermost context is kept:
+		let val = Value::with_context(
+			ValueDef::Composite(Composite::Named(vec![("a".into(), Value::with_context(Primitive::U8(1).into(), 2))])),
+			1,
+		);
+		assert_eq!(val.flatten_single_field(), Value::with_context(Primitive::U8(1).into(), 2));
+	}
+
+	#[test]
+	fn primitives_in_order() {
+		let val = Value::named_composite(vec![
+			("a".into(), Value::u8(1)),
+			(
+				"b".into(),
+				Value::variant(
+					"Foo".into(),
+					Composite::Unnamed(vec![
+						Value::unnamed_composite(vec![Value::str("x".into()), Value::bool(true)]),
+						Value::bit_sequence(BitSequence::new()),
+						Value::char('c'),
+					]),
+				),
+			),
+			("c".into(), Value::i32(-1)),
+		]);
+
+		let prims: Vec<_> = val.primitives().cloned().collect();
+		assert_eq!(
+			prims,
+			vec![
+				Primitive::U8(1),
+				Primitive::Str("x".into()),
+				Primitive::Bool(true),
+				Primitive::Char('c'),
+				Primitive::I32(-1)
+			]
+		);
+		assert_eq!(Value::u8(1).primitives().count(), 1);
+		assert_eq!(Value::unnamed_composite(vec![]).primitives().count(), 0);
+	}
+}
@@ -0,0 +1,488 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{BitSequence, Composite, Primitive, Value, ValueDef};
+use crate::decoder::DEFAULT_MAX_DEPTH;
+use sp_core::U256;
+
+/// An error that is returned if a string cannot be parsed into a [`Value`]. Each variant
+/// carries the byte offset into the string at which the problem was found.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ParseError {
+	#[error("Expected {1} at position {0}")]
+	Expected(usize, &'static str),
+	#[error("Invalid number at position {0}: {1}")]
+	InvalidNumber(usize, String),
+	#[error("Invalid escape sequence at position {0}")]
+	InvalidEscape(usize),
+	#[error("Unexpected input at position {0} after the value was parsed")]
+	TrailingInput(usize),
+	#[error("Values are nested more than {1} levels deep at position {0}")]
+	MaxDepthExceeded(usize, usize),
+}
+
+impl ParseError {
+	/// The byte offset into the string being parsed at which the error occurred.
+	pub fn position(&self) -> usize {
+		match self {
+			ParseError::Expected(pos, _) => *pos,
+			ParseError::InvalidNumber(pos, _) => *pos,
+			ParseError::InvalidEscape(pos) => *pos,
+			ParseError::TrailingInput(pos) => *pos,
+			ParseError::MaxDepthExceeded(pos, _) => *pos,
+		}
+	}
+}
+
+/// Parse a string in the textual format used by the `scale-value` crate (and produced by
+/// [`Value::to_scale_value_string`]) into a [`Value`]. Integers without a type suffix (like `123u8`)
+/// are parsed into a `U128` if positive and an `I128` if negative, or into a `U256` or `I256` if they
+/// are too large for those. Numbers with a fractional part or exponent (as well as `NaN`, `inf` and
+/// `-inf`) are parsed into an `F64` unless they have an `f32` suffix. Values nested more than
+/// [`DEFAULT_MAX_DEPTH`] levels deep are rejected.
+pub fn parse_value_string(s: &str) -> Result<Value<()>, ParseError> {
+	parse_value_string_with_max_depth(s, DEFAULT_MAX_DEPTH)
+}
+
+/// Parse a string into a [`Value`] in the same way as [`parse_value_string`], but fail with
+/// [`ParseError::MaxDepthExceeded`] if values are nested more than `max_depth` levels deep (a value
+/// with nothing nested inside it has a depth of 1; see [`Value::depth`]).
+pub fn parse_value_string_with_max_depth(s: &str, max_depth: usize) -> Result<Value<()>, ParseError> {
+	let mut parser = Parser { s, pos: 0, depth: 0, max_depth };
+	let value = parser.value()?;
+	parser.skip_whitespace();
+	if parser.pos != s.len() {
+		return Err(ParseError::TrailingInput(parser.pos));
+	}
+	Ok(value)
+}
+
+struct Parser<'a> {
+	s: &'a str,
+	pos: usize,
+	depth: usize,
+	max_depth: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn peek(&self) -> Option<char> {
+		self.s[self.pos..].chars().next()
+	}
+	fn bump(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.pos += c.len_utf8();
+		Some(c)
+	}
+	fn skip_whitespace(&mut self) {
+		while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+			self.bump();
+		}
+	}
+	// Consume the char given (after any whitespace), or complain that it was expected.
+	fn expect(&mut self, c: char, expected: &'static str) -> Result<(), ParseError> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some(next) if next == c => {
+				self.pos += c.len_utf8();
+				Ok(())
+			}
+			_ => Err(ParseError::Expected(self.pos, expected)),
+		}
+	}
+	// Consume chars while the predicate holds, returning them.
+	fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+		let start = self.pos;
+		while matches!(self.peek(), Some(c) if f(c)) {
+			self.bump();
+		}
+		&self.s[start..self.pos]
+	}
+
+	// Parse a value, keeping track of how deeply nested we are so that we can't exhaust the stack.
+	fn value(&mut self) -> Result<Value<()>, ParseError> {
+		self.skip_whitespace();
+		if self.depth >= self.max_depth {
+			return Err(ParseError::MaxDepthExceeded(self.pos, self.max_depth));
+		}
+		self.depth += 1;
+		let value = self.value_inner();
+		self.depth -= 1;
+		value
+	}
+
+	fn value_inner(&mut self) -> Result<Value<()>, ParseError> {
+		match self.peek() {
+			Some('{') | Some('(') => Ok(Value { value: ValueDef::Composite(self.composite()?), context: () }),
+			Some('<') => self.bits().map(Value::bit_sequence),
+			Some('"') => {
+				// A quoted string followed by a composite is a variant whose name needed quoting:
+				let s = self.quoted('"')?;
+				self.skip_whitespace();
+				match self.peek() {
+					Some('{') | Some('(') => Ok(Value::variant(s, self.composite()?)),
+					_ => Ok(Value::str(s)),
+				}
+			}
+			Some('\'') => self.char().map(Value::char),
+			Some(c) if c == '-' || c.is_ascii_digit() => self.number().map(Value::primitive),
+			Some(c) if c.is_alphabetic() || c == '_' => {
+				let ident = self.take_while(|c| c.is_alphanumeric() || c == '_');
+				match ident {
+					"true" => Ok(Value::bool(true)),
+					"false" => Ok(Value::bool(false)),
+					name => {
+						self.skip_whitespace();
+						match (name, self.peek()) {
+							(_, Some('{') | Some('(')) => Ok(Value::variant(name.to_string(), self.composite()?)),
+							// Floats which aren't numbers are written as their debug output writes them:
+							("NaN", _) => Ok(Value::f64(f64::NAN)),
+							("inf", _) => Ok(Value::f64(f64::INFINITY)),
+							_ => Err(ParseError::Expected(self.pos, "'{' or '(' to follow the variant name")),
+						}
+					}
+				}
+			}
+			_ => Err(ParseError::Expected(self.pos, "a value")),
+		}
+	}
+
+	fn composite(&mut self) -> Result<Composite<()>, ParseError> {
+		if self.peek() == Some('(') {
+			let values = self.delimited('(', ')', |p| p.value())?;
+			Ok(Composite::Unnamed(values))
+		} else {
+			let values = self.delimited('{', '}', |p| {
+				p.skip_whitespace();
+				let name = match p.peek() {
+					Some('"') => p.quoted('"')?,
+					Some(c) if c.is_alphabetic() || c == '_' => {
+						p.take_while(|c| c.is_alphanumeric() || c == '_').to_string()
+					}
+					_ => return Err(ParseError::Expected(p.pos, "a field name")),
+				};
+				p.expect(':', "':' after the field name")?;
+				Ok((name, p.value()?))
+			})?;
+			Ok(Composite::Named(values))
+		}
+	}
+
+	// Parse a comma separated list of items between the open and close chars given. A trailing comma is allowed.
+	fn delimited<T>(
+		&mut self,
+		open: char,
+		close: char,
+		mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+	) -> Result<Vec<T>, ParseError> {
+		self.expect(open, "an opening bracket")?;
+		let mut items = Vec::new();
+		loop {
+			self.skip_whitespace();
+			if self.peek() == Some(close) {
+				self.pos += 1;
+				return Ok(items);
+			}
+			items.push(item(self)?);
+			self.skip_whitespace();
+			match self.peek() {
+				Some(',') => self.pos += 1,
+				Some(c) if c == close => {}
+				_ => return Err(ParseError::Expected(self.pos, "',' or a closing bracket")),
+			}
+		}
+	}
+
+	fn bits(&mut self) -> Result<BitSequence, ParseError> {
+		self.expect('<', "'<'")?;
+		let mut bits = BitSequence::new();
+		loop {
+			match self.bump() {
+				Some('0') => bits.push(false),
+				Some('1') => bits.push(true),
+				Some('>') => return Ok(bits),
+				Some(',') => {}
+				Some(c) if c.is_whitespace() => {}
+				_ => return Err(ParseError::Expected(self.pos.saturating_sub(1), "'0', '1' or '>'")),
+			}
+		}
+	}
+
+	fn char(&mut self) -> Result<char, ParseError> {
+		let start = self.pos;
+		let s = self.quoted('\'')?;
+		let mut chars = s.chars();
+		match (chars.next(), chars.next()) {
+			(Some(c), None) => Ok(c),
+			_ => Err(ParseError::Expected(start, "exactly one character between single quotes")),
+		}
+	}
+
+	// Parse a string surrounded by the given quote char, handling escape sequences.
+	fn quoted(&mut self, quote: char) -> Result<String, ParseError> {
+		self.expect(quote, "an opening quote")?;
+		let mut out = String::new();
+		loop {
+			let pos = self.pos;
+			match self.bump() {
+				Some('\\') => match self.bump() {
+					Some('n') => out.push('\n'),
+					Some('r') => out.push('\r'),
+					Some('t') => out.push('\t'),
+					Some('0') => out.push('\0'),
+					Some(c @ ('\\' | '"' | '\'')) => out.push(c),
+					_ => return Err(ParseError::InvalidEscape(pos)),
+				},
+				Some(c) if c == quote => return Ok(out),
+				Some(c) => out.push(c),
+				None => return Err(ParseError::Expected(pos, "a closing quote")),
+			}
+		}
+	}
+
+	fn number(&mut self) -> Result<Primitive, ParseError> {
+		let start = self.pos;
+		let negative = self.peek() == Some('-');
+		if negative {
+			self.pos += 1;
+			if self.s[self.pos..].starts_with("inf") {
+				self.pos += 3;
+				return Ok(Primitive::F64(f64::NEG_INFINITY));
+			}
+		}
+		let digits_start = self.pos;
+		self.take_while(|c| c.is_ascii_digit());
+		if self.pos == digits_start {
+			return Err(ParseError::Expected(self.pos, "a digit"));
+		}
+		// A fractional part or exponent makes this a float:
+		let mut is_float = false;
+		if self.peek() == Some('.') {
+			self.pos += 1;
+			self.take_while(|c| c.is_ascii_digit());
+			is_float = true;
+		}
+		if matches!(self.peek(), Some('e' | 'E')) {
+			self.pos += 1;
+			if matches!(self.peek(), Some('-' | '+')) {
+				self.pos += 1;
+			}
+			self.take_while(|c| c.is_ascii_digit());
+			is_float = true;
+		}
+		let digits = &self.s[start..self.pos];
+		let suffix = self.take_while(|c| c.is_ascii_alphanumeric());
+
+		let invalid = |e: std::num::ParseIntError| ParseError::InvalidNumber(start, e.to_string());
+		let invalid_float = |e: std::num::ParseFloatError| ParseError::InvalidNumber(start, e.to_string());
+		let out_of_range =
+			|ty: &str| ParseError::InvalidNumber(start, format!("{} is out of range for a {}", digits, ty));
+		let prim = match suffix {
+			"f32" => Primitive::F32(digits.parse().map_err(invalid_float)?),
+			"f64" => Primitive::F64(digits.parse().map_err(invalid_float)?),
+			"" if is_float => Primitive::F64(digits.parse().map_err(invalid_float)?),
+			other if is_float => {
+				return Err(ParseError::InvalidNumber(start, format!("{:?} is not a valid suffix for a float", other)))
+			}
+			// Unsuffixed numbers which don't fit into 128 bits are given 256 bits:
+			"" if negative => match digits.parse() {
+				Ok(n) => Primitive::I128(n),
+				Err(_) => Primitive::I256(parse_i256(digits).ok_or_else(|| out_of_range("i256"))?),
+			},
+			"" => match digits.parse() {
+				Ok(n) => Primitive::U128(n),
+				Err(_) => Primitive::U256(parse_u256(digits).ok_or_else(|| out_of_range("u256"))?),
+			},
+			"u8" => Primitive::U8(digits.parse().map_err(invalid)?),
+			"u16" => Primitive::U16(digits.parse().map_err(invalid)?),
+			"u32" => Primitive::U32(digits.parse().map_err(invalid)?),
+			"u64" => Primitive::U64(digits.parse().map_err(invalid)?),
+			"u128" => Primitive::U128(digits.parse().map_err(invalid)?),
+			"u256" => Primitive::U256(parse_u256(digits).ok_or_else(|| out_of_range("u256"))?),
+			"i8" => Primitive::I8(digits.parse().map_err(invalid)?),
+			"i16" => Primitive::I16(digits.parse().map_err(invalid)?),
+			"i32" => Primitive::I32(digits.parse().map_err(invalid)?),
+			"i64" => Primitive::I64(digits.parse().map_err(invalid)?),
+			"i128" => Primitive::I128(digits.parse().map_err(invalid)?),
+			"i256" => Primitive::I256(parse_i256(digits).ok_or_else(|| out_of_range("i256"))?),
+			other => return Err(ParseError::InvalidNumber(start, format!("unknown type suffix {:?}", other))),
+		};
+		Ok(prim)
+	}
+}
+
+// Parse a decimal number into the 32 little endian bytes of a U256.
+fn parse_u256(digits: &str) -> Option<[u8; 32]> {
+	let n = U256::from_dec_str(digits).ok()?;
+	let mut bytes = [0; 32];
+	n.to_little_endian(&mut bytes);
+	Some(bytes)
+}
+
+// Parse a decimal number with an optional leading '-' into the 32 little endian bytes of a two's
+// complement I256.
+fn parse_i256(digits: &str) -> Option<[u8; 32]> {
+	let (negative, digits) = match digits.strip_prefix('-') {
+		Some(digits) => (true, digits),
+		None => (false, digits),
+	};
+	let magnitude = U256::from_dec_str(digits).ok()?;
+	let min_magnitude = U256::one() << 255;
+	let n = if negative && magnitude <= min_magnitude {
+		(!magnitude).overflowing_add(U256::one()).0
+	} else if !negative && magnitude < min_magnitude {
+		magnitude
+	} else {
+		return None;
+	};
+	let mut bytes = [0; 32];
+	n.to_little_endian(&mut bytes);
+	Some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use bitvec::{bitvec, order::Lsb0};
+
+	fn round_trip(value: Value<()>) {
+		let s = value.to_scale_value_string();
+		assert_eq!(parse_value_string(&s), Ok(value), "could not round trip {}", s);
+	}
+
+	#[test]
+	fn round_trip_values() {
+		round_trip(Value::bool(true));
+		round_trip(Value::u128(123));
+		round_trip(Value::i128(-123));
+		round_trip(Value::u128(u128::MAX));
+		round_trip(Value::i128(i128::MIN));
+		round_trip(Value::f64(-1.25));
+		round_trip(Value::f64(1e100));
+		round_trip(Value::f64(f64::INFINITY));
+		round_trip(Value::f64(f64::NEG_INFINITY));
+		round_trip(Value::char('\''));
+		round_trip(Value::str("say \"hi\"\n\t\\".into()));
+		round_trip(Value::bit_sequence(bitvec![Lsb0, u8; 0, 1, 1, 0]));
+		round_trip(Value::bit_sequence(BitSequence::new()));
+		round_trip(Value::named_composite(vec![]));
+		round_trip(Value::unnamed_composite(vec![]));
+		round_trip(Value::variant(
+			"Transfer".into(),
+			Composite::Named(vec![
+				("dest".into(), Value::unnamed_composite(vec![Value::u128(1), Value::str("a".into())])),
+				("weird name".into(), Value::variant("None".into(), Composite::Unnamed(vec![]))),
+				("value".into(), Value::named_composite(vec![("inner".into(), Value::bool(false))])),
+			]),
+		));
+	}
+
+	#[test]
+	fn round_trip_256_bit_numbers() {
+		// Numbers too large for 128 bits come back as 256 bit numbers:
+		let mut big = [0u8; 32];
+		big[16] = 1;
+		round_trip(Value::primitive(Primitive::U256(big)));
+		round_trip(Value::primitive(Primitive::U256([0xFF; 32])));
+
+		let mut i256_min = [0u8; 32];
+		i256_min[31] = 0x80;
+		round_trip(Value::primitive(Primitive::I256(i256_min)));
+		let mut big_negative = [0xFF; 32];
+		big_negative[16] = 0xFE;
+		round_trip(Value::primitive(Primitive::I256(big_negative)));
+
+		// Smaller ones can be given 256 bits with a suffix:
+		let mut five = [0u8; 32];
+		five[0] = 5;
+		assert_eq!(parse_value_string("5u256"), Ok(Value::primitive(Primitive::U256(five))));
+		assert_eq!(parse_value_string("-1i256"), Ok(Value::primitive(Primitive::I256([0xFF; 32]))));
+
+		// Numbers too large for 256 bits can't be parsed:
+		let max = U256::max_value();
+		assert!(matches!(parse_value_string(&format!("{}0", max)), Err(ParseError::InvalidNumber(0, _))));
+		assert!(matches!(parse_value_string(&format!("{}i256", max)), Err(ParseError::InvalidNumber(0, _))));
+		assert!(matches!(parse_value_string(&format!("-{}", max)), Err(ParseError::InvalidNumber(0, _))));
+	}
+
+	#[test]
+	fn round_trip_quoted_variant_names() {
+		for name in ["true", "false", "inf", "NaN", "a b", "", "say \"hi\""] {
+			round_trip(Value::variant(name.into(), Composite::Unnamed(vec![Value::u128(1)])));
+			round_trip(Value::variant(name.into(), Composite::Named(vec![])));
+		}
+		// A quoted string not followed by a composite is still just a string:
+		assert_eq!(
+			parse_value_string("(\"a b\", 1)"),
+			Ok(Value::unnamed_composite(vec![Value::str("a b".into()), Value::u128(1)]))
+		);
+	}
+
+	#[test]
+	fn round_trip_nan() {
+		let s = Value::f64(f64::NAN).to_scale_value_string();
+		assert!(
+			matches!(parse_value_string(&s), Ok(Value { value: ValueDef::Primitive(Primitive::F64(n)), .. }) if n.is_nan())
+		);
+		// These are still allowed as variant names:
+		assert_eq!(parse_value_string("NaN ()"), Ok(Value::variant("NaN".into(), Composite::Unnamed(vec![]))));
+	}
+
+	#[test]
+	fn parse_respects_max_depth() {
+		// Each composite is a level deeper than the values inside it:
+		assert_eq!(
+			parse_value_string_with_max_depth("((1))", 3),
+			Ok(Value::unnamed_composite(vec![Value::unnamed_composite(vec![Value::u128(1)])]))
+		);
+		assert_eq!(parse_value_string_with_max_depth("((1))", 2), Err(ParseError::MaxDepthExceeded(2, 2)));
+
+		// Deeply nested input fails rather than overflowing the stack:
+		let deep = "(".repeat(100_000);
+		assert_eq!(parse_value_string(&deep), Err(ParseError::MaxDepthExceeded(DEFAULT_MAX_DEPTH, DEFAULT_MAX_DEPTH)));
+	}
+
+	#[test]
+	fn parse_number_suffixes_and_whitespace() {
+		assert_eq!(parse_value_string("12u8"), Ok(Value::u8(12)));
+		assert_eq!(parse_value_string("-12i32"), Ok(Value::i32(-12)));
+		assert_eq!(parse_value_string("1.5"), Ok(Value::f64(1.5)));
+		assert_eq!(parse_value_string("-2e3"), Ok(Value::f64(-2000.0)));
+		assert_eq!(parse_value_string("2f32"), Ok(Value::f32(2.0)));
+		assert!(matches!(parse_value_string("1.5u8"), Err(ParseError::InvalidNumber(0, _))));
+		assert_eq!(
+			parse_value_string("  (1u16 ,2u64,)  "),
+			Ok(Value::unnamed_composite(vec![Value::u16(1), Value::u64(2)]))
+		);
+		assert_eq!(parse_value_string("{a:1}"), Ok(Value::named_composite(vec![("a".into(), Value::u128(1))])));
+		assert_eq!(parse_value_string("< 0, 1 >"), Ok(Value::bit_sequence(bitvec![Lsb0, u8; 0, 1])));
+	}
+
+	#[test]
+	fn parse_malformed_input() {
+		assert_eq!(parse_value_string(""), Err(ParseError::Expected(0, "a value")));
+		assert_eq!(parse_value_string("(1, 2"), Err(ParseError::Expected(5, "',' or a closing bracket")));
+		assert_eq!(parse_value_string("{ a 1 }"), Err(ParseError::Expected(4, "':' after the field name")));
+		assert_eq!(parse_value_string("Foo"), Err(ParseError::Expected(3, "'{' or '(' to follow the variant name")));
+		assert_eq!(parse_value_string("\"abc"), Err(ParseError::Expected(4, "a closing quote")));
+		assert_eq!(parse_value_string("\"\\q\""), Err(ParseError::InvalidEscape(1)));
+		assert_eq!(parse_value_string("<012>"), Err(ParseError::Expected(3, "'0', '1' or '>'")));
+		assert_eq!(parse_value_string("(1) 2"), Err(ParseError::TrailingInput(4)));
+		assert!(matches!(parse_value_string("256u8"), Err(ParseError::InvalidNumber(0, _))));
+		assert!(matches!(parse_value_string("(1, 2x)"), Err(ParseError::InvalidNumber(4, _))));
+		assert_eq!(parse_value_string("(1, 2").unwrap_err().position(), 5);
+	}
+}
@@ -0,0 +1,235 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{Composite, Value, ValueDef};
+
+/// One step along a path to some value nested within a [`Value`]. See [`Value::at`] and [`Value::replace_at`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+	/// The named field of a composite or variant.
+	Field(String),
+	/// The value at some position in a composite or variant (named or unnamed).
+	Index(usize),
+}
+
+impl From<&str> for PathSegment {
+	fn from(name: &str) -> Self {
+		PathSegment::Field(name.to_string())
+	}
+}
+
+impl From<usize> for PathSegment {
+	fn from(idx: usize) -> Self {
+		PathSegment::Index(idx)
+	}
+}
+
+/// An error that is returned if a path does not resolve to a value.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PathError {
+	#[error("Path segment {0}: cannot find field {1:?}")]
+	FieldNotFound(usize, String),
+	#[error("Path segment {0}: cannot find index {1}")]
+	IndexNotFound(usize, usize),
+	#[error("Path segment {0}: the value is not a composite or variant, and so has nothing nested within it")]
+	NotNested(usize),
+}
+
+impl<T> Value<T> {
+	/// Return the value found by following the path provided into this one. An empty path returns this value.
+	pub fn at(&self, path: &[PathSegment]) -> Result<&Value<T>, PathError> {
+		let mut value = self;
+		for (pos, segment) in path.iter().enumerate() {
+			let composite = match &value.value {
+				ValueDef::Composite(composite) => composite,
+				ValueDef::Variant(variant) => &variant.values,
+				ValueDef::BitSequence(_) | ValueDef::Primitive(_) => return Err(PathError::NotNested(pos)),
+			};
+			value = match (composite, segment) {
+				(Composite::Named(vals), PathSegment::Field(name)) => {
+					vals.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+				}
+				(Composite::Named(vals), PathSegment::Index(idx)) => vals.get(*idx).map(|(_, v)| v),
+				(Composite::Unnamed(vals), PathSegment::Index(idx)) => vals.get(*idx),
+				(Composite::Unnamed(_), PathSegment::Field(_)) => None,
+			}
+			.ok_or_else(|| segment_not_found(pos, segment))?;
+		}
+		Ok(value)
+	}
+
+	/// A mutable version of [`Value::at`].
+	pub fn at_mut(&mut self, path: &[PathSegment]) -> Result<&mut Value<T>, PathError> {
+		let mut value = self;
+		for (pos, segment) in path.iter().enumerate() {
+			let composite = match &mut value.value {
+				ValueDef::Composite(composite) => composite,
+				ValueDef::Variant(variant) => &mut variant.values,
+				ValueDef::BitSequence(_) | ValueDef::Primitive(_) => return Err(PathError::NotNested(pos)),
+			};
+			value = match (composite, segment) {
+				(Composite::Named(vals), PathSegment::Field(name)) => {
+					vals.iter_mut().find(|(n, _)| n == name).map(|(_, v)| v)
+				}
+				(Composite::Named(vals), PathSegment::Index(idx)) => vals.get_mut(*idx).map(|(_, v)| v),
+				(Composite::Unnamed(vals), PathSegment::Index(idx)) => vals.get_mut(*idx),
+				(Composite::Unnamed(_), PathSegment::Field(_)) => None,
+			}
+			.ok_or_else(|| segment_not_found(pos, segment))?;
+		}
+		Ok(value)
+	}
+
+	/// Replace the value found by following the path provided with a new value, returning an error
+	/// if the path does not resolve to an existing value. An empty path replaces this entire value.
+	pub fn replace_at(&mut self, path: &[PathSegment], new: Value<T>) -> Result<(), PathError> {
+		*self.at_mut(path)? = new;
+		Ok(())
+	}
+	/// Look up a value using a JSON Pointer-like string (as with `serde_json::Value::pointer`). The pointer
+	/// is made up of tokens which each begin with `/`, and in which `~1` and `~0` are used to escape `/`
+	/// and `~` respectively. Named composite fields (including those of variants) are looked up by name,
+	/// and unnamed composite values by their index. The empty pointer returns this value. `None` is returned
+	/// if the pointer is malformed or doesn't lead to a value.
+	pub fn pointer(&self, pointer: &str) -> Option<&Value<T>> {
+		if pointer.is_empty() {
+			return Some(self);
+		}
+		if !pointer.starts_with('/') {
+			return None;
+		}
+		let mut value = self;
+		for token in pointer.split('/').skip(1) {
+			let token = token.replace("~1", "/").replace("~0", "~");
+			let composite = match &value.value {
+				ValueDef::Composite(composite) => composite,
+				ValueDef::Variant(variant) => &variant.values,
+				ValueDef::BitSequence(_) | ValueDef::Primitive(_) => return None,
+			};
+			value = match composite {
+				Composite::Named(_) => composite.get(&token)?,
+				Composite::Unnamed(_) => composite.get_index(token.parse().ok()?)?,
+			};
+		}
+		Some(value)
+	}
+}
+
+/// Render a path as a pointer of the sort accepted by [`Value::pointer`], escaping any `~` and `/` in field
+/// names. The empty path is rendered as the empty pointer.
+pub(crate) fn path_to_pointer(path: &[PathSegment]) -> String {
+	path.iter()
+		.map(|segment| match segment {
+			PathSegment::Field(name) => format!("/{}", name.replace('~', "~0").replace('/', "~1")),
+			PathSegment::Index(idx) => format!("/{}", idx),
+		})
+		.collect()
+}
+
+fn segment_not_found(pos: usize, segment: &PathSegment) -> PathError {
+	match segment {
+		PathSegment::Field(name) => PathError::FieldNotFound(pos, name.clone()),
+		PathSegment::Index(idx) => PathError::IndexNotFound(pos, *idx),
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	fn call() -> Value<()> {
+		Value::variant(
+			"transfer".into(),
+			Composite::Named(vec![
+				("dest".into(), Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)])),
+				(
+					"details".into(),
+					Value::named_composite(vec![
+						("amount".into(), Value::u128(100)),
+						("memo".into(), Value::str("hi".into())),
+					]),
+				),
+			]),
+		)
+	}
+
+	#[test]
+	fn get_values_at_paths() {
+		let call = call();
+		assert_eq!(call.at(&[]), Ok(&call));
+		assert_eq!(call.at(&["details".into(), "amount".into()]), Ok(&Value::u128(100)));
+		assert_eq!(call.at(&["dest".into(), 1.into()]), Ok(&Value::u8(2)));
+		// Named fields can be indexed by position too:
+		assert_eq!(call.at(&[1.into(), 1.into()]), Ok(&Value::str("hi".into())));
+	}
+
+	#[test]
+	fn replace_deeply_nested_value() {
+		let mut call = call();
+		call.replace_at(&["details".into(), "amount".into()], Value::u128(200)).expect("path exists");
+		assert_eq!(call.at(&["details".into(), "amount".into()]), Ok(&Value::u128(200)));
+
+		call.replace_at(&["dest".into(), 0.into()], Value::u8(3)).expect("path exists");
+		assert_eq!(call.at(&["dest".into()]), Ok(&Value::unnamed_composite(vec![Value::u8(3), Value::u8(2)])));
+	}
+
+	#[test]
+	fn replace_at_nonexistent_path() {
+		let mut call = call();
+		assert_eq!(call.replace_at(&["dest".into(), 2.into()], Value::u8(3)), Err(PathError::IndexNotFound(1, 2)));
+		assert_eq!(call.replace_at(&["nope".into()], Value::u8(3)), Err(PathError::FieldNotFound(0, "nope".into())));
+		assert_eq!(call.replace_at(&["dest".into(), 0.into(), 0.into()], Value::u8(3)), Err(PathError::NotNested(2)));
+		// Nothing was changed:
+		assert_eq!(call, self::call());
+	}
+
+	#[test]
+	fn pointer_access() {
+		let call = call();
+		assert_eq!(call.pointer(""), Some(&call));
+		assert_eq!(call.pointer("/details/amount"), Some(&Value::u128(100)));
+		assert_eq!(call.pointer("/dest/1"), Some(&Value::u8(2)));
+
+		let val =
+			Value::named_composite(vec![("a/b".into(), Value::named_composite(vec![("~c".into(), Value::u8(1))]))]);
+		assert_eq!(val.pointer("/a~1b/~0c"), Some(&Value::u8(1)));
+	}
+
+	#[test]
+	fn path_to_pointer_escapes_names() {
+		let val = Value::named_composite(vec![(
+			"a/b".into(),
+			Value::named_composite(vec![("~c".into(), Value::unnamed_composite(vec![Value::u8(1)]))]),
+		)]);
+		let path: Vec<PathSegment> = vec!["a/b".into(), "~c".into(), 0.into()];
+		assert_eq!(path_to_pointer(&path), "/a~1b/~0c/0");
+		assert_eq!(val.pointer(&path_to_pointer(&path)), Some(&Value::u8(1)));
+		assert_eq!(path_to_pointer(&[]), "");
+	}
+
+	#[test]
+	fn pointer_to_missing_value() {
+		let call = call();
+		assert_eq!(call.pointer("/details/nope"), None);
+		assert_eq!(call.pointer("/dest/2"), None);
+		assert_eq!(call.pointer("/dest/foo"), None);
+		// Named fields are only looked up by name:
+		assert_eq!(call.pointer("/0"), None);
+		// Pointers must begin with a slash:
+		assert_eq!(call.pointer("details"), None);
+	}
+}
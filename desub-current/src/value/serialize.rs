@@ -15,10 +15,14 @@
 // along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{Composite, Primitive, Value, ValueDef, Variant};
+use crate::TypeId;
+use scale_info::{form::PortableForm, Field, PortableRegistry, TypeDef};
 use serde::{
-	ser::{SerializeMap, SerializeSeq},
+	ser::{Error as _, SerializeMap, SerializeSeq},
 	Serialize,
 };
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use sp_core::U256;
 
 impl<T> Serialize for Value<T> {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -72,7 +76,9 @@ impl Serialize for Primitive {
 	where
 		S: serde::Serializer,
 	{
-		// Delegate to the serialization strategy used by the primitive types.
+		// Delegate to the serialization strategy used by the primitive types, except for
+		// the 256 bit integers, which are serialized as decimal strings since they are too
+		// large to be represented as numbers in many formats (JSON, for instance).
 		match self {
 			Primitive::Bool(v) => v.serialize(serializer),
 			Primitive::Char(v) => v.serialize(serializer),
@@ -82,17 +88,349 @@ impl Serialize for Primitive {
 			Primitive::U32(v) => v.serialize(serializer),
 			Primitive::U64(v) => v.serialize(serializer),
 			Primitive::U128(v) => v.serialize(serializer),
-			Primitive::U256(v) => v.serialize(serializer),
+			Primitive::U256(v) => serializer.serialize_str(&u256_to_decimal(v)),
 			Primitive::I8(v) => v.serialize(serializer),
 			Primitive::I16(v) => v.serialize(serializer),
 			Primitive::I32(v) => v.serialize(serializer),
 			Primitive::I64(v) => v.serialize(serializer),
 			Primitive::I128(v) => v.serialize(serializer),
-			Primitive::I256(v) => v.serialize(serializer),
+			Primitive::I256(v) => serializer.serialize_str(&i256_to_decimal(v)),
+			Primitive::F32(v) => v.serialize(serializer),
+			Primitive::F64(v) => v.serialize(serializer),
 		}
 	}
 }
 
+/// Options which tweak how a [`Value`] is serialized; see [`Value::serialize_with`]. The default options
+/// serialize values in the same way as the [`Serialize`] impl on [`Value`] does, except that longer byte
+/// sequences are serialized as hex strings (see [`SerializeOptions::bytes_as_hex_min_len`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerializeOptions {
+	/// If set, unnamed composites containing exactly 32 `U8` values (the shape that 32 byte account IDs
+	/// decode into) are serialized as SS58 addresses using this network prefix. Values don't know which
+	/// of them are account IDs, so this is applied heuristically, and is disabled by default.
+	pub ss58_prefix: Option<u16>,
+	/// Non-empty unnamed composites containing only `U8` values are serialized as `0x` prefixed hex strings
+	/// if they contain at least this many values, and as arrays of numbers otherwise. This defaults to 8.
+	/// Set it to `usize::MAX` to never serialize bytes as hex. SS58 addresses take precedence over this.
+	pub bytes_as_hex_min_len: usize,
+	/// How variants are represented. This defaults to [`VariantTagging::Adjacent`] with the tag `"name"` and
+	/// the content `"values"`, which is how the [`Serialize`] impl on [`Variant`] represents them.
+	pub variant_tagging: VariantTagging,
+}
+
+impl Default for SerializeOptions {
+	fn default() -> Self {
+		SerializeOptions {
+			ss58_prefix: None,
+			bytes_as_hex_min_len: 8,
+			variant_tagging: VariantTagging::Adjacent { tag: "name", content: "values" },
+		}
+	}
+}
+
+/// How a variant is serialized; see [`SerializeOptions::variant_tagging`]. These line up with the enum
+/// representations that serde supports, and the examples below show how a variant called `Foo` with a
+/// single field `a` would be serialized into JSON in each case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantTagging {
+	/// Serialize variants as a map from the variant name to its values, eg `{ "Foo": { "a": 1 } }`.
+	External,
+	/// Serialize variants as a map containing the variant name under `tag` alongside its named fields,
+	/// eg `{ "type": "Foo", "a": 1 }` given a tag of `"type"`. Variants with unnamed fields can't be
+	/// represented this way unless they have no fields, and so fail to serialize.
+	Internal {
+		/// The key to store the variant name under.
+		tag: &'static str,
+	},
+	/// Serialize variants as a map containing the variant name under `tag` and its values under `content`,
+	/// eg `{ "type": "Foo", "values": { "a": 1 } }` given a tag of `"type"` and content of `"values"`.
+	Adjacent {
+		/// The key to store the variant name under.
+		tag: &'static str,
+		/// The key to store the variant values under.
+		content: &'static str,
+	},
+}
+
+impl<T> Value<T> {
+	/// Hand back something which serializes this value according to the [`SerializeOptions`] provided.
+	pub fn serialize_with(&self, options: SerializeOptions) -> SerializeWith<'_, T> {
+		SerializeWith { value: self, options }
+	}
+	/// Serialize this value as JSON according to the [`SerializeOptions`] provided, writing it straight to
+	/// the writer given rather than building up an intermediate `String` or `serde_json::Value`. This keeps
+	/// memory usage down when serializing large values. Consider wrapping the writer in a `BufWriter` if it
+	/// isn't already buffered.
+	pub fn write_json<W: std::io::Write>(&self, options: SerializeOptions, writer: W) -> std::io::Result<()> {
+		serde_json::to_writer(writer, &self.serialize_with(options)).map_err(std::io::Error::from)
+	}
+	/// Serialize this value into a `serde_json::Value` according to the [`SerializeOptions`] provided, using
+	/// the type with the given [`TypeId`] to fill in anything that the value itself doesn't record. Unnamed
+	/// composites whose type has named fields are serialized as objects using those names, and variants are
+	/// labelled with the name given in the type (looking them up by position if the names don't match). Where
+	/// the value and type don't line up, that part of the value is serialized as it would be without a type.
+	pub fn to_json_with_type<Id: Into<TypeId>>(
+		&self,
+		ty: Id,
+		types: &PortableRegistry,
+		options: SerializeOptions,
+	) -> Result<serde_json::Value, serde_json::Error> {
+		let value = with_type_info(self, ty.into().id(), types);
+		serde_json::to_value(value.serialize_with(options))
+	}
+}
+
+// Copy a value, adding field and variant names from the type given where they are missing or don't match.
+fn with_type_info<T>(value: &Value<T>, ty_id: u32, types: &PortableRegistry) -> Value<()> {
+	let ty = match types.resolve(ty_id) {
+		Some(ty) => ty,
+		None => return without_type_info(value),
+	};
+
+	let def = match (ty.type_def(), &value.value) {
+		(TypeDef::Composite(inner), ValueDef::Composite(composite)) => {
+			ValueDef::Composite(composite_with_type_info(composite, inner.fields(), types))
+		}
+		(TypeDef::Variant(inner), ValueDef::Variant(variant)) => {
+			let ty_variant =
+				inner.variants().iter().enumerate().find(|(_, v)| v.name() == &variant.name).or_else(|| {
+					let index = variant.index? as usize;
+					inner.variants().get(index).map(|v| (index, v))
+				});
+			match ty_variant {
+				Some((index, ty_variant)) => ValueDef::Variant(Variant {
+					name: ty_variant.name().clone(),
+					values: composite_with_type_info(&variant.values, ty_variant.fields(), types),
+					index: Some(index as u32),
+				}),
+				None => return without_type_info(value),
+			}
+		}
+		(TypeDef::Sequence(inner), ValueDef::Composite(composite)) => {
+			ValueDef::Composite(items_with_type_info(composite, |_| inner.type_param().id(), types))
+		}
+		(TypeDef::Array(inner), ValueDef::Composite(composite)) => {
+			ValueDef::Composite(items_with_type_info(composite, |_| inner.type_param().id(), types))
+		}
+		(TypeDef::Tuple(inner), ValueDef::Composite(composite)) if composite.len() == inner.fields().len() => {
+			ValueDef::Composite(items_with_type_info(composite, |idx| inner.fields()[idx].id(), types))
+		}
+		(TypeDef::Compact(inner), _) => return with_type_info(value, inner.type_param().id(), types),
+		_ => return without_type_info(value),
+	};
+	Value { value: def, context: () }
+}
+
+// Line up the values in a composite with the fields of a composite type or variant, naming any unnamed
+// values if the fields are named.
+fn composite_with_type_info<T>(
+	composite: &Composite<T>,
+	fields: &[Field<PortableForm>],
+	types: &PortableRegistry,
+) -> Composite<()> {
+	if composite.len() != fields.len() {
+		return without_type_info_composite(composite);
+	}
+	let are_named = !fields.is_empty() && fields.iter().all(|f| f.name().is_some());
+	match composite {
+		Composite::Named(values) if are_named => Composite::Named(
+			values
+				.iter()
+				.map(|(name, value)| match fields.iter().find(|f| f.name() == Some(name)) {
+					Some(field) => (name.clone(), with_type_info(value, field.ty().id(), types)),
+					None => (name.clone(), without_type_info(value)),
+				})
+				.collect(),
+		),
+		Composite::Unnamed(values) if are_named => Composite::Named(
+			values
+				.iter()
+				.zip(fields)
+				.map(|(value, field)| {
+					let name = field.name().cloned().unwrap_or_default();
+					(name, with_type_info(value, field.ty().id(), types))
+				})
+				.collect(),
+		),
+		_ => items_with_type_info(composite, |idx| fields[idx].ty().id(), types),
+	}
+}
+
+// Copy the values in a composite, keeping it as it is, but using the type given by `ty_id` for each item.
+fn items_with_type_info<T>(
+	composite: &Composite<T>,
+	ty_id: impl Fn(usize) -> u32,
+	types: &PortableRegistry,
+) -> Composite<()> {
+	let item = |idx: usize, value: &Value<T>| with_type_info(value, ty_id(idx), types);
+	match composite {
+		Composite::Named(values) => Composite::Named(
+			values.iter().enumerate().map(|(idx, (name, value))| (name.clone(), item(idx, value))).collect(),
+		),
+		Composite::Unnamed(values) => {
+			Composite::Unnamed(values.iter().enumerate().map(|(idx, value)| item(idx, value)).collect())
+		}
+	}
+}
+
+fn without_type_info<T>(value: &Value<T>) -> Value<()> {
+	let def = match &value.value {
+		ValueDef::Composite(composite) => ValueDef::Composite(without_type_info_composite(composite)),
+		ValueDef::Variant(variant) => ValueDef::Variant(Variant {
+			name: variant.name.clone(),
+			values: without_type_info_composite(&variant.values),
+			index: variant.index,
+		}),
+		ValueDef::BitSequence(bits) => ValueDef::BitSequence(bits.clone()),
+		ValueDef::Primitive(prim) => ValueDef::Primitive(prim.clone()),
+	};
+	Value { value: def, context: () }
+}
+
+fn without_type_info_composite<T>(composite: &Composite<T>) -> Composite<()> {
+	match composite {
+		Composite::Named(values) => {
+			Composite::Named(values.iter().map(|(name, value)| (name.clone(), without_type_info(value))).collect())
+		}
+		Composite::Unnamed(values) => Composite::Unnamed(values.iter().map(without_type_info).collect()),
+	}
+}
+
+/// A [`Value`] which is serialized according to some [`SerializeOptions`]. See [`Value::serialize_with`].
+pub struct SerializeWith<'a, T> {
+	value: &'a Value<T>,
+	options: SerializeOptions,
+}
+
+impl<'a, T> Serialize for SerializeWith<'a, T> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		match &self.value.value {
+			ValueDef::Composite(val) => CompositeWith(val, self.options).serialize(serializer),
+			ValueDef::Variant(val) => VariantWith(val, self.options).serialize(serializer),
+			ValueDef::BitSequence(val) => val.serialize(serializer),
+			ValueDef::Primitive(val) => val.serialize(serializer),
+		}
+	}
+}
+
+struct VariantWith<'a, T>(&'a Variant<T>, SerializeOptions);
+
+impl<'a, T> Serialize for VariantWith<'a, T> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		let (variant, options) = (self.0, self.1);
+		let values = CompositeWith(&variant.values, options);
+		match options.variant_tagging {
+			VariantTagging::External => {
+				let mut map = serializer.serialize_map(Some(1))?;
+				map.serialize_entry(&variant.name, &values)?;
+				map.end()
+			}
+			VariantTagging::Internal { tag } => {
+				let fields: &[(String, Value<T>)] = match &variant.values {
+					Composite::Named(fields) => &fields[..],
+					Composite::Unnamed(vals) if vals.is_empty() => &[],
+					Composite::Unnamed(_) => {
+						return Err(S::Error::custom(format!(
+							"Cannot serialize the variant {} with unnamed fields as internally tagged",
+							variant.name
+						)))
+					}
+				};
+				let mut map = serializer.serialize_map(Some(fields.len() + 1))?;
+				map.serialize_entry(tag, &variant.name)?;
+				for (key, val) in fields {
+					map.serialize_entry(key, &val.serialize_with(options))?;
+				}
+				map.end()
+			}
+			VariantTagging::Adjacent { tag, content } => {
+				let mut map = serializer.serialize_map(Some(2))?;
+				map.serialize_entry(tag, &variant.name)?;
+				map.serialize_entry(content, &values)?;
+				map.end()
+			}
+		}
+	}
+}
+
+struct CompositeWith<'a, T>(&'a Composite<T>, SerializeOptions);
+
+impl<'a, T> Serialize for CompositeWith<'a, T> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: serde::Serializer,
+	{
+		let options = self.1;
+		match self.0 {
+			Composite::Named(vals) => {
+				let mut map = serializer.serialize_map(Some(vals.len()))?;
+				for (key, val) in vals {
+					map.serialize_entry(key, &val.serialize_with(options))?;
+				}
+				map.end()
+			}
+			Composite::Unnamed(vals) => {
+				if let (Some(prefix), Some(account_id)) = (options.ss58_prefix, as_account_id(vals)) {
+					let address = account_id.to_ss58check_with_version(Ss58AddressFormat::custom(prefix));
+					return serializer.serialize_str(&address);
+				}
+				if vals.len() >= options.bytes_as_hex_min_len {
+					if let Some(bytes) = as_bytes(vals) {
+						return serializer.serialize_str(&format!("0x{}", hex::encode(bytes)));
+					}
+				}
+				let mut seq = serializer.serialize_seq(Some(vals.len()))?;
+				for val in vals {
+					seq.serialize_element(&val.serialize_with(options))?;
+				}
+				seq.end()
+			}
+		}
+	}
+}
+
+// If the values are exactly 32 `U8`s, treat them as an account ID.
+fn as_account_id<T>(vals: &[Value<T>]) -> Option<AccountId32> {
+	let bytes: [u8; 32] = as_bytes(vals)?.try_into().ok()?;
+	Some(AccountId32::from(bytes))
+}
+
+// If there are some values, and they are all `U8`s, return them as bytes.
+fn as_bytes<T>(vals: &[Value<T>]) -> Option<Vec<u8>> {
+	if vals.is_empty() {
+		return None;
+	}
+	vals.iter()
+		.map(|val| match val.value {
+			ValueDef::Primitive(Primitive::U8(b)) => Some(b),
+			_ => None,
+		})
+		.collect()
+}
+
+// Interpret 32 little endian bytes as an unsigned integer and return it in decimal form.
+pub(super) fn u256_to_decimal(bytes: &[u8; 32]) -> String {
+	U256::from_little_endian(bytes).to_string()
+}
+
+// Interpret 32 little endian bytes as a two's complement signed integer and return it in decimal form.
+pub(super) fn i256_to_decimal(bytes: &[u8; 32]) -> String {
+	let n = U256::from_little_endian(bytes);
+	if bytes[31] & 0b1000_0000 != 0 {
+		// Negative, so the magnitude is given by inverting the bits and adding 1:
+		format!("-{}", !n + 1)
+	} else {
+		n.to_string()
+	}
+}
+
 impl<T> Serialize for Variant<T> {
 	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
 	where
@@ -130,6 +468,168 @@ mod test {
 		assert_value(Value::u64(1), json!(1));
 		assert_value(Value::bool(true), json!(true));
 		assert_value(Value::bool(false), json!(false));
+		assert_value(Value::f64(1.5), json!(1.5));
+	}
+
+	#[test]
+	fn serialize_256_bit_primitives_as_strings() {
+		// 2^128, which is too large for any of the other integer types:
+		let mut two_pow_128 = [0u8; 32];
+		two_pow_128[16] = 1;
+		assert_value(Value::primitive(Primitive::U256(two_pow_128)), json!("340282366920938463463374607431768211456"));
+		assert_value(Value::primitive(Primitive::I256(two_pow_128)), json!("340282366920938463463374607431768211456"));
+
+		assert_value(
+			Value::primitive(Primitive::U256([0xFF; 32])),
+			json!("115792089237316195423570985008687907853269984665640564039457584007913129639935"),
+		);
+		assert_value(Value::primitive(Primitive::I256([0xFF; 32])), json!("-1"));
+
+		let mut i256_min = [0u8; 32];
+		i256_min[31] = 0x80;
+		assert_value(
+			Value::primitive(Primitive::I256(i256_min)),
+			json!("-57896044618658097711785492504343953926634992332820282019728792003956564819968"),
+		);
+
+		// Smaller integers are unaffected:
+		assert_value(Value::u64(u64::MAX), json!(u64::MAX));
+	}
+
+	#[test]
+	fn serialize_account_ids_as_ss58() {
+		// Alice's public key, wrapped in a composite like an AccountId32 is:
+		let alice = hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d").unwrap();
+		let account_id =
+			Value::unnamed_composite(vec![Value::unnamed_composite(alice.iter().map(|b| Value::u8(*b)).collect())]);
+		let value = Value::variant("Transfer".into(), Composite::Named(vec![("dest".into(), account_id)]));
+
+		let options =
+			SerializeOptions { ss58_prefix: Some(42), bytes_as_hex_min_len: usize::MAX, ..Default::default() };
+		let json = serde_json::to_value(value.serialize_with(options)).expect("can serialize");
+		assert_eq!(
+			json,
+			json!({
+				"name": "Transfer",
+				"values": { "dest": ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"] }
+			})
+		);
+
+		// By default, the account ID is treated like any other bytes:
+		let json = serde_json::to_value(value.serialize_with(SerializeOptions::default())).expect("can serialize");
+		assert_eq!(
+			json,
+			json!({
+				"name": "Transfer",
+				"values": { "dest": [format!("0x{}", hex::encode(&alice))] }
+			})
+		);
+
+		// Anything other than exactly 32 bytes is left alone:
+		let value = Value::unnamed_composite(alice[..31].iter().map(|b| Value::u8(*b)).collect());
+		let json = serde_json::to_value(value.serialize_with(options)).expect("can serialize");
+		assert_eq!(json, serde_json::to_value(&value).expect("can serialize"));
+	}
+
+	#[test]
+	fn serialize_bytes_as_hex_above_threshold() {
+		let bytes = |n: u8| Value::unnamed_composite((0..n).map(Value::u8).collect());
+		let options = SerializeOptions::default();
+
+		// Just below the default threshold, bytes are left as an array:
+		let json = serde_json::to_value(bytes(7).serialize_with(options)).expect("can serialize");
+		assert_eq!(json, json!([0, 1, 2, 3, 4, 5, 6]));
+		// At the threshold, they become hex:
+		let json = serde_json::to_value(bytes(8).serialize_with(options)).expect("can serialize");
+		assert_eq!(json, json!("0x0001020304050607"));
+
+		// The threshold can be changed, and applies to nested values:
+		let options = SerializeOptions { bytes_as_hex_min_len: 2, ..Default::default() };
+		let value = Value::named_composite(vec![("a".into(), bytes(1)), ("b".into(), bytes(2))]);
+		let json = serde_json::to_value(value.serialize_with(options)).expect("can serialize");
+		assert_eq!(json, json!({ "a": [0], "b": "0x0001" }));
+
+		// Composites containing anything other than bytes aren't affected, and nor are empty ones:
+		let value = Value::unnamed_composite(vec![Value::u8(1), Value::u16(2)]);
+		let json = serde_json::to_value(value.serialize_with(options)).expect("can serialize");
+		assert_eq!(json, json!([1, 2]));
+		let options = SerializeOptions { bytes_as_hex_min_len: 0, ..Default::default() };
+		let json = serde_json::to_value(bytes(0).serialize_with(options)).expect("can serialize");
+		assert_eq!(json, json!([]));
+	}
+
+	#[test]
+	fn serialize_variants_with_each_tagging() {
+		let value = Value::variant(
+			"Foo".into(),
+			Composite::Named(vec![("a".into(), Value::u8(1)), ("b".into(), Value::bool(true))]),
+		);
+		let to_json = |value: &Value<()>, variant_tagging| {
+			let options = SerializeOptions { variant_tagging, ..Default::default() };
+			serde_json::to_value(value.serialize_with(options))
+		};
+
+		// The default matches the Serialize impl:
+		let json = serde_json::to_value(value.serialize_with(SerializeOptions::default())).expect("can serialize");
+		assert_eq!(json, serde_json::to_value(&value).expect("can serialize"));
+
+		let json = to_json(&value, VariantTagging::External).expect("can serialize");
+		assert_eq!(json, json!({ "Foo": { "a": 1, "b": true } }));
+
+		let json = to_json(&value, VariantTagging::Internal { tag: "type" }).expect("can serialize");
+		assert_eq!(json, json!({ "type": "Foo", "a": 1, "b": true }));
+
+		let json = to_json(&value, VariantTagging::Adjacent { tag: "type", content: "values" }).expect("can serialize");
+		assert_eq!(json, json!({ "type": "Foo", "values": { "a": 1, "b": true } }));
+
+		// Unnamed fields can't be internally tagged, unless there aren't any:
+		let tagging = VariantTagging::Internal { tag: "type" };
+		let value = Value::variant("Bar".into(), Composite::Unnamed(vec![Value::u8(1)]));
+		assert!(to_json(&value, tagging).is_err());
+		assert_eq!(to_json(&value, VariantTagging::External).expect("can serialize"), json!({ "Bar": [1] }));
+		let value = Value::variant("Bar".into(), Composite::Unnamed(vec![]));
+		assert_eq!(to_json(&value, tagging).expect("can serialize"), json!({ "type": "Bar" }));
+
+		// Nested variants are tagged in the same way:
+		let value = Value::variant(
+			"Outer".into(),
+			Composite::Named(vec![("inner".into(), Value::variant("Inner".into(), Composite::Named(vec![])))]),
+		);
+		let json = to_json(&value, tagging).expect("can serialize");
+		assert_eq!(json, json!({ "type": "Outer", "inner": { "type": "Inner" } }));
+	}
+
+	#[test]
+	fn write_json_to_writer() {
+		let value = Value::variant(
+			"Remark".into(),
+			Composite::Named(vec![
+				("remark".into(), Value::unnamed_composite((0..10).map(Value::u8).collect())),
+				("big".into(), Value::primitive(Primitive::U256([0xFF; 32]))),
+			]),
+		);
+
+		let mut out = Vec::new();
+		value.write_json(SerializeOptions::default(), &mut out).expect("can write JSON");
+		let json: serde_json::Value = serde_json::from_slice(&out).expect("valid JSON");
+		assert_eq!(json, serde_json::to_value(value.serialize_with(SerializeOptions::default())).unwrap());
+		assert_eq!(
+			json,
+			json!({
+				"name": "Remark",
+				"values": {
+					"remark": "0x00010203040506070809",
+					"big": "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+				}
+			})
+		);
+
+		// Options are honoured:
+		let mut out = Vec::new();
+		let options = SerializeOptions { bytes_as_hex_min_len: usize::MAX, ..Default::default() };
+		value.write_json(options, &mut out).expect("can write JSON");
+		let json: serde_json::Value = serde_json::from_slice(&out).expect("valid JSON");
+		assert_eq!(json["values"]["remark"], json!([0, 1, 2, 3, 4, 5, 6, 7, 8, 9]));
 	}
 
 	#[test]
@@ -187,4 +687,67 @@ mod test {
 			}),
 		)
 	}
+
+	#[test]
+	fn serialize_with_type_recovers_names() {
+		#[allow(dead_code)]
+		#[derive(scale_info::TypeInfo)]
+		struct Transfer {
+			dest: (u8, u8),
+			amount: u64,
+			kind: Kind,
+		}
+		#[allow(dead_code)]
+		#[derive(scale_info::TypeInfo)]
+		enum Kind {
+			#[codec(index = 3)]
+			KeepAlive {
+				fee: u32,
+			},
+			Empty,
+		}
+
+		let mut registry = scale_info::Registry::new();
+		let id: TypeId = registry.register_type(&scale_info::MetaType::new::<Transfer>()).into();
+		let kind_id: TypeId = registry.register_type(&scale_info::MetaType::new::<Kind>()).into();
+		let types: PortableRegistry = registry.into();
+
+		// A value which has lost its field names, and whose variant is only known by its position:
+		let kind = Value::with_context(
+			ValueDef::Variant(Variant {
+				name: "Unknown".into(),
+				values: Composite::Unnamed(vec![Value::u32(5)]),
+				index: Some(0),
+			}),
+			(),
+		);
+		let value = Value::unnamed_composite(vec![
+			Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)]),
+			Value::u64(100),
+			kind,
+		]);
+		let options = SerializeOptions::default();
+
+		let json = serde_json::to_value(value.serialize_with(options)).expect("can serialize");
+		assert_eq!(json, json!([[1, 2], 100, { "name": "Unknown", "values": [5] }]));
+
+		let json = value.to_json_with_type(id, &types, options).expect("can serialize");
+		assert_eq!(
+			json,
+			json!({ "dest": [1, 2], "amount": 100, "kind": { "name": "KeepAlive", "values": { "fee": 5 } } })
+		);
+
+		// Variants without fields keep their (empty) values unnamed:
+		let empty = Value::with_context(
+			ValueDef::Variant(Variant { name: "Unknown".into(), values: Composite::Unnamed(vec![]), index: Some(1) }),
+			(),
+		);
+		let json = empty.to_json_with_type(kind_id, &types, options).expect("can serialize");
+		assert_eq!(json, json!({ "name": "Empty", "values": [] }));
+
+		// Where the value and type disagree, the value is serialized as it would be without the type:
+		let value = Value::unnamed_composite(vec![Value::u8(1), Value::bool(true)]);
+		let json = value.to_json_with_type(id, &types, options).expect("can serialize");
+		assert_eq!(json, json!([1, true]));
+	}
 }
@@ -0,0 +1,198 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::serialize::{i256_to_decimal, u256_to_decimal};
+use super::{BitSequence, Composite, Primitive, Value, ValueDef};
+
+impl<T> Value<T> {
+	/// Render this value in the textual format used by the `scale-value` crate, so that it can be
+	/// passed between tools which understand that format. Named composites look like `{ a: 1, b: true }`,
+	/// unnamed composites like `(1, true)`, variants like `Foo { a: 1 }` or `Foo (1, true)`, and bit
+	/// sequences like `<0101>`. Strings and chars are quoted and escaped, and field and variant names which
+	/// aren't valid identifiers (or which would be read back as some other value, like `true`) are quoted too.
+	pub fn to_scale_value_string(&self) -> String {
+		let mut out = String::new();
+		write_value_def(&self.value, &mut out);
+		out
+	}
+}
+
+fn write_value_def<T>(value: &ValueDef<T>, out: &mut String) {
+	match value {
+		ValueDef::Composite(composite) => write_composite(composite, out),
+		ValueDef::Variant(variant) => {
+			// Names which would otherwise be parsed as some other value need quoting too:
+			if is_ident(&variant.name) && !matches!(&*variant.name, "true" | "false" | "NaN" | "inf") {
+				out.push_str(&variant.name);
+			} else {
+				write_quoted(&variant.name, '"', out);
+			}
+			out.push(' ');
+			write_composite(&variant.values, out);
+		}
+		ValueDef::BitSequence(bits) => write_bits(bits, out),
+		ValueDef::Primitive(prim) => write_primitive(prim, out),
+	}
+}
+
+fn write_composite<T>(composite: &Composite<T>, out: &mut String) {
+	match composite {
+		Composite::Named(values) if values.is_empty() => out.push_str("{}"),
+		Composite::Named(values) => {
+			out.push_str("{ ");
+			for (idx, (name, value)) in values.iter().enumerate() {
+				if idx != 0 {
+					out.push_str(", ");
+				}
+				if is_ident(name) {
+					out.push_str(name);
+				} else {
+					write_quoted(name, '"', out);
+				}
+				out.push_str(": ");
+				write_value_def(&value.value, out);
+			}
+			out.push_str(" }");
+		}
+		Composite::Unnamed(values) => {
+			out.push('(');
+			for (idx, value) in values.iter().enumerate() {
+				if idx != 0 {
+					out.push_str(", ");
+				}
+				write_value_def(&value.value, out);
+			}
+			out.push(')');
+		}
+	}
+}
+
+fn write_bits(bits: &BitSequence, out: &mut String) {
+	out.push('<');
+	out.extend(bits.iter().map(|bit| if *bit { '1' } else { '0' }));
+	out.push('>');
+}
+
+fn write_primitive(prim: &Primitive, out: &mut String) {
+	let s = match prim {
+		Primitive::Bool(b) => b.to_string(),
+		Primitive::Char(c) => return write_quoted(&c.to_string(), '\'', out),
+		Primitive::Str(s) => return write_quoted(s, '"', out),
+		Primitive::U8(n) => n.to_string(),
+		Primitive::U16(n) => n.to_string(),
+		Primitive::U32(n) => n.to_string(),
+		Primitive::U64(n) => n.to_string(),
+		Primitive::U128(n) => n.to_string(),
+		Primitive::U256(bytes) => u256_to_decimal(bytes),
+		Primitive::I8(n) => n.to_string(),
+		Primitive::I16(n) => n.to_string(),
+		Primitive::I32(n) => n.to_string(),
+		Primitive::I64(n) => n.to_string(),
+		Primitive::I128(n) => n.to_string(),
+		// The debug output always includes a decimal point or exponent, so floats can be told apart from integers:
+		Primitive::F32(n) => format!("{:?}", n),
+		Primitive::F64(n) => format!("{:?}", n),
+		Primitive::I256(bytes) => i256_to_decimal(bytes),
+	};
+	out.push_str(&s);
+}
+
+fn write_quoted(s: &str, quote: char, out: &mut String) {
+	out.push(quote);
+	for c in s.chars() {
+		match c {
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			'\0' => out.push_str("\\0"),
+			c if c == quote => {
+				out.push('\\');
+				out.push(c);
+			}
+			c => out.push(c),
+		}
+	}
+	out.push(quote);
+}
+
+fn is_ident(s: &str) -> bool {
+	let mut chars = s.chars();
+	match chars.next() {
+		Some(c) if c.is_alphabetic() || c == '_' => chars.all(|c| c.is_alphanumeric() || c == '_'),
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+	use bitvec::{bitvec, order::Lsb0};
+
+	#[test]
+	fn stringify_primitives() {
+		assert_eq!(Value::bool(true).to_scale_value_string(), "true");
+		assert_eq!(Value::u128(123).to_scale_value_string(), "123");
+		assert_eq!(Value::i8(-5).to_scale_value_string(), "-5");
+		assert_eq!(Value::char('a').to_scale_value_string(), "'a'");
+		assert_eq!(Value::char('\'').to_scale_value_string(), "'\\''");
+		assert_eq!(Value::str("say \"hi\"\n".into()).to_scale_value_string(), "\"say \\\"hi\\\"\\n\"");
+
+		let mut big = [0u8; 32];
+		big[16] = 1;
+		assert_eq!(
+			Value::primitive(Primitive::U256(big)).to_scale_value_string(),
+			"340282366920938463463374607431768211456"
+		);
+		assert_eq!(Value::primitive(Primitive::I256([0xFF; 32])).to_scale_value_string(), "-1");
+	}
+
+	#[test]
+	fn stringify_composites_and_variants() {
+		let val = Value::variant(
+			"Transfer".into(),
+			Composite::Named(vec![
+				("dest".into(), Value::unnamed_composite(vec![Value::u8(1), Value::u8(2)])),
+				("value".into(), Value::u128(123)),
+			]),
+		);
+		assert_eq!(val.to_scale_value_string(), "Transfer { dest: (1, 2), value: 123 }");
+
+		let val = Value::variant("Foo".into(), Composite::Unnamed(vec![Value::bool(true)]));
+		assert_eq!(val.to_scale_value_string(), "Foo (true)");
+
+		assert_eq!(Value::named_composite(vec![]).to_scale_value_string(), "{}");
+		assert_eq!(Value::unnamed_composite(vec![]).to_scale_value_string(), "()");
+
+		// Names which aren't identifiers are quoted:
+		let val = Value::named_composite(vec![("a b".into(), Value::u8(1)), ("_c1".into(), Value::u8(2))]);
+		assert_eq!(val.to_scale_value_string(), "{ \"a b\": 1, _c1: 2 }");
+
+		// As are variant names which aren't identifiers or would be read back as something else:
+		let val = Value::variant("a b".into(), Composite::Unnamed(vec![]));
+		assert_eq!(val.to_scale_value_string(), "\"a b\" ()");
+		let val = Value::variant("true".into(), Composite::Named(vec![]));
+		assert_eq!(val.to_scale_value_string(), "\"true\" {}");
+	}
+
+	#[test]
+	fn stringify_bit_sequences() {
+		let val = Value::bit_sequence(bitvec![Lsb0, u8; 0, 1, 1, 0]);
+		assert_eq!(val.to_scale_value_string(), "<0110>");
+		assert_eq!(Value::bit_sequence(BitSequence::new()).to_scale_value_string(), "<>");
+	}
+}
@@ -0,0 +1,125 @@
+// Copyright 2019-2021 Parity Technologies (UK) Ltd.
+// This file is part of substrate-desub.
+//
+// substrate-desub is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// substrate-desub is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with substrate-desub.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{DeserializeError, Primitive, Value, ValueDef};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Deserializer};
+use std::convert::TryFrom;
+
+/// A [`Value`] which is expected to hold a timestamp, as stored by (for example) `pallet_timestamp`.
+///
+/// The wrapped value must be an integer primitive counting the number of **milliseconds** since the
+/// Unix epoch (1970-01-01T00:00:00 UTC). This can be used as a field in some struct that is being
+/// deserialized from a [`Value`], and [`UtcMillis::to_datetime()`] will then convert it into a
+/// [`chrono::DateTime`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtcMillis<T>(pub Value<T>);
+
+impl<T> UtcMillis<T> {
+	/// Attempt to convert the wrapped millisecond value into a UTC [`DateTime`]. This will fail if
+	/// the value is not an integer primitive, or if it is out of the range that [`DateTime`] supports.
+	pub fn to_datetime(&self) -> Result<DateTime<Utc>, DeserializeError> {
+		let millis = match &self.0.value {
+			ValueDef::Primitive(prim) => prim_to_i64(prim)?,
+			_ => return Err(DeserializeError::from_str("UtcMillis expects an integer primitive value")),
+		};
+		Utc.timestamp_millis_opt(millis)
+			.single()
+			.ok_or_else(|| DeserializeError::from_string(format!("{} milliseconds is not a valid timestamp", millis)))
+	}
+}
+
+impl<'de> Deserialize<'de> for UtcMillis<()> {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Value::deserialize(deserializer).map(UtcMillis)
+	}
+}
+
+fn prim_to_i64(prim: &Primitive) -> Result<i64, DeserializeError> {
+	let out_of_range = || DeserializeError::from_str("Timestamp does not fit into an i64 number of milliseconds");
+	match *prim {
+		Primitive::U8(v) => Ok(v.into()),
+		Primitive::U16(v) => Ok(v.into()),
+		Primitive::U32(v) => Ok(v.into()),
+		Primitive::U64(v) => i64::try_from(v).map_err(|_| out_of_range()),
+		Primitive::U128(v) => i64::try_from(v).map_err(|_| out_of_range()),
+		Primitive::I8(v) => Ok(v.into()),
+		Primitive::I16(v) => Ok(v.into()),
+		Primitive::I32(v) => Ok(v.into()),
+		Primitive::I64(v) => Ok(v),
+		Primitive::I128(v) => i64::try_from(v).map_err(|_| out_of_range()),
+		_ => Err(DeserializeError::from_str("UtcMillis expects an integer primitive value")),
+	}
+}
+
+#[cfg(test)]
+mod test {
+
+	use super::*;
+
+	#[test]
+	fn millis_to_datetime() {
+		let millis = UtcMillis(Value::u64(1_600_000_000_123));
+		assert_eq!(millis.to_datetime(), Ok(Utc.ymd(2020, 9, 13).and_hms_milli(12, 26, 40, 123)));
+	}
+
+	#[test]
+	fn non_integer_is_not_a_datetime() {
+		UtcMillis(Value::bool(true)).to_datetime().expect_err("bool is not a timestamp");
+		UtcMillis(Value::u128(u128::MAX)).to_datetime().expect_err("out of range");
+	}
+
+	#[test]
+	fn unix_millis_to_datetime() {
+		let val = Value::named_composite(vec![("now".into(), Value::u64(1_600_000_000_123))]);
+		let millis = val.at(&["now".into()]).ok().and_then(Value::as_unix_millis).expect("is a timestamp");
+		let datetime = Utc.timestamp_millis_opt(millis).single().expect("valid timestamp");
+		assert_eq!(datetime, Utc.ymd(2020, 9, 13).and_hms_milli(12, 26, 40, 123));
+	}
+
+	#[test]
+	fn deserialize_utc_millis() {
+		#[derive(Deserialize)]
+		struct Block {
+			now: UtcMillis<()>,
+		}
+
+		let val = Value::named_composite(vec![("now".into(), Value::u64(0))]);
+		let block: Block = crate::value::from_value(val).expect("can deserialize");
+		assert_eq!(block.now.to_datetime(), Ok(Utc.ymd(1970, 1, 1).and_hms(0, 0, 0)));
+	}
+
+	#[test]
+	fn deserialize_with_chrono_ts_milliseconds() {
+		#[derive(Deserialize)]
+		struct Block {
+			#[serde(with = "chrono::serde::ts_milliseconds")]
+			now: DateTime<Utc>,
+		}
+
+		// Unsigned timestamps are handed to the adapter (which asks for an i64) as is:
+		let val = Value::named_composite(vec![("now".into(), Value::u64(1_600_000_000_123))]);
+		let block: Block = crate::value::from_value(val).expect("can deserialize");
+		assert_eq!(block.now, Utc.ymd(2020, 9, 13).and_hms_milli(12, 26, 40, 123));
+
+		let val = Value::named_composite(vec![("now".into(), Value::i64(-1_000))]);
+		let block: Block = crate::value::from_value(val).expect("can deserialize");
+		assert_eq!(block.now, Utc.ymd(1969, 12, 31).and_hms(23, 59, 59));
+	}
+}
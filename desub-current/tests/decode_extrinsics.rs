@@ -179,7 +179,8 @@ fn technical_committee_execute_unsigned() {
 	assert!(matches!(&ext.call_data.arguments[0],
 		Value { value: ValueDef::Variant(value::Variant {
 			name,
-			values: value::Composite::Unnamed(args)
+			values: value::Composite::Unnamed(args),
+			..
 		}), .. }
 		if &*name == "Balances"
 		&& matches!(&args[0], Value { value: ValueDef::Variant(value::Variant { name, ..}), .. } if &*name == "transfer")
@@ -272,7 +273,15 @@ fn can_decode_signer_payload() {
 		),
 		(
 			"CheckMortality",
-			singleton_value(Value::variant("Mortal185".to_string(), value::Composite::Unnamed(vec![Value::u8(52)]))),
+			singleton_value(Value::with_context(
+				ValueDef::Variant(value::Variant {
+					name: "Mortal185".to_string(),
+					values: value::Composite::Unnamed(vec![Value::u8(52)]),
+					// Decoded variants know their index:
+					index: Some(185),
+				}),
+				(),
+			)),
 			hash_value(to_bytes("0x1c81d421f68281950ad2901291603b5e49fc5c872f129e75433f4b55f07ca072")),
 		),
 		("CheckNonce", singleton_value(Value::u32(0)), empty_value()),